@@ -0,0 +1,117 @@
+//!
+//! Throughput of the simulation at scale. Run with
+//!
+//!     cargo bench --features bench -- --baseline main
+//!
+//! to compare against the baseline stored in `benches/baseline`, and with `--save-baseline main`
+//! to replace it once a change is meant to be the new reference. The baseline was recorded on one
+//! machine, so on another one record it first before a change, and compare after.
+//!
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use economy::simulation::{bench, scenario::Scenario, world::World};
+use std::path::Path;
+
+fn market_tick(c: &mut Criterion) {
+    c.bench_function("market_tick_100k_queued", |b| {
+        b.iter_batched(
+            || bench::create_market_with_queue(100_000),
+            |mut market| {
+                bench::tick_market(&mut market);
+                market
+            },
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+fn listings_of_kind(c: &mut Criterion) {
+    let item_kind = bench::get_book_item_kind();
+    let mut group = c.benchmark_group("get_listings_of_kind");
+    for listing_count in [1_000, 100_000] {
+        let market = bench::create_market_with_book(listing_count);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(listing_count),
+            &market,
+            |b, market| b.iter(|| bench::sum_listings_of_kind(market, item_kind)),
+        );
+    }
+    group.finish();
+}
+
+fn account_balance(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_balance");
+    for transaction_count in [1_000, 100_000] {
+        let (bank, account) = bench::create_account_with_history(transaction_count);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(transaction_count),
+            &bank,
+            |b, bank| b.iter(|| bench::get_balance(bank, account)),
+        );
+    }
+    group.finish();
+}
+
+///
+/// Ticks a world of the default scenario scaled up to the given number of actors, after letting
+/// it settle for a few ticks. Every iteration ticks a copy of the settled world, so that all of
+/// them measure the same tick.
+///
+fn world_tick(c: &mut Criterion) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/default.toml");
+    let scenario = Scenario::load(&path).unwrap();
+
+    let mut group = c.benchmark_group("world_tick");
+    group.sample_size(10);
+    for actor_count in [1_000, 10_000, 100_000] {
+        let scaled = scale_actors(&scenario, actor_count);
+        let mut settled = World::new(&scaled);
+        for _ in 0..10 {
+            settled.tick();
+        }
+        group.bench_function(BenchmarkId::from_parameter(actor_count), |b| {
+            b.iter_batched(
+                || settled.clone(),
+                |mut world| {
+                    world.tick();
+                    world
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+///
+/// Get the scenario with its actors scaled to exactly the given total, keeping their proportions.
+/// Counts are rounded down, and what is left over goes to the actors rounded down the most.
+///
+fn scale_actors(scenario: &Scenario, actor_count: usize) -> Scenario {
+    let total = scenario
+        .actors
+        .iter()
+        .map(|actor| actor.count)
+        .sum::<usize>();
+    let mut scaled = scenario.clone();
+    let mut remainders = Vec::new();
+    for (i, actor) in scaled.actors.iter_mut().enumerate() {
+        let share = actor.count * actor_count;
+        actor.count = share / total;
+        remainders.push((share % total, i));
+    }
+    let left_over = actor_count - scaled.actors.iter().map(|actor| actor.count).sum::<usize>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, i) in remainders.into_iter().take(left_over) {
+        scaled.actors[i].count += 1;
+    }
+    scaled
+}
+
+criterion_group!(
+    benches,
+    market_tick,
+    listings_of_kind,
+    account_balance,
+    world_tick
+);
+criterion_main!(benches);
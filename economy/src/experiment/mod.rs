@@ -0,0 +1,128 @@
+use self::sweep::{ParameterSet, Sweep};
+use crate::simulation::{metrics::Metrics, run_headless, scenario::Scenario};
+use rayon::prelude::*;
+use std::{collections::BTreeSet, io::Write};
+
+pub mod sweep;
+
+///
+/// Parameters and final metrics of a single run within a sweep.
+///
+pub struct RunSummary {
+    pub run: usize,
+    pub parameters: ParameterSet,
+    pub metrics: Metrics,
+}
+
+///
+/// Runs the base scenario once for every parameter set of the sweep. Runs are independent and
+/// executed in parallel on the global rayon pool. Summaries are returned in run order.
+///
+pub fn run_sweep(base: &Scenario, sweep: &Sweep) -> Result<Vec<RunSummary>, String> {
+    // Build every scenario up front so invalid parameters fail before any run starts
+    let runs = sweep
+        .get_parameter_sets()?
+        .into_iter()
+        .map(|parameters| {
+            let mut scenario = base.clone();
+            for (name, value) in parameters.iter() {
+                scenario.set_parameter(name, *value)?;
+            }
+            scenario.validate()?;
+            Ok((parameters, scenario))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(runs
+        .into_par_iter()
+        .enumerate()
+        .map(|(run, (parameters, scenario))| RunSummary {
+            run,
+            parameters,
+            metrics: run_headless(&scenario, sweep.ticks),
+        })
+        .collect())
+}
+
+///
+/// Writes the summaries as a CSV table with one row per run. Item kinds which appear in any run
+/// get a held and a listed column each.
+///
+pub fn write_summary(writer: &mut impl Write, summaries: &[RunSummary]) -> std::io::Result<()> {
+    let item_kinds = summaries
+        .iter()
+        .flat_map(|summary| summary.metrics.get_item_kinds())
+        .collect::<BTreeSet<_>>();
+
+    // Header
+    let mut header = vec!["run".to_string()];
+    if let Some(summary) = summaries.first() {
+        header.extend(summary.parameters.iter().map(|(name, _)| name.clone()));
+    }
+    header.extend(Metrics::get_csv_columns(&item_kinds));
+    writeln!(writer, "{}", header.join(","))?;
+
+    // Rows
+    for summary in summaries.iter() {
+        let mut row = vec![summary.run.to_string()];
+        row.extend(
+            summary
+                .parameters
+                .iter()
+                .map(|(_, value)| value.to_string()),
+        );
+        row.extend(summary.metrics.get_csv_values(&item_kinds));
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sweep::SweepParameter, *};
+    use crate::experiment::sweep::SweepMode;
+
+    #[test]
+    fn run_sweep_grid() {
+        let sweep = Sweep {
+            mode: SweepMode::Grid,
+            ticks: 20,
+            samples: 0,
+            seed: 0,
+            parameters: vec![SweepParameter {
+                name: "tasks.packer.work_points".to_string(),
+                values: vec![10.0, 20.0],
+                min: None,
+                max: None,
+            }],
+        };
+        let summaries = run_sweep(&Scenario::default(), &sweep).unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[1].run, 1);
+        assert_eq!(summaries[1].parameters[0].1, 20.0);
+        assert_eq!(summaries[1].metrics.tick, 20);
+
+        let mut output = Vec::new();
+        write_summary(&mut output, &summaries).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.starts_with("run,tasks.packer.work_points,ticks,"));
+    }
+
+    #[test]
+    fn run_sweep_unknown_parameter() {
+        let sweep = Sweep {
+            mode: SweepMode::Grid,
+            ticks: 20,
+            samples: 0,
+            seed: 0,
+            parameters: vec![SweepParameter {
+                name: "tasks.miner.work_points".to_string(),
+                values: vec![10.0],
+                min: None,
+                max: None,
+            }],
+        };
+        assert!(run_sweep(&Scenario::default(), &sweep).is_err());
+    }
+}
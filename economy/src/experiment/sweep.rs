@@ -0,0 +1,195 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Deserialize;
+
+///
+/// Describes which scenario parameters to vary and how. A grid sweep runs every combination of
+/// the listed values, a random sweep draws a fixed number of samples.
+///
+#[derive(Clone, Debug, Deserialize)]
+pub struct Sweep {
+    #[serde(default)]
+    pub mode: SweepMode,
+    pub ticks: u64,
+    #[serde(default)]
+    pub samples: usize,
+    #[serde(default)]
+    pub seed: u64,
+    pub parameters: Vec<SweepParameter>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SweepMode {
+    Grid,
+    Random,
+}
+impl Default for SweepMode {
+    fn default() -> Self {
+        SweepMode::Grid
+    }
+}
+
+///
+/// A named scenario parameter, see `Scenario::set_parameter`. Grid sweeps use `values`. Random
+/// sweeps pick from `values` if given, otherwise uniformly from `min..=max`.
+///
+#[derive(Clone, Debug, Deserialize)]
+pub struct SweepParameter {
+    pub name: String,
+    #[serde(default)]
+    pub values: Vec<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// One combination of parameter values, in the order the parameters are declared.
+pub type ParameterSet = Vec<(String, f64)>;
+
+impl Sweep {
+    ///
+    /// Parses a sweep definition from TOML.
+    ///
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|error| format!("Could not parse sweep: {}", error))
+    }
+    ///
+    /// Expands the sweep into the parameter sets of each run.
+    ///
+    pub fn get_parameter_sets(&self) -> Result<Vec<ParameterSet>, String> {
+        match self.mode {
+            SweepMode::Grid => self.get_grid_parameter_sets(),
+            SweepMode::Random => self.get_random_parameter_sets(),
+        }
+    }
+    fn get_grid_parameter_sets(&self) -> Result<Vec<ParameterSet>, String> {
+        let mut parameter_sets: Vec<ParameterSet> = vec![Vec::new()];
+        for parameter in self.parameters.iter() {
+            if parameter.values.is_empty() {
+                return Err(format!(
+                    "Parameter '{}' needs a list of values in a grid sweep",
+                    parameter.name
+                ));
+            }
+            parameter_sets = parameter_sets
+                .into_iter()
+                .flat_map(|parameter_set| {
+                    parameter.values.iter().map(move |value| {
+                        let mut parameter_set = parameter_set.clone();
+                        parameter_set.push((parameter.name.clone(), *value));
+                        parameter_set
+                    })
+                })
+                .collect();
+        }
+        Ok(parameter_sets)
+    }
+    fn get_random_parameter_sets(&self) -> Result<Vec<ParameterSet>, String> {
+        if self.samples == 0 {
+            return Err("A random sweep needs a number of samples".to_string());
+        }
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut parameter_sets = Vec::new();
+        for _ in 0..self.samples {
+            let mut parameter_set = ParameterSet::new();
+            for parameter in self.parameters.iter() {
+                let value = match (
+                    parameter.values.choose(&mut rng),
+                    parameter.min,
+                    parameter.max,
+                ) {
+                    (Some(value), _, _) => *value,
+                    (None, Some(min), Some(max)) if min <= max => rng.gen_range(min..=max),
+                    _ => {
+                        return Err(format!(
+                            "Parameter '{}' needs values or a valid min and max",
+                            parameter.name
+                        ))
+                    }
+                };
+                parameter_set.push((parameter.name.clone(), value));
+            }
+            parameter_sets.push(parameter_set);
+        }
+        Ok(parameter_sets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(name: &str, values: Vec<f64>) -> SweepParameter {
+        SweepParameter {
+            name: name.to_string(),
+            values,
+            min: None,
+            max: None,
+        }
+    }
+
+    #[test]
+    fn grid() {
+        let sweep = Sweep {
+            mode: SweepMode::Grid,
+            ticks: 10,
+            samples: 0,
+            seed: 0,
+            parameters: vec![
+                parameter("tasks.packer.work_points", vec![10.0, 20.0]),
+                parameter("tasks.packer.safety_factor", vec![1.5, 2.0, 3.0]),
+            ],
+        };
+        let parameter_sets = sweep.get_parameter_sets().unwrap();
+        assert_eq!(parameter_sets.len(), 6);
+        assert_eq!(parameter_sets[0][0].1, 10.0);
+        assert_eq!(parameter_sets[0][1].1, 1.5);
+        assert_eq!(parameter_sets[5][0].1, 20.0);
+        assert_eq!(parameter_sets[5][1].1, 3.0);
+    }
+
+    #[test]
+    fn grid_without_values() {
+        let sweep = Sweep {
+            mode: SweepMode::Grid,
+            ticks: 10,
+            samples: 0,
+            seed: 0,
+            parameters: vec![parameter("seed", vec![])],
+        };
+        assert!(sweep.get_parameter_sets().is_err());
+    }
+
+    #[test]
+    fn random() {
+        let mut range = parameter("tasks.packer.safety_factor", vec![]);
+        range.min = Some(1.5);
+        range.max = Some(3.0);
+        let sweep = Sweep {
+            mode: SweepMode::Random,
+            ticks: 10,
+            samples: 25,
+            seed: 3,
+            parameters: vec![parameter("seed", vec![1.0, 2.0]), range],
+        };
+        let parameter_sets = sweep.get_parameter_sets().unwrap();
+        assert_eq!(parameter_sets.len(), 25);
+        for parameter_set in parameter_sets.iter() {
+            assert!(parameter_set[0].1 == 1.0 || parameter_set[0].1 == 2.0);
+            assert!((1.5..=3.0).contains(&parameter_set[1].1));
+        }
+        // Same seed gives the same samples
+        assert_eq!(sweep.get_parameter_sets().unwrap(), parameter_sets);
+    }
+
+    #[test]
+    fn random_without_samples() {
+        let sweep = Sweep {
+            mode: SweepMode::Random,
+            ticks: 10,
+            samples: 0,
+            seed: 0,
+            parameters: vec![parameter("seed", vec![1.0, 2.0])],
+        };
+        assert!(sweep.get_parameter_sets().is_err());
+    }
+}
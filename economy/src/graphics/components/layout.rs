@@ -0,0 +1,156 @@
+///
+/// Area on screen, from the top left corner.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Rect {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+impl Rect {
+    pub(crate) fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    pub(crate) fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+    ///
+    /// The rect with the padding taken off every side.
+    ///
+    pub(crate) fn shrink(&self, padding: f32) -> Rect {
+        Rect {
+            x: self.x + padding,
+            y: self.y + padding,
+            width: (self.width - padding * 2.0).max(0.0),
+            height: (self.height - padding * 2.0).max(0.0),
+        }
+    }
+    pub(crate) fn get_center(&self) -> (f32, f32) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    Row,
+    Column,
+}
+
+///
+/// Hands out rects one after another along a row or down a column of an area, so that layers do
+/// not need to keep track of pixel offsets themselves.
+///
+pub(crate) struct Layout {
+    area: Rect,
+    direction: Direction,
+    spacing: f32,
+    cursor: f32,
+}
+impl Layout {
+    ///
+    /// Rects stacked top to bottom, each as wide as the area.
+    ///
+    pub(crate) fn column(area: Rect) -> Self {
+        Self {
+            area,
+            direction: Direction::Column,
+            spacing: 6.0,
+            cursor: 0.0,
+        }
+    }
+    ///
+    /// Rects placed left to right, each as tall as the area.
+    ///
+    pub(crate) fn row(area: Rect) -> Self {
+        Self {
+            area,
+            direction: Direction::Row,
+            spacing: 6.0,
+            cursor: 0.0,
+        }
+    }
+    pub(crate) fn with_padding(mut self, padding: f32) -> Self {
+        self.area = self.area.shrink(padding);
+        self
+    }
+    pub(crate) fn with_spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+    ///
+    /// Takes the next rect of the given height in a column, or width in a row.
+    ///
+    pub(crate) fn next(&mut self, size: f32) -> Rect {
+        let rect = match self.direction {
+            Direction::Column => Rect::new(
+                self.area.x,
+                self.area.y + self.cursor,
+                self.area.width,
+                size,
+            ),
+            Direction::Row => Rect::new(
+                self.area.x + self.cursor,
+                self.area.y,
+                size,
+                self.area.height,
+            ),
+        };
+        self.cursor += size + self.spacing;
+        rect
+    }
+    ///
+    /// Leaves an empty gap of the given size.
+    ///
+    pub(crate) fn skip(&mut self, size: f32) {
+        self.cursor += size;
+    }
+    ///
+    /// What is left of the area after the rects handed out so far.
+    ///
+    pub(crate) fn get_remaining(&self) -> Rect {
+        match self.direction {
+            Direction::Column => Rect::new(
+                self.area.x,
+                self.area.y + self.cursor,
+                self.area.width,
+                (self.area.height - self.cursor).max(0.0),
+            ),
+            Direction::Row => Rect::new(
+                self.area.x + self.cursor,
+                self.area.y,
+                (self.area.width - self.cursor).max(0.0),
+                self.area.height,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column() {
+        let mut layout = Layout::column(Rect::new(10.0, 20.0, 200.0, 100.0))
+            .with_padding(5.0)
+            .with_spacing(10.0);
+        assert_eq!(layout.next(20.0), Rect::new(15.0, 25.0, 190.0, 20.0));
+        assert_eq!(layout.next(30.0), Rect::new(15.0, 55.0, 190.0, 30.0));
+        assert_eq!(layout.get_remaining(), Rect::new(15.0, 95.0, 190.0, 20.0));
+    }
+
+    #[test]
+    fn row() {
+        let mut layout = Layout::row(Rect::new(0.0, 0.0, 300.0, 28.0));
+        layout.next(100.0);
+        layout.skip(20.0);
+        assert_eq!(layout.next(50.0), Rect::new(126.0, 0.0, 50.0, 28.0));
+        assert!(layout.get_remaining().contains(200.0, 10.0));
+    }
+}
@@ -4,6 +4,14 @@ use femtovg::{
 };
 use powder::Meta;
 
+mod layout;
+mod ui;
+mod widgets;
+
+pub(super) use layout::{Layout, Rect};
+pub(super) use ui::{draw_with_ui, Id, Ui, UiState};
+pub(super) use widgets::{get_keypad_height, Cell};
+
 pub(super) fn draw_horizontal_slider(
     canvas: &mut Canvas<OpenGl>,
     x: f32,
@@ -29,6 +37,37 @@ pub(super) fn draw_horizontal_slider(
     canvas.stroke_path(&mut path, paint);
 }
 
+///
+/// Interactive horizontal slider. Clicking on the slider moves the value to the clicked position.
+/// Returns the new value if it was changed this frame.
+///
+pub(super) fn draw_horizontal_slider_input(
+    canvas: &mut Canvas<OpenGl>,
+    meta: &Meta,
+    x: f32,
+    y: f32,
+    width: f32,
+    min: f32,
+    max: f32,
+    value: f32,
+) -> Option<f32> {
+    draw_horizontal_slider(canvas, x, y, width, min, max, value);
+
+    let mut path = Path::new();
+    path.rect(x, y - 8.0, width, 16.0);
+    let is_mouse_hovering = canvas.contains_point(
+        &mut path,
+        meta.mouse_position.x,
+        meta.mouse_position.y,
+        FillRule::EvenOdd,
+    );
+    if !is_mouse_hovering || !meta.is_mouse_released {
+        return None;
+    }
+    let fraction = ((meta.mouse_position.x - x) / width).clamp(0.0, 1.0);
+    Some(min + fraction * (max - min))
+}
+
 pub(super) fn draw_button(
     canvas: &mut Canvas<OpenGl>,
     meta: &Meta,
@@ -71,3 +110,94 @@ pub(super) fn draw_button(
     // Return clicked boolean
     is_mouse_hovering && meta.is_mouse_released
 }
+
+///
+/// Draws the left and bottom axis of a chart area, labelled with the ends and middle of each
+/// range.
+///
+pub(super) fn draw_axes(
+    canvas: &mut Canvas<OpenGl>,
+    state: &PowderState,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    x_range: (f32, f32),
+    y_range: (f32, f32),
+) {
+    let mut paint = Paint::color(Color::rgbf(0.5, 0.5, 0.5));
+    paint.set_line_width(1.0);
+    let mut path = Path::new();
+    path.move_to(x, y);
+    path.line_to(x, y + height);
+    path.line_to(x + width, y + height);
+    canvas.stroke_path(&mut path, paint);
+
+    paint.set_color(Color::rgbf(0.8, 0.8, 0.8));
+    paint.set_font_size(12.0);
+    paint.set_font(&[state.font.unwrap()]);
+    for fraction in [0.0, 0.5, 1.0] {
+        let x_value = x_range.0 + (x_range.1 - x_range.0) * fraction;
+        paint.set_text_align(Align::Center);
+        paint.set_text_baseline(Baseline::Top);
+        let _ = canvas.fill_text(
+            x + width * fraction,
+            y + height + 4.0,
+            format!("{:.0}", x_value),
+            paint,
+        );
+        let y_value = y_range.0 + (y_range.1 - y_range.0) * fraction;
+        paint.set_text_align(Align::Right);
+        paint.set_text_baseline(Baseline::Middle);
+        let _ = canvas.fill_text(
+            x - 4.0,
+            y + height * (1.0 - fraction),
+            format!("{:.0}", y_value),
+            paint,
+        );
+    }
+}
+
+///
+/// Plots points as a line within a chart area. Values outside the ranges are clamped to the
+/// edges of the area, a `None` value breaks the line.
+///
+pub(super) fn draw_line_chart(
+    canvas: &mut Canvas<OpenGl>,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    x_range: (f32, f32),
+    y_range: (f32, f32),
+    points: &[(f32, Option<f32>)],
+    color: Color,
+) {
+    let x_span = (x_range.1 - x_range.0).max(f32::EPSILON);
+    let y_span = (y_range.1 - y_range.0).max(f32::EPSILON);
+    let mut paint = Paint::color(color);
+    paint.set_line_cap(LineCap::Round);
+    paint.set_line_join(LineJoin::Round);
+    paint.set_line_width(2.0);
+
+    let mut path = Path::new();
+    let mut is_drawing = false;
+    for (point_x, point_y) in points.iter() {
+        let point_y = match point_y {
+            Some(point_y) => point_y,
+            None => {
+                is_drawing = false;
+                continue;
+            }
+        };
+        let screen_x = x + ((point_x - x_range.0) / x_span).clamp(0.0, 1.0) * width;
+        let screen_y = y + height - ((point_y - y_range.0) / y_span).clamp(0.0, 1.0) * height;
+        if is_drawing {
+            path.line_to(screen_x, screen_y);
+        } else {
+            path.move_to(screen_x, screen_y);
+            is_drawing = true;
+        }
+    }
+    canvas.stroke_path(&mut path, paint);
+}
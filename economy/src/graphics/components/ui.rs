@@ -0,0 +1,198 @@
+use super::{super::PowderState, layout::Rect};
+use femtovg::{renderer::OpenGl, Align, Baseline, Canvas, Color, FontId, Paint, Path};
+use powder::Meta;
+use std::{
+    any::Any,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+///
+/// Identifies a widget across frames. Widgets repeated in a list derive their id from the id of
+/// the list with `with`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Id(u64);
+impl Id {
+    pub(crate) fn new(name: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+    pub(crate) fn with(self, child: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        child.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+///
+/// Everything widgets remember between frames, keyed by widget id.
+///
+#[derive(Default)]
+pub(crate) struct UiState {
+    widgets: HashMap<Id, Box<dyn Any + Send>>,
+    pub(crate) focused: Option<Id>,
+}
+impl UiState {
+    ///
+    /// Get the state of a widget, starting from the default the first time it is asked for.
+    ///
+    pub(crate) fn get<T: Default + Send + 'static>(&mut self, id: Id) -> &mut T {
+        let widget = self
+            .widgets
+            .entry(id)
+            .or_insert_with(|| Box::new(T::default()));
+        if !widget.is::<T>() {
+            *widget = Box::new(T::default());
+        }
+        widget.downcast_mut::<T>().unwrap()
+    }
+}
+
+///
+/// Colours and sizes shared by every widget.
+///
+pub(crate) struct Theme {
+    pub(crate) text: Color,
+    pub(crate) muted: Color,
+    pub(crate) background: Color,
+    pub(crate) hover: Color,
+    pub(crate) accent: Color,
+    pub(crate) font_size: f32,
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: Color::rgbf(1.0, 1.0, 1.0),
+            muted: Color::rgbf(0.6, 0.6, 0.6),
+            background: Color::rgbf(0.2, 0.2, 0.25),
+            hover: Color::rgbf(0.3, 0.3, 0.4),
+            accent: Color::rgbf(0.5, 1.0, 0.5),
+            font_size: 14.0,
+        }
+    }
+}
+
+///
+/// Immediate mode context for drawing widgets in a layer. Built at the start of a layer with the
+/// state kept in `PowderState`, widgets are then drawn and interacted with in one call each.
+///
+pub(crate) struct Ui<'a> {
+    pub(crate) canvas: &'a mut Canvas<OpenGl>,
+    pub(crate) state: &'a mut UiState,
+    pub(crate) theme: Theme,
+    meta: &'a Meta,
+    font: FontId,
+    /// Only the part of the screen inside this rect can be interacted with, if set.
+    pub(super) clip: Option<Rect>,
+    pub(super) is_click_taken: bool,
+    tooltip: Option<String>,
+}
+impl<'a> Ui<'a> {
+    pub(crate) fn new(
+        canvas: &'a mut Canvas<OpenGl>,
+        meta: &'a Meta,
+        font: FontId,
+        state: &'a mut UiState,
+    ) -> Self {
+        Self {
+            canvas,
+            state,
+            theme: Theme::default(),
+            meta,
+            font,
+            clip: None,
+            is_click_taken: false,
+            tooltip: None,
+        }
+    }
+    ///
+    /// Paint for text in the theme colour, vertically centered on the given position.
+    ///
+    pub(crate) fn get_text_paint(&self, align: Align) -> Paint {
+        let mut paint = Paint::color(self.theme.text);
+        paint.set_font_size(self.theme.font_size);
+        paint.set_font(&[self.font]);
+        paint.set_text_align(align);
+        paint.set_text_baseline(Baseline::Middle);
+        paint
+    }
+    pub(crate) fn get_mouse_position(&self) -> (f32, f32) {
+        (self.meta.mouse_position.x, self.meta.mouse_position.y)
+    }
+    pub(crate) fn is_hovered(&self, rect: Rect) -> bool {
+        let (x, y) = self.get_mouse_position();
+        rect.contains(x, y) && self.clip.map_or(true, |clip| clip.contains(x, y))
+    }
+    ///
+    /// Whether the rect was clicked this frame. A click goes to the first widget asking for it.
+    ///
+    pub(crate) fn is_clicked(&mut self, rect: Rect) -> bool {
+        if self.is_click_taken || !self.meta.is_mouse_released || !self.is_hovered(rect) {
+            return false;
+        }
+        self.is_click_taken = true;
+        true
+    }
+    ///
+    /// Whether the mouse was released anywhere this frame, whether or not a widget took the click.
+    ///
+    pub(crate) fn is_mouse_released(&self) -> bool {
+        self.meta.is_mouse_released
+    }
+    pub(crate) fn is_focused(&self, id: Id) -> bool {
+        self.state.focused == Some(id)
+    }
+    pub(crate) fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let mut path = Path::new();
+        path.rect(rect.x, rect.y, rect.width, rect.height);
+        self.canvas.fill_path(&mut path, Paint::color(color));
+    }
+    pub(crate) fn fill_text(&mut self, x: f32, y: f32, text: &str, paint: Paint) {
+        let _ = self.canvas.fill_text(x, y, text, paint);
+    }
+    ///
+    /// Shows the text next to the mouse while the rect is hovered.
+    ///
+    pub(crate) fn tooltip(&mut self, rect: Rect, text: &str) {
+        if self.is_hovered(rect) {
+            self.tooltip = Some(text.to_string());
+        }
+    }
+    ///
+    /// Draws what has to be on top of every widget. Call once after the last widget.
+    ///
+    pub(crate) fn finish(mut self) {
+        if let Some(text) = self.tooltip.take() {
+            let (x, y) = self.get_mouse_position();
+            let paint = self.get_text_paint(Align::Left);
+            let width = self
+                .canvas
+                .measure_text(0.0, 0.0, &text, paint)
+                .map_or(200.0, |metrics| metrics.width());
+            let rect = Rect::new(x + 12.0, y + 12.0, width + 12.0, 24.0);
+            let background = self.theme.background;
+            self.fill_rect(rect, background);
+            self.fill_text(rect.x + 6.0, rect.y + rect.height / 2.0, &text, paint);
+        }
+    }
+}
+
+///
+/// Draws a layer with widgets. The UI state is lent out of `PowderState` for the duration, so the
+/// rest of the state can still be used alongside the widgets.
+///
+pub(crate) fn draw_with_ui(
+    canvas: &mut Canvas<OpenGl>,
+    meta: &Meta,
+    state: &mut PowderState,
+    draw: impl FnOnce(&mut Ui, &mut PowderState),
+) {
+    let mut ui_state = std::mem::take(&mut state.ui);
+    let mut ui = Ui::new(canvas, meta, state.font.unwrap(), &mut ui_state);
+    draw(&mut ui, state);
+    ui.finish();
+    state.ui = ui_state;
+}
@@ -0,0 +1,417 @@
+use super::{
+    layout::{Layout, Rect},
+    ui::{Id, Ui},
+};
+use femtovg::{Align, Color};
+use std::{cmp::Ordering, fmt::Display};
+
+const SCROLLBAR_WIDTH: f32 = 8.0;
+const KEY_SIZE: f32 = 24.0;
+
+///
+/// Height of the keypad a focused text input of the given width shows below itself.
+///
+pub(crate) fn get_keypad_height(width: f32, character_count: usize) -> f32 {
+    // Three keys more than characters, to delete, clear and close
+    let keys_per_row = ((width / KEY_SIZE) as usize).max(1);
+    let rows = (character_count + 3 + keys_per_row - 1) / keys_per_row;
+    rows as f32 * KEY_SIZE + 2.0
+}
+
+///
+/// A table cell. Numbers sort by value, text alphabetically.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Cell {
+    Text(String),
+    Number(f64),
+}
+impl Cell {
+    fn compare(&self, other: &Cell) -> Ordering {
+        match (self, other) {
+            (Cell::Number(a), Cell::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Cell::Number(_), Cell::Text(_)) => Ordering::Less,
+            (Cell::Text(_), Cell::Number(_)) => Ordering::Greater,
+            (Cell::Text(a), Cell::Text(b)) => a.cmp(b),
+        }
+    }
+}
+impl Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Text(text) => write!(f, "{}", text),
+            Cell::Number(number) if number.fract() == 0.0 => write!(f, "{:.0}", number),
+            Cell::Number(number) => write!(f, "{:.2}", number),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DropdownState {
+    is_open: bool,
+    selected: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct TableSort {
+    column: Option<usize>,
+    is_descending: bool,
+}
+
+///
+/// Order in which the rows of a table are shown.
+///
+fn get_sorted_rows(rows: &[Vec<Cell>], sort: TableSort) -> Vec<usize> {
+    let mut order = (0..rows.len()).collect::<Vec<_>>();
+    if let Some(column) = sort.column {
+        order.sort_by(|a, b| {
+            let ordering = match (rows[*a].get(column), rows[*b].get(column)) {
+                (Some(a), Some(b)) => a.compare(b),
+                _ => Ordering::Equal,
+            };
+            if sort.is_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+    order
+}
+
+impl<'a> Ui<'a> {
+    pub(crate) fn label(&mut self, rect: Rect, text: impl Display) {
+        let paint = self.get_text_paint(Align::Left);
+        self.fill_text(rect.x, rect.y + rect.height / 2.0, &text.to_string(), paint);
+    }
+    pub(crate) fn muted_label(&mut self, rect: Rect, text: impl Display) {
+        let mut paint = self.get_text_paint(Align::Left);
+        paint.set_color(self.theme.muted);
+        self.fill_text(rect.x, rect.y + rect.height / 2.0, &text.to_string(), paint);
+    }
+    ///
+    /// Returns true when clicked.
+    ///
+    pub(crate) fn button(&mut self, rect: Rect, label: &str) -> bool {
+        let color = if self.is_hovered(rect) {
+            self.theme.hover
+        } else {
+            self.theme.background
+        };
+        self.fill_rect(rect, color);
+        let paint = self.get_text_paint(Align::Center);
+        let (x, y) = rect.get_center();
+        self.fill_text(x, y, label, paint);
+        self.is_clicked(rect)
+    }
+    ///
+    /// A row of buttons of which one is selected. Returns the index of the selected button.
+    ///
+    pub(crate) fn tabs(&mut self, id: Id, rect: Rect, labels: &[&str]) -> usize {
+        let width = rect.width / labels.len().max(1) as f32;
+        let mut layout = Layout::row(rect).with_spacing(0.0);
+        for (i, label) in labels.iter().enumerate() {
+            let tab_rect = layout.next(width);
+            if self.button(tab_rect, label) {
+                *self.state.get::<usize>(id) = i;
+            }
+        }
+        let selected = *self.state.get::<usize>(id);
+        let underline = Rect::new(
+            rect.x + width * selected as f32,
+            rect.y + rect.height - 3.0,
+            width,
+            3.0,
+        );
+        let accent = self.theme.accent;
+        self.fill_rect(underline, accent);
+        selected
+    }
+    ///
+    /// A checkbox with a label, flipped by clicking either. Returns whether it is checked.
+    ///
+    pub(crate) fn toggle(&mut self, id: Id, rect: Rect, label: &str, default: bool) -> bool {
+        let is_checked = *self.state.get::<Option<bool>>(id).get_or_insert(default);
+        let is_checked = if self.is_clicked(rect) {
+            !is_checked
+        } else {
+            is_checked
+        };
+        *self.state.get::<Option<bool>>(id) = Some(is_checked);
+
+        let size = rect.height.min(16.0);
+        let box_rect = Rect::new(rect.x, rect.y + (rect.height - size) / 2.0, size, size);
+        let background = self.theme.background;
+        self.fill_rect(box_rect, background);
+        if is_checked {
+            let accent = self.theme.accent;
+            self.fill_rect(box_rect.shrink(3.0), accent);
+        }
+        let paint = self.get_text_paint(Align::Left);
+        self.fill_text(
+            rect.x + size + 6.0,
+            rect.y + rect.height / 2.0,
+            label,
+            paint,
+        );
+        is_checked
+    }
+    ///
+    /// A button showing the selected option, which opens the list of options below it. Returns
+    /// the index of the selected option.
+    ///
+    pub(crate) fn dropdown(&mut self, id: Id, rect: Rect, options: &[&str]) -> usize {
+        let dropdown = *self.state.get::<DropdownState>(id);
+        let label = options.get(dropdown.selected).copied().unwrap_or("");
+        let arrow = if dropdown.is_open { "^" } else { "v" };
+        if self.button(rect, &format!("{} {}", label, arrow)) {
+            self.state.get::<DropdownState>(id).is_open = !dropdown.is_open;
+            return dropdown.selected;
+        }
+        if !dropdown.is_open {
+            return dropdown.selected;
+        }
+
+        let mut layout = Layout::column(Rect::new(
+            rect.x,
+            rect.y + rect.height,
+            rect.width,
+            rect.height * options.len() as f32,
+        ))
+        .with_spacing(0.0);
+        for (i, option) in options.iter().enumerate() {
+            if self.button(layout.next(rect.height), option) {
+                self.state.get::<DropdownState>(id).selected = i;
+            }
+        }
+        // Choosing an option or clicking anywhere else closes the list
+        if self.is_mouse_released() {
+            self.state.get::<DropdownState>(id).is_open = false;
+        }
+        self.state.get::<DropdownState>(id).selected
+    }
+    ///
+    /// A single line text field. Layers only see the mouse, so once the field is focused by
+    /// clicking it, text is typed on a keypad of the given characters shown below it. Returns
+    /// the text.
+    ///
+    pub(crate) fn text_input(&mut self, id: Id, rect: Rect, characters: &[char]) -> String {
+        let is_focused = self.is_focused(id);
+        let color = if is_focused {
+            self.theme.hover
+        } else {
+            self.theme.background
+        };
+        self.fill_rect(rect, color);
+        let paint = self.get_text_paint(Align::Left);
+        let cursor = if is_focused { "_" } else { "" };
+        let text = format!("{}{}", self.state.get::<String>(id), cursor);
+        self.fill_text(rect.x + 6.0, rect.y + rect.height / 2.0, &text, paint);
+        if self.is_clicked(rect) {
+            self.state.focused = if is_focused { None } else { Some(id) };
+        }
+        if !is_focused {
+            return self.state.get::<String>(id).clone();
+        }
+
+        // Keypad, ending with keys to delete a character, clear the text and close the keypad
+        let keys_per_row = ((rect.width / KEY_SIZE) as usize).max(1);
+        let mut keys = characters
+            .iter()
+            .map(|character| character.to_string())
+            .collect::<Vec<_>>();
+        keys.extend(["<", "C", "OK"].map(String::from));
+        for (i, key) in keys.iter().enumerate() {
+            let key_rect = Rect::new(
+                rect.x + (i % keys_per_row) as f32 * KEY_SIZE,
+                rect.y + rect.height + 2.0 + (i / keys_per_row) as f32 * KEY_SIZE,
+                KEY_SIZE - 2.0,
+                KEY_SIZE - 2.0,
+            );
+            if !self.button(key_rect, key) {
+                continue;
+            }
+            let value = self.state.get::<String>(id);
+            match keys.len() - i {
+                3 => {
+                    value.pop();
+                }
+                2 => value.clear(),
+                1 => self.state.focused = None,
+                _ => value.push_str(key),
+            }
+        }
+        self.state.get::<String>(id).clone()
+    }
+    ///
+    /// Starts a scroll area. Widgets drawn until `end_scroll` are clipped to the rect and should
+    /// be offset upwards by the returned amount. Clicking the scrollbar on the right jumps there.
+    ///
+    pub(crate) fn begin_scroll(&mut self, id: Id, rect: Rect, content_height: f32) -> f32 {
+        let max_offset = (content_height - rect.height).max(0.0);
+        let bar = Rect::new(
+            rect.x + rect.width - SCROLLBAR_WIDTH,
+            rect.y,
+            SCROLLBAR_WIDTH,
+            rect.height,
+        );
+        if max_offset > 0.0 && self.is_clicked(bar) {
+            let fraction = ((self.get_mouse_position().1 - rect.y) / rect.height).clamp(0.0, 1.0);
+            *self.state.get::<f32>(id) = fraction * max_offset;
+        }
+        let offset = self.state.get::<f32>(id).clamp(0.0, max_offset);
+        *self.state.get::<f32>(id) = offset;
+
+        // Scrollbar, with a thumb as tall as the visible part of the content
+        if max_offset > 0.0 {
+            let background = self.theme.background;
+            self.fill_rect(bar, background);
+            let thumb_height = rect.height * rect.height / content_height;
+            let thumb = Rect::new(
+                bar.x,
+                bar.y + (rect.height - thumb_height) * offset / max_offset,
+                bar.width,
+                thumb_height,
+            );
+            let muted = self.theme.muted;
+            self.fill_rect(thumb, muted);
+        }
+
+        self.canvas.save();
+        self.canvas
+            .scissor(rect.x, rect.y, rect.width - SCROLLBAR_WIDTH, rect.height);
+        self.clip = Some(rect);
+        offset
+    }
+    pub(crate) fn end_scroll(&mut self) {
+        self.canvas.restore();
+        self.clip = None;
+    }
+    ///
+    /// A table with a header row. Clicking a header sorts by that column, clicking it again
+    /// reverses the order. Rows scroll when they do not fit. Returns the index of a clicked row.
+    ///
+    pub(crate) fn table(
+        &mut self,
+        id: Id,
+        rect: Rect,
+        headers: &[&str],
+        rows: &[Vec<Cell>],
+        selected: Option<usize>,
+    ) -> Option<usize> {
+        let row_height = 22.0;
+        let column_width = (rect.width - SCROLLBAR_WIDTH) / headers.len().max(1) as f32;
+
+        // Header
+        let mut sort = *self.state.get::<TableSort>(id);
+        for (i, header) in headers.iter().enumerate() {
+            let header_rect = Rect::new(
+                rect.x + column_width * i as f32,
+                rect.y,
+                column_width,
+                row_height,
+            );
+            let arrow = match sort {
+                TableSort {
+                    column: Some(column),
+                    is_descending,
+                } if column == i => {
+                    if is_descending {
+                        " v"
+                    } else {
+                        " ^"
+                    }
+                }
+                _ => "",
+            };
+            if self.button(header_rect, &format!("{}{}", header, arrow)) {
+                sort = TableSort {
+                    column: Some(i),
+                    is_descending: sort.column == Some(i) && !sort.is_descending,
+                };
+            }
+        }
+        *self.state.get::<TableSort>(id) = sort;
+
+        // Body
+        let body = Rect::new(
+            rect.x,
+            rect.y + row_height,
+            rect.width,
+            (rect.height - row_height).max(0.0),
+        );
+        let offset = self.begin_scroll(id.with("body"), body, rows.len() as f32 * row_height);
+        let mut clicked = None;
+        for (position, index) in get_sorted_rows(rows, sort).into_iter().enumerate() {
+            let row_y = body.y + position as f32 * row_height - offset;
+            if row_y + row_height < body.y || row_y > body.y + body.height {
+                continue;
+            }
+            let row_rect = Rect::new(body.x, row_y, body.width - SCROLLBAR_WIDTH, row_height);
+            if selected == Some(index) {
+                let hover = self.theme.hover;
+                self.fill_rect(row_rect, hover);
+            }
+            if self.is_clicked(row_rect) {
+                clicked = Some(index);
+            }
+            for (i, cell) in rows[index].iter().enumerate() {
+                let cell_rect = Rect::new(
+                    row_rect.x + 4.0 + column_width * i as f32,
+                    row_y,
+                    column_width,
+                    row_height,
+                );
+                self.label(cell_rect, cell);
+            }
+        }
+        self.end_scroll();
+        clicked
+    }
+    ///
+    /// A horizontal bar filled to the given fraction.
+    ///
+    pub(crate) fn progress_bar(&mut self, rect: Rect, fraction: f32, color: Color) {
+        let background = self.theme.background;
+        self.fill_rect(rect, background);
+        let filled = Rect::new(
+            rect.x,
+            rect.y,
+            rect.width * fraction.clamp(0.0, 1.0),
+            rect.height,
+        );
+        self.fill_rect(filled, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_rows() {
+        let rows = vec![
+            vec![Cell::Text("Banana".to_string()), Cell::Number(3.0)],
+            vec![Cell::Text("Apple".to_string()), Cell::Number(10.0)],
+            vec![Cell::Text("Cherry".to_string()), Cell::Number(1.0)],
+        ];
+        assert_eq!(get_sorted_rows(&rows, TableSort::default()), vec![0, 1, 2]);
+        let by_name = TableSort {
+            column: Some(0),
+            is_descending: false,
+        };
+        assert_eq!(get_sorted_rows(&rows, by_name), vec![1, 0, 2]);
+        let by_count = TableSort {
+            column: Some(1),
+            is_descending: true,
+        };
+        assert_eq!(get_sorted_rows(&rows, by_count), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn display_cell() {
+        assert_eq!(Cell::Number(3.0).to_string(), "3");
+        assert_eq!(Cell::Number(2.5).to_string(), "2.50");
+        assert_eq!(Cell::Text("Apple".to_string()).to_string(), "Apple");
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    graphics::{
+        components::{get_keypad_height, Cell, Id, Layout, Rect, Ui},
+        PowderState,
+    },
+    renderable::ActorDetail,
+    simulation::control::SimulationCommand,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+const LINE_HEIGHT: f32 = 20.0;
+
+///
+/// Master/detail view of the actors. The list on the left can be searched and scrolled, the
+/// selected actor is inspected on the right.
+///
+pub fn render_actor_inspector(ui: &mut Ui, state: &mut PowderState, area: Rect) {
+    let mut columns = Layout::row(area).with_spacing(40.0);
+    render_actor_list(ui, state, columns.next(240.0));
+    render_actor_detail(ui, state, columns.get_remaining());
+}
+
+///
+/// Search field, typed into with a keypad of the characters which appear in actor names, above
+/// the matching actors.
+///
+fn render_actor_list(ui: &mut Ui, state: &mut PowderState, area: Rect) {
+    let row_height = 26.0;
+    let search_id = Id::new("actor_search");
+    let mut layout = Layout::column(area);
+
+    let characters = state
+        .renderable_state
+        .actor_info
+        .iter()
+        .flat_map(|(_, name, _, _)| name.to_lowercase().chars().collect::<Vec<_>>())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let search_rect = layout.next(28.0);
+    if ui.is_focused(search_id) {
+        layout.skip(get_keypad_height(area.width, characters.len()));
+    }
+    let search = ui
+        .text_input(search_id, search_rect, &characters)
+        .to_lowercase();
+
+    let actors = state
+        .renderable_state
+        .actor_info
+        .iter()
+        .filter(|(_, name, _, _)| name.to_lowercase().contains(&search))
+        .collect::<Vec<_>>();
+    ui.muted_label(
+        layout.next(LINE_HEIGHT),
+        format!(
+            "{} of {} actors",
+            actors.len(),
+            state.renderable_state.actor_info.len()
+        ),
+    );
+
+    let list = layout.get_remaining();
+    let offset = ui.begin_scroll(
+        Id::new("actor_list"),
+        list,
+        actors.len() as f32 * row_height,
+    );
+    let mut selected = None;
+    for (i, (id, name, log, _)) in actors.iter().enumerate() {
+        let row = Rect::new(
+            list.x,
+            list.y + i as f32 * row_height - offset,
+            list.width - 10.0,
+            row_height - 2.0,
+        );
+        if row.y + row.height < list.y || row.y > list.y + list.height {
+            continue;
+        }
+        let label = if state.selected_actor == Some(*id) {
+            format!("> {}", name)
+        } else {
+            name.clone()
+        };
+        if ui.button(row, &label) {
+            selected = Some(*id);
+        }
+        if let Some(entry) = log.first() {
+            ui.tooltip(row, entry);
+        }
+    }
+    ui.end_scroll();
+    if selected.is_some() {
+        select_actor(ui, state, selected);
+    }
+}
+
+fn render_actor_detail(ui: &mut Ui, state: &mut PowderState, area: Rect) {
+    let table_width = 360.0;
+    let table_height = 180.0;
+
+    let detail = match (
+        state.selected_actor,
+        state.renderable_state.actor_detail.as_ref(),
+    ) {
+        (None, _) => {
+            ui.label(
+                Rect {
+                    height: LINE_HEIGHT,
+                    ..area
+                },
+                "Select an actor to inspect",
+            );
+            return;
+        }
+        (Some(id), Some(detail)) if detail.id == id => detail,
+        _ => {
+            ui.label(
+                Rect {
+                    height: LINE_HEIGHT,
+                    ..area
+                },
+                "Loading...",
+            );
+            return;
+        }
+    };
+
+    let mut columns = Layout::row(area).with_spacing(60.0);
+    let mut layout = Layout::column(columns.next(table_width));
+    render_log(ui, detail, columns.get_remaining());
+
+    // Overview
+    let font_size = ui.theme.font_size;
+    ui.theme.font_size = 20.0;
+    ui.label(layout.next(LINE_HEIGHT + 4.0), &detail.name);
+    ui.theme.font_size = font_size;
+    let loans_due = detail.loans.iter().sum::<u64>();
+    let lines = [
+        format!("Balance: {}", detail.balance),
+        format!("Loans: {} ({} due)", detail.loans.len(), loans_due),
+        format!("Action: {}", detail.action),
+    ];
+    for line in lines {
+        ui.label(layout.next(LINE_HEIGHT), line);
+    }
+    if let Some(progress) = detail.action_progress {
+        let mut row = Layout::row(layout.next(LINE_HEIGHT));
+        let accent = ui.theme.accent;
+        ui.progress_bar(row.next(200.0).shrink(6.0), progress, accent);
+        ui.label(row.next(60.0), format!("{:.0}%", progress * 100.0));
+    }
+
+    // Store, actual against target
+    layout.skip(10.0);
+    let item_kinds = detail
+        .store_actual
+        .keys()
+        .chain(detail.store_target.keys())
+        .collect::<BTreeSet<_>>();
+    let store = item_kinds
+        .into_iter()
+        .map(|item_kind| {
+            let actual = detail.store_actual.get(item_kind).copied().unwrap_or(0);
+            let target = detail.store_target.get(item_kind).copied().unwrap_or(0);
+            vec![
+                Cell::Text(item_kind.clone()),
+                Cell::Number(actual as f64),
+                Cell::Number(target as f64),
+            ]
+        })
+        .collect::<Vec<_>>();
+    ui.table(
+        Id::new("actor_store"),
+        layout.next(table_height),
+        &["Item", "Actual", "Target"],
+        &store,
+        None,
+    );
+
+    // Open listings, grouped by item kind and price
+    layout.skip(10.0);
+    ui.label(
+        layout.next(LINE_HEIGHT),
+        format!("Listings: {}", detail.listings.len()),
+    );
+    let mut listings = BTreeMap::<(&String, i64), usize>::new();
+    for (item_kind, price) in detail.listings.iter() {
+        *listings.entry((item_kind, *price)).or_default() += 1;
+    }
+    let listings = listings
+        .into_iter()
+        .map(|((item_kind, price), count)| {
+            vec![
+                Cell::Text(item_kind.clone()),
+                Cell::Number(price as f64),
+                Cell::Number(count as f64),
+            ]
+        })
+        .collect::<Vec<_>>();
+    ui.table(
+        Id::new("actor_listings"),
+        layout.next(table_height),
+        &["Item", "Price", "Count"],
+        &listings,
+        None,
+    );
+}
+
+///
+/// The full log of the inspected actor, newest first.
+///
+fn render_log(ui: &mut Ui, detail: &ActorDetail, area: Rect) {
+    let mut layout = Layout::column(area);
+    ui.label(
+        layout.next(LINE_HEIGHT),
+        format!("Log, {} entries", detail.log.len()),
+    );
+    let log = layout.get_remaining();
+    let offset = ui.begin_scroll(
+        Id::new("actor_log"),
+        log,
+        detail.log.len() as f32 * LINE_HEIGHT,
+    );
+    for (i, entry) in detail.log.iter().enumerate() {
+        let row = Rect::new(
+            log.x,
+            log.y + i as f32 * LINE_HEIGHT - offset,
+            log.width,
+            LINE_HEIGHT,
+        );
+        if row.y + row.height >= log.y && row.y <= log.y + log.height {
+            ui.label(row, entry);
+        }
+    }
+    ui.end_scroll();
+}
+
+fn select_actor(ui: &mut Ui, state: &mut PowderState, id: Option<Uuid>) {
+    state.selected_actor = id;
+    *ui.state.get::<f32>(Id::new("actor_log")) = 0.0;
+    if let Some(command_tx) = state.command_tx.as_ref() {
+        let _ = command_tx.send(SimulationCommand::Inspect(id));
+    }
+}
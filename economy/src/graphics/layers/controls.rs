@@ -0,0 +1,141 @@
+use crate::{
+    graphics::{
+        components::{draw_button, draw_horizontal_slider_input},
+        PowderState,
+    },
+    simulation::control::{ControlCommand, MIN_SPEED},
+};
+use femtovg::{renderer::OpenGl, Align, Baseline, Canvas, Color, Paint};
+use powder::Meta;
+
+const MAX_SLIDER_SPEED: f64 = 10.0;
+
+pub fn render_controls(canvas: &mut Canvas<OpenGl>, meta: &mut Meta, state: &mut PowderState) {
+    let x = 10.0;
+    let y = 70.0;
+    let button_width = 85.0;
+    let button_height = 28.0;
+    let spacing = 6.0;
+
+    let mut paint = Paint::color(Color::rgbf(1.0, 1.0, 1.0));
+    paint.set_font_size(14.0);
+    paint.set_font(&[state.font.unwrap()]);
+    paint.set_text_align(Align::Left);
+    paint.set_text_baseline(Baseline::Top);
+    let speed = if state.speed.is_infinite() {
+        "max".to_string()
+    } else {
+        format!("{:.1}x", state.speed)
+    };
+    let status = if state.is_paused { "Paused" } else { "Running" };
+    let _ = canvas.fill_text(
+        x,
+        y,
+        format!(
+            "Tick {}  {}  {}",
+            state.renderable_state.tick, status, speed
+        ),
+        paint,
+    );
+
+    // Pause and step
+    let y = y + 24.0;
+    let label = if state.is_paused { "Resume" } else { "Pause" };
+    if draw_button(
+        canvas,
+        meta,
+        state,
+        x,
+        y,
+        button_width,
+        button_height,
+        label,
+    ) {
+        state.is_paused = !state.is_paused;
+        send(
+            state,
+            if state.is_paused {
+                ControlCommand::Pause
+            } else {
+                ControlCommand::Resume
+            },
+        );
+    }
+    let step_x = x + button_width + spacing;
+    if draw_button(
+        canvas,
+        meta,
+        state,
+        step_x,
+        y,
+        button_width,
+        button_height,
+        "Step",
+    ) {
+        state.is_paused = true;
+        send(state, ControlCommand::Step);
+    }
+
+    // Jump ahead
+    let y = y + button_height + spacing;
+    for (i, ticks) in [10, 100].iter().enumerate() {
+        let jump_x = x + (button_width + spacing) * i as f32;
+        let label = format!("+{}", ticks);
+        if draw_button(
+            canvas,
+            meta,
+            state,
+            jump_x,
+            y,
+            button_width,
+            button_height,
+            &label,
+        ) {
+            let target_tick = state.renderable_state.tick + ticks;
+            send(state, ControlCommand::JumpToTick(target_tick));
+        }
+    }
+
+    // Speed, logarithmic between the minimum speed and the slider maximum
+    let y = y + button_height + spacing + 12.0;
+    let slider_width = button_width * 2.0 + spacing;
+    let slider_value = state.speed.clamp(MIN_SPEED, MAX_SLIDER_SPEED).log10() as f32;
+    if let Some(value) = draw_horizontal_slider_input(
+        canvas,
+        meta,
+        x,
+        y,
+        slider_width,
+        MIN_SPEED.log10() as f32,
+        MAX_SLIDER_SPEED.log10() as f32,
+        slider_value,
+    ) {
+        state.speed = 10f64.powf(value as f64);
+        send(state, ControlCommand::SetSpeed(state.speed));
+    }
+    let y = y + 12.0;
+    if draw_button(canvas, meta, state, x, y, button_width, button_height, "1x") {
+        state.speed = 1.0;
+        send(state, ControlCommand::SetSpeed(state.speed));
+    }
+    let max_x = x + button_width + spacing;
+    if draw_button(
+        canvas,
+        meta,
+        state,
+        max_x,
+        y,
+        button_width,
+        button_height,
+        "Max",
+    ) {
+        state.speed = f64::INFINITY;
+        send(state, ControlCommand::SetSpeed(state.speed));
+    }
+}
+
+fn send(state: &PowderState, command: ControlCommand) {
+    if let Some(command_tx) = state.command_tx.as_ref() {
+        let _ = command_tx.send(command.into());
+    }
+}
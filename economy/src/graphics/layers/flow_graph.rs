@@ -0,0 +1,201 @@
+use crate::{
+    graphics::PowderState,
+    simulation::flow::{FlowGraph, FlowNodeKind},
+};
+use femtovg::{renderer::OpenGl, Align, Baseline, Canvas, Color, LineCap, Paint, Path};
+use std::collections::HashMap;
+
+///
+/// Weight of the latest sample in the smoothed flow rates.
+///
+const RATE_SMOOTHING: f32 = 0.1;
+
+///
+/// Draws the production network. Tasks and item kinds are laid out in columns from raw
+/// materials on the left to end products on the right. Edge thickness shows how many items flow
+/// along an edge per tick, item colour goes from green to red as inventory builds up.
+///
+pub fn render_flow_graph(canvas: &mut Canvas<OpenGl>, state: &mut PowderState, x: f32, y: f32) {
+    let column_width = 160.0;
+    let row_height = 90.0;
+    let item_radius = 24.0;
+    let task_width = 110.0;
+    let task_height = 36.0;
+
+    update_flow_rates(state);
+
+    let mut paint = Paint::color(Color::rgbf(1.0, 1.0, 1.0));
+    paint.set_font_size(14.0);
+    paint.set_font(&[state.font.unwrap()]);
+    paint.set_text_align(Align::Center);
+    paint.set_text_baseline(Baseline::Middle);
+
+    let graph = &state.renderable_state.flow_graph;
+    if graph.nodes.is_empty() {
+        paint.set_text_align(Align::Left);
+        let _ = canvas.fill_text(x, y, "No production network to show", paint);
+        return;
+    }
+
+    // Node positions, stacked within their column
+    let columns = get_columns(graph);
+    let mut rows = HashMap::<usize, usize>::new();
+    let mut positions = HashMap::<&str, (f32, f32)>::new();
+    for node in graph.nodes.iter() {
+        let column = columns[&node.name];
+        let row = rows.entry(column).or_default();
+        positions.insert(
+            &node.name,
+            (
+                x + task_width / 2.0 + column as f32 * column_width,
+                y + item_radius + *row as f32 * row_height,
+            ),
+        );
+        *row += 1;
+    }
+
+    // Edges
+    let max_rate = state
+        .flow_rates
+        .values()
+        .copied()
+        .fold(f32::EPSILON, f32::max);
+    for edge in graph.edges.iter() {
+        let (from, to) = match (
+            positions.get(edge.from.as_str()),
+            positions.get(edge.to.as_str()),
+        ) {
+            (Some(from), Some(to)) => (from, to),
+            _ => continue,
+        };
+        let rate = state
+            .flow_rates
+            .get(&(edge.from.clone(), edge.to.clone()))
+            .copied()
+            .unwrap_or(0.0);
+        let mut edge_paint = Paint::color(Color::rgbf(0.6, 0.6, 0.8));
+        edge_paint.set_line_cap(LineCap::Round);
+        edge_paint.set_line_width(1.0 + 11.0 * rate / max_rate);
+        let mut path = Path::new();
+        path.move_to(from.0, from.1);
+        path.line_to(to.0, to.1);
+        canvas.stroke_path(&mut path, edge_paint);
+        let _ = canvas.fill_text(
+            (from.0 + to.0) / 2.0,
+            (from.1 + to.1) / 2.0 - 12.0,
+            format!("{:.2}/t", rate),
+            paint,
+        );
+    }
+
+    // Nodes
+    let max_inventory = graph
+        .nodes
+        .iter()
+        .map(|node| node.inventory)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for node in graph.nodes.iter() {
+        let (node_x, node_y) = positions[node.name.as_str()];
+        let mut path = Path::new();
+        match node.kind {
+            FlowNodeKind::Item => {
+                let build_up = (node.inventory.max(0) as f32 / max_inventory as f32).min(1.0);
+                path.circle(node_x, node_y, item_radius);
+                canvas.fill_path(
+                    &mut path,
+                    Paint::color(Color::rgbf(0.3 + 0.7 * build_up, 0.8 - 0.5 * build_up, 0.3)),
+                );
+                let _ = canvas.fill_text(
+                    node_x,
+                    node_y + item_radius + 12.0,
+                    format!("{} ({})", node.name, node.inventory),
+                    paint,
+                );
+            }
+            FlowNodeKind::Task => {
+                path.rounded_rect(
+                    node_x - task_width / 2.0,
+                    node_y - task_height / 2.0,
+                    task_width,
+                    task_height,
+                    6.0,
+                );
+                canvas.fill_path(&mut path, Paint::color(Color::rgbf(0.25, 0.3, 0.45)));
+                let _ = canvas.fill_text(node_x, node_y, &node.name, paint);
+            }
+        }
+    }
+}
+
+///
+/// Updates the smoothed flow rate of every edge from the change in totals since the previous
+/// flow graph.
+///
+pub(crate) fn update_flow_rates(state: &mut PowderState) {
+    let graph = &state.renderable_state.flow_graph;
+    if let Some(previous) = state.flow_sample.as_ref() {
+        // Nothing new yet
+        if graph.tick == previous.tick {
+            return;
+        }
+        // Started over, for example when a replay was restarted
+        if graph.tick < previous.tick {
+            state.flow_rates.clear();
+        } else {
+            let ticks = (graph.tick - previous.tick) as f32;
+            for edge in graph.edges.iter() {
+                let previous_total = previous
+                    .edges
+                    .iter()
+                    .find(|previous| previous.from == edge.from && previous.to == edge.to)
+                    .map_or(0, |previous| previous.total);
+                let rate = edge.total.saturating_sub(previous_total) as f32 / ticks;
+                let smoothed = state
+                    .flow_rates
+                    .entry((edge.from.clone(), edge.to.clone()))
+                    .or_insert(rate);
+                *smoothed += (rate - *smoothed) * RATE_SMOOTHING;
+            }
+        }
+    }
+    state.flow_sample = Some(graph.clone());
+}
+
+///
+/// Column of every node, so that every edge points to a column further right. Cycles are cut
+/// off after as many passes as there are nodes.
+///
+fn get_columns(graph: &FlowGraph) -> HashMap<String, usize> {
+    let mut columns = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let column = match node.kind {
+                FlowNodeKind::Item => 0,
+                FlowNodeKind::Task => 1,
+            };
+            (node.name.clone(), column)
+        })
+        .collect::<HashMap<_, _>>();
+    for _ in 0..graph.nodes.len() {
+        let mut is_changed = false;
+        for edge in graph.edges.iter() {
+            let column = match columns.get(&edge.from) {
+                Some(column) => column + 1,
+                None => continue,
+            };
+            if let Some(to_column) = columns.get_mut(&edge.to) {
+                if *to_column < column {
+                    *to_column = column;
+                    is_changed = true;
+                }
+            }
+        }
+        if !is_changed {
+            break;
+        }
+    }
+    columns
+}
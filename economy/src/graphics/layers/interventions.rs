@@ -0,0 +1,123 @@
+use crate::{
+    graphics::{components::draw_button, PowderState},
+    simulation::intervention::{Intervention, InterventionRequest},
+};
+use femtovg::{renderer::OpenGl, Align, Baseline, Canvas, Color, Paint};
+use powder::Meta;
+
+pub fn render_interventions(canvas: &mut Canvas<OpenGl>, meta: &mut Meta, state: &mut PowderState) {
+    let x = 10.0;
+    let y = 270.0;
+    let button_width = 176.0;
+    let button_height = 28.0;
+    let spacing = 6.0;
+
+    // Collect replies to earlier interventions
+    while let Ok(reply) = state.reply_rx.try_recv() {
+        state.last_reply = Some(reply);
+    }
+
+    let mut paint = Paint::color(Color::rgbf(1.0, 1.0, 1.0));
+    paint.set_font_size(14.0);
+    paint.set_font(&[state.font.unwrap()]);
+    paint.set_text_align(Align::Left);
+    paint.set_text_baseline(Baseline::Top);
+    let _ = canvas.fill_text(x, y, "Interventions", paint);
+
+    let mut interventions = Vec::new();
+    let mut y = y + 24.0;
+
+    // Spawn an actor for each task
+    for task in state.renderable_state.task_names.clone() {
+        let label = format!("Spawn {}", task);
+        if draw_button(
+            canvas,
+            meta,
+            state,
+            x,
+            y,
+            button_width,
+            button_height,
+            &label,
+        ) {
+            let name = format!(
+                "Spawned_{}_{}",
+                task,
+                state.renderable_state.actor_count + 1
+            );
+            interventions.push(Intervention::SpawnActor {
+                name,
+                task: Some(task),
+                balance: 0,
+                location: None,
+            });
+        }
+        y += button_height + spacing;
+    }
+
+    // Remove the most recently added actor
+    let last_actor = state
+        .renderable_state
+        .actor_info
+        .last()
+        .map(|(_, name, _, _)| name.clone());
+    if let Some(name) = last_actor {
+        if draw_button(
+            canvas,
+            meta,
+            state,
+            x,
+            y,
+            button_width,
+            button_height,
+            "Remove last",
+        ) {
+            interventions.push(Intervention::RemoveActor { name });
+        }
+        y += button_height + spacing;
+    }
+
+    // Destroy half of the stock of each listed item kind
+    let item_kinds = state
+        .renderable_state
+        .listed_item_kinds
+        .iter()
+        .map(|(item_kind, _)| item_kind.clone())
+        .collect::<Vec<_>>();
+    for item_kind in item_kinds {
+        let label = format!("Halve {}", item_kind);
+        if draw_button(
+            canvas,
+            meta,
+            state,
+            x,
+            y,
+            button_width,
+            button_height,
+            &label,
+        ) {
+            interventions.push(Intervention::DestroyStock {
+                actor: None,
+                item_kind,
+                fraction: 0.5,
+            });
+        }
+        y += button_height + spacing;
+    }
+
+    // Send interventions
+    if let Some(command_tx) = state.command_tx.as_ref() {
+        for intervention in interventions {
+            let request = InterventionRequest::new(intervention, Some(state.reply_tx.clone()));
+            let _ = command_tx.send(request.into());
+        }
+    }
+
+    // Show outcome of the latest intervention
+    let message = match state.last_reply.as_ref() {
+        Some(Ok(message)) => message.clone(),
+        Some(Err(reason)) => format!("Rejected: {}", reason),
+        None => return,
+    };
+    let _ = canvas.fill_text(x, y, message, paint);
+}
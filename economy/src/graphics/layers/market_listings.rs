@@ -1,85 +1,49 @@
-use crate::{
-    graphics::{
-        components::{draw_button, draw_horizontal_slider},
-        PowderState,
-    },
-    simulation::store::Store,
+use super::{
+    actor_inspector::render_actor_inspector, flow_graph::render_flow_graph,
+    price_charts::render_price_charts,
 };
-use femtovg::{renderer::OpenGl, Align, Baseline, Canvas, Color, LineCap, LineJoin, Paint};
+use crate::graphics::{
+    components::{draw_horizontal_slider, draw_with_ui, Id, Layout, Rect, Ui},
+    PowderState,
+};
+use femtovg::{renderer::OpenGl, Align, Canvas};
 use powder::Meta;
 
+pub(crate) const TABS: [&str; 4] = ["Listings", "Actors", "Prices", "Flows"];
+
 pub fn render_market_listings(
     canvas: &mut Canvas<OpenGl>,
     meta: &mut Meta,
     state: &mut PowderState,
 ) {
-    // Render tab bar
-    let x = 200.0;
-    let y = 15.0;
     let tab_width = 120.0;
-    let tab_height = 32.0;
-    let text_padding = 15.0;
-    render_tabs(canvas, meta, state, x, y, tab_width, tab_height);
-
-    // Render selected tab
-    let tab = state.dynamic_state.get("market_info_tab");
-    let y = y + tab_height + 25.0;
-    match tab {
-        Some(tab) if tab == "Actors" => {
-            render_actors(canvas, meta, state, x, y, text_padding);
-        }
-        _ => {
-            let width = 500.0;
-            render_listings(canvas, meta, state, x, y, width, text_padding);
-        }
-    }
-}
+    let area = Rect::new(200.0, 15.0, canvas.width() - 220.0, canvas.height() - 30.0);
+    draw_with_ui(canvas, meta, state, |ui, state| {
+        let mut layout = Layout::column(area).with_spacing(25.0);
+        let tab_bar = Rect {
+            width: tab_width * TABS.len() as f32,
+            ..layout.next(32.0)
+        };
+        let tab = ui.tabs(Id::new("market_info_tab"), tab_bar, &TABS);
 
-fn render_tabs(
-    canvas: &mut Canvas<OpenGl>,
-    meta: &mut Meta,
-    state: &mut PowderState,
-    x: f32,
-    y: f32,
-    tab_width: f32,
-    tab_height: f32,
-) {
-    let tabs = vec!["Listings", "Actors"];
-    for (i, tab) in tabs.iter().enumerate() {
-        if draw_button(
-            canvas,
-            meta,
-            state,
-            x + tab_width * i as f32,
-            y,
-            tab_width,
-            tab_height,
-            tab,
-        ) {
-            state
-                .dynamic_state
-                .insert("market_info_tab".to_string(), tab.to_string());
+        // Render selected tab
+        let content = layout.get_remaining();
+        match TABS[tab] {
+            "Actors" => render_actor_inspector(ui, state, content),
+            "Prices" => render_price_charts(ui, state, content),
+            "Flows" => render_flow_graph(ui.canvas, state, content.x, content.y),
+            _ => render_listings(ui, state, content, 500.0, 15.0),
         }
-    }
+    });
 }
 
 fn render_listings(
-    canvas: &mut Canvas<OpenGl>,
-    _meta: &mut Meta,
+    ui: &mut Ui,
     state: &mut PowderState,
-    x: f32,
-    y: f32,
+    area: Rect,
     width: f32,
     text_padding: f32,
 ) {
-    let mut paint = Paint::color(Color::rgbf(1.0, 1.0, 1.0));
-    paint.set_line_cap(LineCap::Butt);
-    paint.set_line_join(LineJoin::Bevel);
-    paint.set_line_width(4.0);
-    paint.set_font_size(14.0);
-    paint.set_font(&[state.font.unwrap()]);
-    paint.set_text_baseline(Baseline::Middle);
-
     let maximum_count = state
         .renderable_state
         .listed_item_kinds
@@ -92,12 +56,12 @@ fn render_listings(
     let maximum_count = maximum_count.unwrap();
 
     for (i, (item_kind, count)) in state.renderable_state.listed_item_kinds.iter().enumerate() {
-        let x = x + 64.0;
-        let vertical_offset = y + i as f32 * 30.0;
-        paint.set_text_align(Align::Right);
-        let _ = canvas.fill_text(x, vertical_offset, format!("{}", item_kind), paint);
+        let x = area.x + 64.0;
+        let vertical_offset = area.y + i as f32 * 30.0;
+        let paint = ui.get_text_paint(Align::Right);
+        ui.fill_text(x, vertical_offset, item_kind, paint);
         draw_horizontal_slider(
-            canvas,
+            ui.canvas,
             x + text_padding,
             vertical_offset,
             width,
@@ -105,98 +69,11 @@ fn render_listings(
             maximum_count as f32,
             *count as f32,
         );
-        paint.set_text_align(Align::Left);
-        let _ = canvas.fill_text(
+        let paint = ui.get_text_paint(Align::Left);
+        ui.fill_text(
             x + text_padding * 2.0 + width,
             vertical_offset,
-            format!("{}", count),
-            paint,
-        );
-    }
-}
-
-fn render_actors(
-    canvas: &mut Canvas<OpenGl>,
-    meta: &mut Meta,
-    state: &mut PowderState,
-    x: f32,
-    y: f32,
-    text_padding: f32,
-) {
-    let indentation = 15.0;
-    let column_width = 240.0;
-
-    let mut paint = Paint::color(Color::rgbf(1.0, 1.0, 1.0));
-    paint.set_font_size(14.0);
-    paint.set_font(&[state.font.unwrap()]);
-    paint.set_text_baseline(Baseline::Middle);
-    paint.set_text_align(Align::Left);
-
-    // Render chosen actor log
-    for (ix, (actor_id, actor_name, actor_log, actor_store)) in
-        state.renderable_state.actor_info.iter().enumerate()
-    {
-        let x = x + text_padding + (ix as f32) * column_width;
-        let _ = canvas.fill_text(x, y, format!("{}", actor_name), paint);
-        let mut vertical_offset = 0.0;
-        for (iy, entry) in actor_log.iter().enumerate() {
-            vertical_offset = y + (iy + 1) as f32 * 30.0;
-            let _ = canvas.fill_text(
-                x + indentation,
-                vertical_offset,
-                format!("{}", entry),
-                paint,
-            );
-        }
-
-        render_store(
-            canvas,
-            x,
-            vertical_offset + 30.0,
-            column_width,
-            text_padding,
-            actor_store,
-        );
-    }
-}
-
-fn render_store(
-    canvas: &mut Canvas<OpenGl>,
-    x: f32,
-    y: f32,
-    width: f32,
-    text_padding: f32,
-    store: &Store,
-) {
-    let mut paint = Paint::color(Color::rgbf(1.0, 1.0, 1.0));
-    paint.set_line_cap(LineCap::Butt);
-    paint.set_line_join(LineJoin::Bevel);
-    paint.set_line_width(4.0);
-    paint.set_font_size(14.0);
-    paint.set_text_baseline(Baseline::Middle);
-
-    let maximum_count = 10;
-
-    for (i, item_kind) in store.get_item_kinds().iter().enumerate() {
-        let count = store.count(&item_kind);
-        let x = x + 64.0;
-        let vertical_offset = y + i as f32 * 30.0;
-        paint.set_text_align(Align::Right);
-        let _ = canvas.fill_text(x, vertical_offset, format!("{}", item_kind), paint);
-        draw_horizontal_slider(
-            canvas,
-            x + text_padding,
-            vertical_offset,
-            width - 128.0,
-            0.0,
-            maximum_count as f32,
-            count as f32,
-        );
-        paint.set_text_align(Align::Left);
-        let _ = canvas.fill_text(
-            x + text_padding * 2.0 + width - 128.0,
-            vertical_offset,
-            format!("{}", count),
+            &count.to_string(),
             paint,
         );
     }
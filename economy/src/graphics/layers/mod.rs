@@ -1,2 +1,7 @@
+pub mod actor_inspector;
 pub mod basic_info;
+pub mod controls;
+pub mod flow_graph;
+pub mod interventions;
 pub mod market_listings;
+pub mod price_charts;
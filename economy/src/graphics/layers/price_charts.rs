@@ -0,0 +1,164 @@
+use crate::{
+    graphics::{
+        components::{draw_axes, draw_line_chart, Id, Layout, Rect, Ui},
+        PowderState,
+    },
+    simulation::prices::PricePoint,
+};
+use femtovg::{Align, Baseline, Color};
+
+///
+/// Selectable time windows in ticks. `None` shows the whole history.
+///
+const WINDOWS: [(&str, Option<usize>); 3] = [
+    ("Last 100", Some(100)),
+    ("Last 500", Some(500)),
+    ("All", None),
+];
+
+pub fn render_price_charts(ui: &mut Ui, state: &mut PowderState, area: Rect) {
+    let control_height = 28.0;
+    let chart_width = 500.0;
+    let chart_height = 140.0;
+    let chart_spacing = 60.0;
+    let axis_padding = 40.0;
+    let (x, y) = (area.x, area.y);
+
+    // Series, each of which can be hidden
+    let series = [
+        ("Last trade", Color::rgbf(1.0, 1.0, 1.0)),
+        ("Best ask", Color::rgbf(1.0, 0.5, 0.5)),
+        ("VWAP", Color::rgbf(0.5, 1.0, 0.5)),
+    ];
+    let mut controls = Layout::row(Rect::new(x + axis_padding, y, area.width, control_height));
+    let accent = ui.theme.accent;
+    let is_shown = series
+        .iter()
+        .map(|(label, color)| {
+            ui.theme.accent = *color;
+            ui.toggle(
+                Id::new("price_chart_series").with(label),
+                controls.next(110.0),
+                label,
+                true,
+            )
+        })
+        .collect::<Vec<_>>();
+    ui.theme.accent = accent;
+
+    // Time window, right of the charts so that its options do not open over them
+    let labels = WINDOWS.map(|(label, _)| label);
+    let window_rect = Rect::new(
+        x + axis_padding + chart_width + 20.0,
+        y,
+        100.0,
+        control_height,
+    );
+    let window = WINDOWS[ui.dropdown(Id::new("price_chart_window"), window_rect, &labels)].1;
+
+    // One chart per item kind
+    let y = y + control_height + 30.0;
+    for (i, (item_kind, history)) in state.renderable_state.price_history.iter().enumerate() {
+        let chart_x = x + axis_padding;
+        let chart_y = y + (chart_height + chart_spacing) * i as f32;
+        let skip = window.map_or(0, |window| history.len().saturating_sub(window));
+        let points = history.iter().skip(skip).collect::<Vec<_>>();
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+
+        let mut paint = ui.get_text_paint(Align::Left);
+        paint.set_text_baseline(Baseline::Bottom);
+        ui.fill_text(chart_x, chart_y - 6.0, item_kind, paint);
+
+        let last_trades = get_last_trades(&points);
+        let best_asks = points
+            .iter()
+            .map(|point| (point.tick as f32, point.best_ask.map(|price| price as f32)))
+            .collect::<Vec<_>>();
+        let vwaps = get_vwaps(&points);
+
+        let x_range = (first.tick as f32, last.tick as f32);
+        let y_range = get_y_range([&last_trades, &best_asks, &vwaps]);
+        draw_axes(
+            ui.canvas,
+            state,
+            chart_x,
+            chart_y,
+            chart_width,
+            chart_height,
+            x_range,
+            y_range,
+        );
+        for (i, values) in [&last_trades, &best_asks, &vwaps].iter().enumerate() {
+            if !is_shown[i] {
+                continue;
+            }
+            draw_line_chart(
+                ui.canvas,
+                chart_x,
+                chart_y,
+                chart_width,
+                chart_height,
+                x_range,
+                y_range,
+                values,
+                series[i].1,
+            );
+        }
+    }
+}
+
+///
+/// Price of the most recent trade at every tick, carried forward over ticks without trades.
+///
+fn get_last_trades(points: &[&PricePoint]) -> Vec<(f32, Option<f32>)> {
+    let mut last_trade = None;
+    points
+        .iter()
+        .map(|point| {
+            last_trade = point.last_trade.or(last_trade);
+            (point.tick as f32, last_trade.map(|price| price as f32))
+        })
+        .collect()
+}
+
+///
+/// Volume weighted average price from the start of the window up to every tick.
+///
+fn get_vwaps(points: &[&PricePoint]) -> Vec<(f32, Option<f32>)> {
+    let mut trade_count = 0;
+    let mut turnover = 0;
+    points
+        .iter()
+        .map(|point| {
+            trade_count += point.trade_count;
+            turnover += point.turnover;
+            let vwap = PricePoint {
+                trade_count,
+                turnover,
+                ..**point
+            }
+            .get_vwap();
+            (point.tick as f32, vwap.map(|vwap| vwap as f32))
+        })
+        .collect()
+}
+
+///
+/// Range covering every value of every series, with a little headroom.
+///
+fn get_y_range(series: [&Vec<(f32, Option<f32>)>; 3]) -> (f32, f32) {
+    let values = series
+        .iter()
+        .flat_map(|values| values.iter().filter_map(|(_, value)| *value));
+    let (min, max) = values.fold((f32::MAX, f32::MIN), |(min, max), value| {
+        (min.min(value), max.max(value))
+    });
+    if min > max {
+        return (0.0, 1.0);
+    }
+    let padding = ((max - min) * 0.1).max(1.0);
+    (min - padding, max + padding)
+}
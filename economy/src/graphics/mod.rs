@@ -1,46 +1,86 @@
-use self::layers::{basic_info::render_basic_info, market_listings::render_market_listings};
-use crate::RenderableState;
+use self::{
+    components::UiState,
+    layers::{
+        basic_info::render_basic_info, controls::render_controls,
+        interventions::render_interventions, market_listings::render_market_listings,
+    },
+};
+use crate::{
+    renderable::StateReceiver,
+    simulation::{control::SimulationCommand, flow::FlowGraph, intervention::InterventionResult},
+    RenderableState,
+};
 use femtovg::FontId;
 use powder::{Powder, RenderLayerFn};
-use std::{collections::HashMap, sync::mpsc::Receiver};
+use std::{
+    collections::HashMap,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+use uuid::Uuid;
 
 mod components;
 mod layers;
+#[cfg(unix)]
+pub mod offscreen;
 
 pub struct PowderState {
     renderable_state: RenderableState,
-    dynamic_state: HashMap<String, String>,
+    ui: UiState,
     font: Option<FontId>,
+    command_tx: Option<Sender<SimulationCommand>>,
+    is_paused: bool,
+    speed: f64,
+    reply_tx: Sender<InterventionResult>,
+    reply_rx: Receiver<InterventionResult>,
+    last_reply: Option<InterventionResult>,
+    selected_actor: Option<Uuid>,
+    flow_sample: Option<FlowGraph>,
+    flow_rates: HashMap<(String, String), f32>,
 }
 impl Default for PowderState {
     fn default() -> Self {
+        let (reply_tx, reply_rx) = channel();
         Self {
             renderable_state: Default::default(),
-            dynamic_state: Default::default(),
+            ui: UiState::default(),
             font: None,
+            command_tx: None,
+            is_paused: false,
+            speed: 1.0,
+            reply_tx,
+            reply_rx,
+            last_reply: None,
+            selected_actor: None,
+            flow_sample: None,
+            flow_rates: HashMap::new(),
         }
     }
 }
 
-pub fn render(rx: Receiver<RenderableState>) {
+pub fn render(rx: StateReceiver, command_tx: Sender<SimulationCommand>) {
     // Define layers for powder renderer
     let layers: Vec<RenderLayerFn<PowderState>> = vec![
         Box::new(render_basic_info),
+        Box::new(render_controls),
+        Box::new(render_interventions),
         Box::new(render_market_listings),
     ];
 
     // Setup powder instance with initial state
-    let mut powder = Powder::new(PowderState::default()).expect("Could not start powder");
+    let mut powder = Powder::new(PowderState {
+        command_tx: Some(command_tx),
+        ..Default::default()
+    })
+    .expect("Could not start powder");
 
     // Load after-init assets
     powder.state.font = Some(powder.load_font("assets/Roboto-Regular.ttf"));
 
     // Push thread receiver layer
     powder.push(Box::new(move |_canvas, _meta, state| {
-        match rx.try_recv() {
-            Ok(new_renderable_state) => state.renderable_state = new_renderable_state,
-            _ => (),
-        };
+        if let Some(diff) = rx.try_recv() {
+            state.renderable_state.apply(diff);
+        }
     }));
 
     // Push custom layers
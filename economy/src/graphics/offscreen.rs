@@ -0,0 +1,120 @@
+use super::{
+    components::Id,
+    layers::{
+        basic_info::render_basic_info, flow_graph::update_flow_rates,
+        market_listings::render_market_listings, market_listings::TABS,
+    },
+    PowderState,
+};
+use crate::renderable::RenderableDiff;
+use femtovg::{renderer::OpenGl, Canvas, Color};
+use glutin::{
+    dpi::PhysicalSize, platform::unix::HeadlessContextExt, Context, ContextBuilder, PossiblyCurrent,
+};
+use powder::{Meta, Position};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+///
+/// Renders the dashboards without a window or GPU, into a software OpenGL context, so that they
+/// can be saved as images from the headless runner.
+///
+pub struct OffscreenRenderer {
+    // Kept alive for as long as the canvas draws into it
+    _context: Context<PossiblyCurrent>,
+    canvas: Canvas<OpenGl>,
+    state: PowderState,
+    width: u32,
+    height: u32,
+}
+impl OffscreenRenderer {
+    pub fn new(width: u32, height: u32) -> Result<Self, String> {
+        let context = ContextBuilder::new()
+            .build_osmesa(PhysicalSize::new(width, height))
+            .map_err(|error| format!("Could not create offscreen context: {}", error))?;
+        let context = unsafe { context.make_current() }
+            .map_err(|(_, error)| format!("Could not use offscreen context: {}", error))?;
+        let renderer = OpenGl::new_from_function(|name| context.get_proc_address(name))
+            .map_err(|error| format!("Could not create offscreen renderer: {:?}", error))?;
+        let mut canvas = Canvas::new(renderer)
+            .map_err(|error| format!("Could not create offscreen canvas: {:?}", error))?;
+        canvas.set_size(width, height, 1.0);
+        let font = canvas
+            .add_font("assets/Roboto-Regular.ttf")
+            .map_err(|error| format!("Could not load font: {:?}", error))?;
+        Ok(Self {
+            _context: context,
+            canvas,
+            state: PowderState {
+                font: Some(font),
+                ..Default::default()
+            },
+            width,
+            height,
+        })
+    }
+    ///
+    /// Applies the changes of a tick. Has to be given every tick for the history in the charts
+    /// and the flow rates to be complete, even when images are only saved at some ticks.
+    ///
+    pub fn update(&mut self, diff: RenderableDiff) {
+        self.state.renderable_state.apply(diff);
+        update_flow_rates(&mut self.state);
+    }
+    ///
+    /// Saves one PNG per market tab, named after the tick and the tab. Returns the paths written.
+    ///
+    pub fn save_dashboards(&mut self, directory: &Path) -> Result<Vec<PathBuf>, String> {
+        let mut paths = Vec::new();
+        for (i, tab) in TABS.iter().enumerate() {
+            *self.state.ui.get::<usize>(Id::new("market_info_tab")) = i;
+            let path = directory.join(format!(
+                "tick-{:06}-{}.png",
+                self.state.renderable_state.tick,
+                tab.to_lowercase()
+            ));
+            self.render();
+            self.save_png(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+    fn render(&mut self) {
+        // The mouse is kept off screen so that nothing is hovered or clicked
+        let mut meta = Meta {
+            mouse_position: Position { x: -1.0, y: -1.0 },
+            is_mouse_released: false,
+            frames_per_second: 0,
+        };
+        self.canvas
+            .clear_rect(0, 0, self.width, self.height, Color::rgbf(0.1, 0.1, 0.12));
+        render_basic_info(&mut self.canvas, &mut meta, &mut self.state);
+        render_market_listings(&mut self.canvas, &mut meta, &mut self.state);
+        self.canvas.flush();
+    }
+    fn save_png(&mut self, path: &Path) -> Result<(), String> {
+        let image = self
+            .canvas
+            .screenshot()
+            .map_err(|error| format!("Could not read back the canvas: {:?}", error))?;
+        let (width, height) = (image.width() as u32, image.height() as u32);
+        let data = image
+            .into_buf()
+            .into_iter()
+            .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+            .collect::<Vec<_>>();
+
+        let file = File::create(path)
+            .map_err(|error| format!("Could not create {}: {}", path.display(), error))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(|error| format!("Could not write {}: {}", path.display(), error))
+    }
+}
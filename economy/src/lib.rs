@@ -1,13 +1,30 @@
 #![feature(is_some_with)]
 
-use simulation::store::Store;
+use renderable::ActorDetail;
+use simulation::{flow::FlowGraph, prices::PricePoint, store::Store};
+use std::collections::{BTreeMap, VecDeque};
 use uuid::Uuid;
+pub mod experiment;
 pub mod graphics;
+pub mod renderable;
+pub mod server;
 pub mod simulation;
+pub mod telemetry;
+pub mod tui;
+
+pub const PRICE_HISTORY_LENGTH: usize = 2000;
 
 #[derive(Default)]
 pub struct RenderableState {
+    pub version: u64,
+    pub tick: u64,
     pub actor_count: usize,
+    pub task_names: Vec<String>,
     pub listed_item_kinds: Vec<(String, usize)>,
     pub actor_info: Vec<(Uuid, String, Vec<String>, Store)>,
+    /// Details of the inspected actor, if any.
+    pub actor_detail: Option<ActorDetail>,
+    pub flow_graph: FlowGraph,
+    /// Prices of every item kind over the last `PRICE_HISTORY_LENGTH` ticks, oldest first.
+    pub price_history: BTreeMap<String, VecDeque<PricePoint>>,
 }
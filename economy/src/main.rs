@@ -1,15 +1,718 @@
+use clap::{Args, Parser, Subcommand};
+use economy::experiment::{run_sweep, sweep::Sweep, write_summary};
+#[cfg(unix)]
+use economy::graphics::offscreen::OffscreenRenderer;
 use economy::graphics::render;
-use economy::simulation::simulate;
-use economy::RenderableState;
-use std::sync::mpsc::channel;
+use economy::renderable::{state_channel, PublishedRevision, RenderableDiff, StateReceiver};
+use economy::server::Server;
+use economy::simulation::control::{Clock, SimulationCommand};
+use economy::simulation::intervention::{parse_script, ScheduledIntervention};
+use economy::simulation::logbook::LogQuery;
+use economy::simulation::metrics::Metrics;
+use economy::simulation::scenario::Scenario;
+use economy::simulation::snapshot::{read_snapshots, write_snapshot, Snapshot};
+use economy::simulation::world::World;
+use economy::simulation::{simulate, SimulationSettings};
+use economy::telemetry::{self, TraceFormat, DEFAULT_FILTER};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{stdout, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use uuid::Uuid;
+
+/// Size of the dashboard images saved by the headless runner.
+#[cfg(unix)]
+const DASHBOARD_SIZE: (u32, u32) = (1600, 1000);
+
+const EXIT_CODES: &str = "EXIT CODES:
+    0     Success
+    1     The simulation failed while running
+    2     Invalid command line usage
+    65    An input file is malformed or invalid
+    66    An input file could not be read
+    73    An output file could not be written";
+
+#[derive(Parser)]
+#[clap(about = "Economy simulation", after_help = EXIT_CODES)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a scenario, in a window or headless
+    Run(RunArgs),
+    /// Play back a recorded event log
+    Replay {
+        /// Event log recorded with `run --record`
+        log: PathBuf,
+        /// Print each tick instead of opening a window
+        #[clap(long)]
+        headless: bool,
+        /// Show the replay in the terminal instead of opening a window
+        #[clap(long, conflicts_with = "headless")]
+        tui: bool,
+        /// Ticks per second
+        #[clap(long, default_value = "1")]
+        tick_rate: f64,
+    },
+    /// Print the actors, accounts and listings of a snapshot
+    Inspect {
+        /// Snapshot or event log
+        file: PathBuf,
+        /// Tick to inspect within an event log, defaults to the last one
+        #[clap(long)]
+        tick: Option<u64>,
+    },
+    /// Export the metrics of every tick of an event log as CSV
+    Export {
+        /// Event log recorded with `run --record`
+        log: PathBuf,
+        /// Write to a file instead of stdout
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Check a scenario file for errors
+    Validate {
+        /// Scenario file
+        scenario: PathBuf,
+    },
+    /// Run a scenario headless once for every parameter combination of a sweep
+    Sweep {
+        /// Base scenario file
+        scenario: PathBuf,
+        /// Sweep definition file
+        sweep: PathBuf,
+        /// Override the number of ticks per run
+        #[clap(long)]
+        ticks: Option<u64>,
+        /// Number of worker threads, defaults to one per core
+        #[clap(long)]
+        threads: Option<usize>,
+        /// Write the summary table to a file instead of stdout
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Args, Default)]
+struct RunArgs {
+    /// Scenario file, defaults to the built-in farmer and packer economy
+    scenario: Option<PathBuf>,
+    /// Override the seed of the scenario
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Stop after this many ticks, runs forever if omitted
+    #[clap(long)]
+    ticks: Option<u64>,
+    /// Run without opening a window
+    #[clap(long, requires = "ticks")]
+    headless: bool,
+    /// Show the simulation in the terminal instead of opening a window
+    #[clap(long, conflicts_with = "headless")]
+    tui: bool,
+    /// Ticks per second, defaults to 1 with a window and unlimited when headless
+    #[clap(long)]
+    tick_rate: Option<f64>,
+    /// Script of interventions to apply at given ticks
+    #[clap(long)]
+    interventions: Option<PathBuf>,
+    /// Record an event log with a snapshot of every tick
+    #[clap(long)]
+    record: Option<PathBuf>,
+    /// Serve an HTTP and WebSocket API for observing and controlling the run on this port of
+    /// localhost
+    #[clap(long)]
+    serve: Option<u16>,
+    /// Write a snapshot of the final tick
+    #[clap(long, requires = "headless")]
+    snapshot: Option<PathBuf>,
+    /// Write the logs of all actors at the final tick as JSON lines
+    #[clap(long, requires = "headless")]
+    actor_log: Option<PathBuf>,
+    /// Save PNG images of the dashboards into this directory
+    #[cfg(unix)]
+    #[clap(long, requires = "headless")]
+    render_dir: Option<PathBuf>,
+    /// Comma separated ticks at which to save the dashboards, defaults to the final tick
+    #[cfg(unix)]
+    #[clap(long, requires = "render-dir", use_value_delimiter = true)]
+    render_at: Vec<u64>,
+    /// Check after every tick that goods and money are conserved and markets are consistent,
+    /// stopping at the first violation
+    #[clap(long)]
+    check_invariants: bool,
+    /// Trace ticks, actors and markets as pretty, json or flame
+    #[clap(long)]
+    trace: Option<TraceFormat>,
+    /// File the json and flame traces are written to
+    #[clap(long, requires = "trace")]
+    trace_file: Option<PathBuf>,
+    /// Spans and events to trace, such as `economy=trace`, defaults to `economy=debug`
+    #[clap(long, requires = "trace")]
+    trace_filter: Option<String>,
+}
+
+///
+/// Dashboards rendered offscreen by the headless runner, saved at chosen ticks. Only on Unix, where
+/// a software OpenGL context can be created without a window.
+///
+#[cfg(unix)]
+struct Dashboards {
+    renderer: OffscreenRenderer,
+    directory: PathBuf,
+    ticks: BTreeSet<u64>,
+    published: HashMap<Uuid, PublishedRevision>,
+}
+
+///
+/// Failure of a command, mapped onto the exit codes listed in the help text.
+///
+enum CliError {
+    Failure(String),
+    Usage(String),
+    InvalidInput(String),
+    UnreadableInput(String),
+    UnwritableOutput(String),
+}
+impl CliError {
+    fn get_exit_code(&self) -> i32 {
+        match self {
+            CliError::Failure(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::InvalidInput(_) => 65,
+            CliError::UnreadableInput(_) => 66,
+            CliError::UnwritableOutput(_) => 73,
+        }
+    }
+}
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Failure(message)
+            | CliError::Usage(message)
+            | CliError::InvalidInput(message)
+            | CliError::UnreadableInput(message)
+            | CliError::UnwritableOutput(message) => write!(f, "error: {}", message),
+        }
+    }
+}
 
 fn main() {
-    let (tx, rx) = channel::<RenderableState>();
+    let cli = Cli::parse();
+    let result = match cli.command {
+        None => run(RunArgs::default()),
+        Some(Command::Run(args)) => run(args),
+        Some(Command::Replay {
+            log,
+            headless,
+            tui,
+            tick_rate,
+        }) => replay(log, headless, tui, tick_rate),
+        Some(Command::Inspect { file, tick }) => inspect(file, tick),
+        Some(Command::Export { log, output }) => export(log, output),
+        Some(Command::Validate { scenario }) => validate(scenario),
+        Some(Command::Sweep {
+            scenario,
+            sweep,
+            ticks,
+            threads,
+            output,
+        }) => sweep_command(scenario, sweep, ticks, threads, output),
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        exit(error.get_exit_code());
+    }
+}
+
+fn run(args: RunArgs) -> Result<(), CliError> {
+    let mut scenario = match args.scenario {
+        Some(path) => load_scenario(&path)?,
+        None => Scenario::default(),
+    };
+    if let Some(seed) = args.seed {
+        scenario.seed = seed;
+    }
+    let filter = args.trace_filter.as_deref().unwrap_or(DEFAULT_FILTER);
+    let _trace = args
+        .trace
+        .map(|format| telemetry::install(format, args.trace_file.as_deref(), filter))
+        .transpose()
+        .map_err(CliError::Failure)?;
+    if let Some(tick_rate) = args.tick_rate {
+        validate_tick_rate(tick_rate)?;
+    }
+    let settings = SimulationSettings {
+        ticks: args.ticks,
+        tick_rate: if args.headless {
+            args.tick_rate
+        } else {
+            args.tick_rate.or(Some(1.0))
+        },
+    };
+    let mut script = match args.interventions {
+        Some(path) => parse_script(&read_file(&path)?)
+            .map_err(|error| CliError::InvalidInput(format!("{}: {}", path.display(), error)))?
+            .into(),
+        None => VecDeque::new(),
+    };
+    let mut recorder = args.record.map(|path| create_file(&path)).transpose()?;
+
+    if args.headless {
+        #[cfg(unix)]
+        let mut dashboards = match args.render_dir {
+            Some(directory) => {
+                let ticks = if args.render_at.is_empty() {
+                    args.ticks.into_iter().collect()
+                } else {
+                    args.render_at.into_iter().collect()
+                };
+                Some(create_dashboards(directory, ticks)?)
+            }
+            None => None,
+        };
+        let (command_tx, command_rx) = channel::<SimulationCommand>();
+        let server = args
+            .serve
+            .map(|port| start_server(port, command_tx))
+            .transpose()?;
+        let mut world = World::new(&scenario);
+        let mut tick_error = None;
+        apply_script(&mut world, &mut script);
+        let on_tick = |world: &mut World| {
+            let result = check_invariants(world, args.check_invariants)
+                .and_then(|_| record_tick(&mut recorder, world));
+            #[cfg(unix)]
+            let result = result.and_then(|_| render_dashboards(&mut dashboards, world));
+            apply_script(world, &mut script);
+            result.map_err(|error| tick_error = Some(error)).is_ok()
+        };
+        let on_update = |world: &World| {
+            if let Some(server) = server.as_ref() {
+                server.publish(world);
+            }
+            true
+        };
+        let control = server.as_ref().map(|_| command_rx);
+        simulate(&mut world, &settings, control, on_tick, on_update);
+        if let Some(error) = tick_error {
+            return Err(error);
+        }
+        if let Some(path) = args.snapshot {
+            let mut writer = create_file(&path)?;
+            write_snapshot(&mut writer, &world.get_snapshot())
+                .map_err(CliError::UnwritableOutput)?;
+        }
+        if let Some(path) = args.actor_log {
+            let mut writer = create_file(&path)?;
+            world
+                .write_actor_logs(&mut writer, &LogQuery::default())
+                .and_then(|_| writer.flush().map_err(|error| error.to_string()))
+                .map_err(CliError::UnwritableOutput)?;
+        }
+        print_metrics(&world.get_metrics());
+        return Ok(());
+    }
+
+    let (tx, rx) = state_channel();
+    let (command_tx, command_rx) = channel::<SimulationCommand>();
+    let server = args
+        .serve
+        .map(|port| start_server(port, command_tx.clone()))
+        .transpose()?;
 
     // Simulation
-    let simulation_thread = thread::spawn(move || simulate(tx));
+    let failure = Arc::new(Mutex::new(None));
+    let simulation_failure = Arc::clone(&failure);
+    let _simulation_thread = thread::spawn(move || {
+        let mut world = World::new(&scenario);
+        apply_script(&mut world, &mut script);
+        let mut published = HashMap::<Uuid, PublishedRevision>::new();
+        let on_tick = |world: &mut World| {
+            let result = check_invariants(world, args.check_invariants)
+                .and_then(|_| record_tick(&mut recorder, world));
+            if let Err(error) = result {
+                *simulation_failure.lock().unwrap() = Some(error);
+                return false;
+            }
+            apply_script(world, &mut script);
+            true
+        };
+        // Publish what changed to be rendered
+        let on_update = |world: &World| {
+            if let Some(server) = server.as_ref() {
+                server.publish(world);
+            }
+            tx.send(world.get_renderable_diff(&mut published)).is_ok()
+        };
+        simulate(&mut world, &settings, Some(command_rx), on_tick, on_update);
+    });
+
+    show(rx, command_tx, args.tui)?;
+    // The simulation stops on its first failure, report it once the view closes
+    let failure = failure.lock().unwrap().take();
+    failure.map_or(Ok(()), Err)
+}
+
+fn replay(log: PathBuf, headless: bool, tui: bool, tick_rate: f64) -> Result<(), CliError> {
+    validate_tick_rate(tick_rate)?;
+    let snapshots = load_snapshots(&log)?;
+
+    if headless {
+        let mut clock = Clock::new(Some(tick_rate), None);
+        for snapshot in snapshots.iter() {
+            clock.wait_for_tick(snapshot.tick.saturating_sub(1), |_| ());
+            println!(
+                "Tick {}: {} trades, {} listings",
+                snapshot.tick,
+                snapshot.trades.len(),
+                snapshot.listings.len()
+            );
+            for trade in snapshot.trades.iter() {
+                println!(
+                    "    {} bought {} from {} for {}",
+                    trade.buyer,
+                    trade.item_kind,
+                    trade.seller.as_deref().unwrap_or("nobody"),
+                    trade.price
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let (tx, rx) = state_channel();
+    let (command_tx, command_rx) = channel::<SimulationCommand>();
+    let _replay_thread = thread::spawn(move || {
+        let mut clock = Clock::new(Some(tick_rate), Some(command_rx));
+        for snapshot in snapshots.iter() {
+            clock.wait_for_tick(snapshot.tick.saturating_sub(1), |command| {
+                if let SimulationCommand::Intervention(request) = command {
+                    request.reply(Err("A replay can not be changed".to_string()))
+                }
+            });
+            let diff = RenderableDiff::from_state(snapshot.get_renderable_state());
+            if tx.send(diff).is_err() {
+                return;
+            }
+        }
+    });
+    show(rx, command_tx, tui)
+}
+
+fn inspect(file: PathBuf, tick: Option<u64>) -> Result<(), CliError> {
+    let snapshots = load_snapshots(&file)?;
+    let snapshot = match tick {
+        Some(tick) => snapshots.iter().find(|snapshot| snapshot.tick == tick),
+        None => snapshots.last(),
+    }
+    .ok_or_else(|| {
+        CliError::InvalidInput(format!("{} has no matching snapshot", file.display()))
+    })?;
+
+    println!(
+        "Scenario '{}' with seed {} at tick {}, interest rate {}",
+        snapshot.scenario, snapshot.seed, snapshot.tick, snapshot.interest_rate
+    );
+
+    println!("\nActors");
+    for actor in snapshot.actors.iter() {
+        println!(
+            "    {:<24} {:>10}  {:<10} {}",
+            actor.name,
+            actor.balance,
+            actor.action,
+            format_counts(actor.store_actual.iter())
+        );
+    }
+
+    println!("\nAccounts");
+    for account in snapshot.accounts.iter() {
+        println!(
+            "    {:<24} {:>10}  {}",
+            account.name, account.balance, account.bank
+        );
+    }
+
+    println!("\nListings");
+    for listing in snapshot.listings.iter() {
+        println!(
+            "    {:<24} {:>10}  {}",
+            listing.item_kind,
+            listing.price,
+            listing.owner.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+fn export(log: PathBuf, output: Option<PathBuf>) -> Result<(), CliError> {
+    let snapshots = load_snapshots(&log)?;
+    let item_kinds = snapshots
+        .iter()
+        .flat_map(|snapshot| snapshot.metrics.get_item_kinds())
+        .collect::<BTreeSet<_>>();
+    let mut writer = create_output(output)?;
+    let mut write = || -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "{}",
+            Metrics::get_csv_columns(&item_kinds).join(",")
+        )?;
+        for snapshot in snapshots.iter() {
+            let values = snapshot.metrics.get_csv_values(&item_kinds);
+            writeln!(writer, "{}", values.join(","))?;
+        }
+        writer.flush()
+    };
+    write().map_err(|error| CliError::UnwritableOutput(format!("Could not export: {}", error)))
+}
+
+fn validate(scenario: PathBuf) -> Result<(), CliError> {
+    let scenario = load_scenario(&scenario)?;
+    let actor_count = scenario
+        .actors
+        .iter()
+        .map(|actor| actor.count)
+        .sum::<usize>();
+    println!(
+        "Scenario '{}' is valid: {} tasks, {} actors",
+        scenario.name,
+        scenario.tasks.len(),
+        actor_count
+    );
+    Ok(())
+}
+
+fn sweep_command(
+    scenario: PathBuf,
+    sweep: PathBuf,
+    ticks: Option<u64>,
+    threads: Option<usize>,
+    output: Option<PathBuf>,
+) -> Result<(), CliError> {
+    let scenario = load_scenario(&scenario)?;
+    let mut sweep = Sweep::parse(&read_file(&sweep)?).map_err(CliError::InvalidInput)?;
+    if let Some(ticks) = ticks {
+        sweep.ticks = ticks;
+    }
+    if let Some(threads) = threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|error| {
+                CliError::Failure(format!("Could not start worker threads: {}", error))
+            })?;
+    }
+
+    let summaries = run_sweep(&scenario, &sweep).map_err(CliError::InvalidInput)?;
+
+    let mut writer = create_output(output)?;
+    write_summary(&mut writer, &summaries)
+        .and_then(|_| writer.flush())
+        .map_err(|error| CliError::UnwritableOutput(format!("Could not write summary: {}", error)))
+}
+
+// Helpers
+///
+/// Shows the renderable state in a window, or in the terminal, until the user closes it.
+///
+fn show(
+    rx: StateReceiver,
+    command_tx: Sender<SimulationCommand>,
+    tui: bool,
+) -> Result<(), CliError> {
+    if tui {
+        return economy::tui::render(rx, command_tx).map_err(CliError::Failure);
+    }
+    render(rx, command_tx);
+    Ok(())
+}
+
+fn validate_tick_rate(tick_rate: f64) -> Result<(), CliError> {
+    if !(tick_rate > 0.0 && tick_rate.is_finite()) {
+        return Err(CliError::Usage(format!(
+            "Tick rate must be a positive number, got {}",
+            tick_rate
+        )));
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<String, CliError> {
+    fs::read_to_string(path).map_err(|error| {
+        CliError::UnreadableInput(format!("Could not read {}: {}", path.display(), error))
+    })
+}
+
+fn load_scenario(path: &Path) -> Result<Scenario, CliError> {
+    Scenario::parse(&read_file(path)?)
+        .map_err(|error| CliError::InvalidInput(format!("{}: {}", path.display(), error)))
+}
+
+fn load_snapshots(path: &Path) -> Result<Vec<Snapshot>, CliError> {
+    let file = File::open(path).map_err(|error| {
+        CliError::UnreadableInput(format!("Could not read {}: {}", path.display(), error))
+    })?;
+    let snapshots = read_snapshots(BufReader::new(file))
+        .map_err(|error| CliError::InvalidInput(format!("{}: {}", path.display(), error)))?;
+    if snapshots.is_empty() {
+        return Err(CliError::InvalidInput(format!(
+            "{} contains no snapshots",
+            path.display()
+        )));
+    }
+    Ok(snapshots)
+}
+
+fn create_file(path: &Path) -> Result<BufWriter<File>, CliError> {
+    File::create(path).map(BufWriter::new).map_err(|error| {
+        CliError::UnwritableOutput(format!("Could not create {}: {}", path.display(), error))
+    })
+}
+
+fn create_output(output: Option<PathBuf>) -> Result<Box<dyn Write>, CliError> {
+    Ok(match output {
+        Some(path) => Box::new(create_file(&path)?),
+        None => Box::new(stdout()),
+    })
+}
+
+fn check_invariants(world: &World, enabled: bool) -> Result<(), CliError> {
+    match enabled {
+        true => world.check_invariants().map_err(|violation| {
+            CliError::Failure(format!(
+                "Invariant violated after tick {}: {}",
+                world.get_tick(),
+                violation
+            ))
+        }),
+        false => Ok(()),
+    }
+}
+
+fn record_tick(recorder: &mut Option<BufWriter<File>>, world: &World) -> Result<(), CliError> {
+    match recorder {
+        Some(writer) => write_snapshot(writer, &world.get_snapshot())
+            .and_then(|_| writer.flush().map_err(|error| error.to_string()))
+            .map_err(CliError::UnwritableOutput),
+        None => Ok(()),
+    }
+}
+
+fn start_server(port: u16, command_tx: Sender<SimulationCommand>) -> Result<Server, CliError> {
+    let server = Server::start(port, command_tx).map_err(CliError::Failure)?;
+    eprintln!("Serving on http://{}", server.get_address());
+    Ok(server)
+}
+
+#[cfg(unix)]
+fn create_dashboards(directory: PathBuf, ticks: BTreeSet<u64>) -> Result<Dashboards, CliError> {
+    fs::create_dir_all(&directory).map_err(|error| {
+        CliError::UnwritableOutput(format!(
+            "Could not create {}: {}",
+            directory.display(),
+            error
+        ))
+    })?;
+    let (width, height) = DASHBOARD_SIZE;
+    Ok(Dashboards {
+        renderer: OffscreenRenderer::new(width, height).map_err(CliError::Failure)?,
+        directory,
+        ticks,
+        published: HashMap::new(),
+    })
+}
+
+///
+/// Updates the offscreen dashboards with the tick and saves them if it is one of the chosen
+/// ticks.
+///
+#[cfg(unix)]
+fn render_dashboards(dashboards: &mut Option<Dashboards>, world: &World) -> Result<(), CliError> {
+    let dashboards = match dashboards {
+        Some(dashboards) => dashboards,
+        None => return Ok(()),
+    };
+    let diff = world.get_renderable_diff(&mut dashboards.published);
+    dashboards.renderer.update(diff);
+    if !dashboards.ticks.contains(&world.get_tick()) {
+        return Ok(());
+    }
+    let paths = dashboards
+        .renderer
+        .save_dashboards(&dashboards.directory)
+        .map_err(CliError::UnwritableOutput)?;
+    for path in paths {
+        eprintln!("Tick {}: saved {}", world.get_tick(), path.display());
+    }
+    Ok(())
+}
+
+///
+/// Applies every scripted intervention which is due by the current tick of the world and reports
+/// the outcome.
+///
+fn apply_script(world: &mut World, script: &mut VecDeque<ScheduledIntervention>) {
+    while script
+        .front()
+        .map_or(false, |scheduled| scheduled.tick <= world.get_tick())
+    {
+        let scheduled = script.pop_front().unwrap();
+        match world.apply_intervention(&scheduled.intervention) {
+            Ok(message) => eprintln!("Tick {}: {}", world.get_tick(), message),
+            Err(reason) => eprintln!("Tick {}: rejected: {}", world.get_tick(), reason),
+        }
+    }
+}
+
+fn print_metrics(metrics: &Metrics) {
+    println!(
+        "Tick {}: {} actors, {} trades worth {}, balances from {} to {}",
+        metrics.tick,
+        metrics.actor_count,
+        metrics.trade_count,
+        metrics.trade_volume,
+        metrics.balance_min,
+        metrics.balance_max
+    );
+    println!(
+        "Held:   {}",
+        format_counts(metrics.held_by_item_kind.iter())
+    );
+    println!(
+        "Listed: {}",
+        format_counts(metrics.listed_by_item_kind.iter())
+    );
+    let revenue = &metrics.total_tax_revenue;
+    let spending = &metrics.total_government_spending;
+    if revenue.get_total() > 0 || spending.get_total() > 0 {
+        println!(
+            "Taxes:  {} ({} sales, {} income, {} listing fees)",
+            revenue.get_total(),
+            revenue.sales_tax,
+            revenue.income_tax,
+            revenue.listing_fees
+        );
+        println!(
+            "Spent:  {} ({} benefits, {} purchases)",
+            spending.get_total(),
+            spending.benefits,
+            spending.purchases
+        );
+    }
+}
 
-    // Graphics
-    render(rx);
+fn format_counts<'a, T: Display + 'a>(counts: impl Iterator<Item = (&'a String, &'a T)>) -> String {
+    counts
+        .map(|(item_kind, count)| format!("{} {}", count, item_kind))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
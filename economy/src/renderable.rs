@@ -0,0 +1,327 @@
+use crate::{
+    simulation::{flow::FlowGraph, prices::PricePoint, store::Store},
+    RenderableState, PRICE_HISTORY_LENGTH,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+pub type ActorInfo = (Uuid, String, Vec<String>, Store);
+
+///
+/// Revisions of the store and log of an actor as last published, to tell which of them changed
+/// since. Logs change nearly every tick, stores far less often.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PublishedRevision {
+    pub store: u64,
+    pub log: u64,
+}
+
+///
+/// Everything about a single actor, published only for the actor being inspected.
+///
+#[derive(Clone, Debug)]
+pub struct ActorDetail {
+    pub id: Uuid,
+    pub name: String,
+    pub balance: i64,
+    /// Amount due on every outstanding loan.
+    pub loans: Vec<u64>,
+    pub action: String,
+    /// Fraction of the task the current action has completed.
+    pub action_progress: Option<f32>,
+    pub store_actual: BTreeMap<String, isize>,
+    pub store_target: BTreeMap<String, isize>,
+    /// Item kind and price of every open listing.
+    pub listings: Vec<(String, i64)>,
+    /// The full log, newest first.
+    pub log: Vec<String>,
+}
+
+///
+/// Changes to the renderable state since the previous diff. Only actors which changed are
+/// included, so publishing a tick does not mean cloning every store in the world.
+///
+#[derive(Default)]
+pub struct RenderableDiff {
+    pub version: u64,
+    pub tick: u64,
+    pub actor_count: usize,
+    pub task_names: Vec<String>,
+    pub listed_item_kinds: Vec<(String, usize)>,
+    /// Actors which are new or whose store changed, by id.
+    pub changed_actors: HashMap<Uuid, ActorInfo>,
+    /// Recent log of actors of which nothing else changed, by id.
+    pub changed_logs: HashMap<Uuid, Vec<String>>,
+    /// Every actor id in order. Only present when actors were added or removed.
+    pub actor_ids: Option<Vec<Uuid>>,
+    pub actor_detail: Option<ActorDetail>,
+    pub flow_graph: FlowGraph,
+    /// Price points since the previous diff. Unlike everything else these are appended to the
+    /// history rather than replacing it.
+    pub price_points: BTreeMap<String, Vec<PricePoint>>,
+}
+impl RenderableDiff {
+    ///
+    /// A diff which replaces the whole state.
+    ///
+    pub fn from_state(state: RenderableState) -> Self {
+        let actor_ids = state.actor_info.iter().map(|info| info.0).collect();
+        Self {
+            version: state.version,
+            tick: state.tick,
+            actor_count: state.actor_count,
+            task_names: state.task_names,
+            listed_item_kinds: state.listed_item_kinds,
+            changed_actors: state
+                .actor_info
+                .into_iter()
+                .map(|info| (info.0, info))
+                .collect(),
+            changed_logs: HashMap::new(),
+            actor_ids: Some(actor_ids),
+            actor_detail: state.actor_detail,
+            flow_graph: state.flow_graph,
+            price_points: state
+                .price_history
+                .into_iter()
+                .map(|(item_kind, points)| (item_kind, points.into()))
+                .collect(),
+        }
+    }
+    ///
+    /// Folds a newer diff into this one, so that applying the result is the same as applying
+    /// both in order.
+    ///
+    fn merge(&mut self, newer: RenderableDiff) {
+        self.version = newer.version;
+        self.tick = newer.tick;
+        self.actor_count = newer.actor_count;
+        self.task_names = newer.task_names;
+        self.listed_item_kinds = newer.listed_item_kinds;
+        for (id, info) in newer.changed_actors {
+            self.changed_logs.remove(&id);
+            self.changed_actors.insert(id, info);
+        }
+        for (id, log) in newer.changed_logs {
+            match self.changed_actors.get_mut(&id) {
+                Some(info) => info.2 = log,
+                None => {
+                    self.changed_logs.insert(id, log);
+                }
+            }
+        }
+        if newer.actor_ids.is_some() {
+            self.actor_ids = newer.actor_ids;
+        }
+        self.actor_detail = newer.actor_detail;
+        self.flow_graph = newer.flow_graph;
+        for (item_kind, points) in newer.price_points {
+            self.price_points
+                .entry(item_kind)
+                .or_default()
+                .extend(points);
+        }
+    }
+}
+
+impl RenderableState {
+    pub fn apply(&mut self, diff: RenderableDiff) {
+        self.version = diff.version;
+        self.tick = diff.tick;
+        self.actor_count = diff.actor_count;
+        self.task_names = diff.task_names;
+        self.listed_item_kinds = diff.listed_item_kinds;
+        self.actor_detail = diff.actor_detail;
+        self.flow_graph = diff.flow_graph;
+        for (item_kind, points) in diff.price_points {
+            let history = self.price_history.entry(item_kind).or_default();
+            history.extend(points);
+            if history.len() > PRICE_HISTORY_LENGTH {
+                history.drain(..history.len() - PRICE_HISTORY_LENGTH);
+            }
+        }
+        let mut changed_actors = diff.changed_actors;
+        match diff.actor_ids {
+            Some(actor_ids) => {
+                let mut existing = self
+                    .actor_info
+                    .drain(..)
+                    .map(|info| (info.0, info))
+                    .collect::<HashMap<_, _>>();
+                self.actor_info = actor_ids
+                    .into_iter()
+                    .filter_map(|id| changed_actors.remove(&id).or_else(|| existing.remove(&id)))
+                    .collect();
+            }
+            None => {
+                for info in self.actor_info.iter_mut() {
+                    if let Some(changed) = changed_actors.remove(&info.0) {
+                        *info = changed;
+                    }
+                }
+            }
+        }
+        let mut changed_logs = diff.changed_logs;
+        for info in self.actor_info.iter_mut() {
+            if let Some(log) = changed_logs.remove(&info.0) {
+                info.2 = log;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    version: u64,
+    pending: Option<RenderableDiff>,
+}
+
+///
+/// The other end of a state channel has been dropped.
+///
+#[derive(Debug)]
+pub struct Disconnected;
+
+///
+/// Publishing end of a latest-value slot. Diffs the renderer has not picked up yet are merged
+/// rather than queued, so a slow renderer never holds up the simulation.
+///
+pub struct StateSender {
+    slot: Arc<Mutex<Slot>>,
+}
+impl StateSender {
+    ///
+    /// Publishes a diff. Fails when the receiving end is gone.
+    ///
+    pub fn send(&self, mut diff: RenderableDiff) -> Result<(), Disconnected> {
+        if Arc::strong_count(&self.slot) < 2 {
+            return Err(Disconnected);
+        }
+        let mut slot = self.slot.lock().unwrap();
+        slot.version += 1;
+        diff.version = slot.version;
+        match slot.pending.as_mut() {
+            Some(pending) => pending.merge(diff),
+            None => slot.pending = Some(diff),
+        }
+        Ok(())
+    }
+}
+
+///
+/// Receiving end of a latest-value slot.
+///
+pub struct StateReceiver {
+    slot: Arc<Mutex<Slot>>,
+}
+impl StateReceiver {
+    ///
+    /// Takes everything published since the last call, if anything was.
+    ///
+    pub fn try_recv(&self) -> Option<RenderableDiff> {
+        self.slot.lock().unwrap().pending.take()
+    }
+}
+
+pub fn state_channel() -> (StateSender, StateReceiver) {
+    let slot = Arc::new(Mutex::new(Slot::default()));
+    (StateSender { slot: slot.clone() }, StateReceiver { slot })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actor(id: Uuid, log: &str) -> ActorInfo {
+        (id, "Actor".to_string(), vec![log.to_string()], Store::new())
+    }
+
+    #[test]
+    fn coalesce() {
+        let (tx, rx) = state_channel();
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let mut diff = RenderableDiff {
+            tick: 1,
+            actor_ids: Some(vec![a, b]),
+            ..Default::default()
+        };
+        diff.changed_actors.insert(a, actor(a, "a1"));
+        diff.changed_actors.insert(b, actor(b, "b1"));
+        assert!(tx.send(diff).is_ok());
+        let mut diff = RenderableDiff {
+            tick: 2,
+            ..Default::default()
+        };
+        diff.changed_actors.insert(b, actor(b, "b2"));
+        assert!(tx.send(diff).is_ok());
+        let mut diff = RenderableDiff {
+            tick: 3,
+            ..Default::default()
+        };
+        diff.changed_logs.insert(a, vec!["a3".to_string()]);
+        assert!(tx.send(diff).is_ok());
+
+        let mut state = RenderableState::default();
+        state.apply(rx.try_recv().unwrap());
+        assert!(rx.try_recv().is_none());
+        assert_eq!(state.version, 3);
+        assert_eq!(state.tick, 3);
+        assert_eq!(state.actor_info.len(), 2);
+        assert_eq!(state.actor_info[0].2, vec!["a3".to_string()]);
+        assert_eq!(state.actor_info[1].2, vec!["b2".to_string()]);
+
+        // Logs apply on their own once the actors are known
+        let mut diff = RenderableDiff::default();
+        diff.changed_logs.insert(b, vec!["b4".to_string()]);
+        state.apply(diff);
+        assert_eq!(state.actor_info[1].2, vec!["b4".to_string()]);
+        assert_eq!(state.actor_info[1].1, "Actor");
+    }
+
+    #[test]
+    fn remove_actor() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let mut state = RenderableState {
+            actor_info: vec![actor(a, "a"), actor(b, "b")],
+            ..Default::default()
+        };
+        state.apply(RenderableDiff {
+            actor_ids: Some(vec![b]),
+            ..Default::default()
+        });
+        assert_eq!(state.actor_info.len(), 1);
+        assert_eq!(state.actor_info[0].0, b);
+    }
+
+    #[test]
+    fn price_history() {
+        let mut state = RenderableState::default();
+        for tick in 0..PRICE_HISTORY_LENGTH as u64 + 10 {
+            let mut diff = RenderableDiff::default();
+            diff.price_points.insert(
+                "Apple".to_string(),
+                vec![PricePoint {
+                    tick,
+                    ..Default::default()
+                }],
+            );
+            state.apply(diff);
+        }
+        let history = &state.price_history["Apple"];
+        assert_eq!(history.len(), PRICE_HISTORY_LENGTH);
+        assert_eq!(history.front().unwrap().tick, 10);
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (tx, rx) = state_channel();
+        drop(rx);
+        assert!(tx.send(RenderableDiff::default()).is_err());
+    }
+}
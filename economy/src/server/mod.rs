@@ -0,0 +1,468 @@
+use crate::simulation::{
+    control::{ControlCommand, SimulationCommand},
+    intervention::{Intervention, InterventionRequest},
+    market::trade::Trade,
+    metrics::Metrics,
+    snapshot::Snapshot,
+    world::World,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Cursor,
+    net::SocketAddr,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tiny_http::{Header, Method, Request, Response};
+
+mod websocket;
+
+///
+/// How long an intervention may wait for the simulation to get to it.
+///
+const INTERVENTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+///
+/// Hosts requests are accepted for and from. A page on any other site could otherwise reach the
+/// server, by rebinding its host name to 127.0.0.1 or by posting from the browser of the user.
+///
+const LOCAL_HOSTS: [&str; 2] = ["localhost", "127.0.0.1"];
+
+///
+/// State of the simulation shared between the simulation thread and the request handlers.
+///
+#[derive(Default)]
+struct Shared {
+    snapshot: Option<Snapshot>,
+    /// Streams of per-tick events, one per connected WebSocket.
+    subscribers: Vec<Sender<String>>,
+}
+
+///
+/// Event sent to every WebSocket after each tick.
+///
+#[derive(Serialize)]
+struct TickEvent<'a> {
+    tick: u64,
+    metrics: &'a Metrics,
+    trades: &'a [Trade],
+}
+
+#[derive(Deserialize)]
+struct SpeedBody {
+    speed: f64,
+}
+
+#[derive(Deserialize)]
+struct JumpBody {
+    tick: u64,
+}
+
+///
+/// Embedded HTTP server on localhost for observing and controlling a running simulation.
+///
+/// `GET /state`, `/actors`, `/accounts`, `/listings`, `/trades` and `/metrics` return the latest
+/// tick as JSON, `GET /events` upgrades to a WebSocket with an event per tick. `POST
+/// /control/pause`, `/control/resume` and `/control/step` control time, as do `/control/speed`
+/// with `{"speed": 2.0}` and `/control/jump` with `{"tick": 500}`. `POST /interventions` applies
+/// an intervention given in the same form as in a script, and replies with the outcome.
+///
+/// Requests have to be for localhost, and if sent from a page, from a page on localhost. Posts
+/// have to be JSON, which a page on another site cannot send without the browser asking first.
+///
+pub struct Server {
+    http: Arc<tiny_http::Server>,
+    shared: Arc<Mutex<Shared>>,
+    address: SocketAddr,
+}
+impl Server {
+    ///
+    /// Starts serving on the given port of localhost. Port 0 picks a free port.
+    ///
+    pub fn start(port: u16, command_tx: Sender<SimulationCommand>) -> Result<Self, String> {
+        let http = tiny_http::Server::http(("127.0.0.1", port))
+            .map_err(|error| format!("Could not serve on port {}: {}", port, error))?;
+        let address = http
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| "Server is not listening on an IP address".to_string())?;
+        let http = Arc::new(http);
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let server = http.clone();
+        let handler_shared = shared.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let shared = handler_shared.clone();
+                let command_tx = command_tx.clone();
+                thread::spawn(move || handle(request, &shared, &command_tx));
+            }
+        });
+
+        Ok(Self {
+            http,
+            shared,
+            address,
+        })
+    }
+    pub fn get_address(&self) -> SocketAddr {
+        self.address
+    }
+    ///
+    /// Makes the world the state served. Sends an event to every WebSocket when a new tick was
+    /// simulated since the last time.
+    ///
+    pub fn publish(&self, world: &World) {
+        let snapshot = world.get_snapshot();
+        let mut shared = self.shared.lock().unwrap();
+        let is_new_tick = shared
+            .snapshot
+            .as_ref()
+            .map_or(true, |previous| previous.tick != snapshot.tick);
+        if is_new_tick && !shared.subscribers.is_empty() {
+            let event = TickEvent {
+                tick: snapshot.tick,
+                metrics: &snapshot.metrics,
+                trades: &snapshot.trades,
+            };
+            let event = serde_json::to_string(&event).unwrap();
+            // Forget the streams whose WebSocket was closed
+            shared
+                .subscribers
+                .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+        shared.snapshot = Some(snapshot);
+    }
+}
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.http.unblock();
+    }
+}
+
+fn handle(mut request: Request, shared: &Mutex<Shared>, command_tx: &Sender<SimulationCommand>) {
+    let method = request.method().clone();
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    if let Err(message) = check_local(&request) {
+        let _ = request.respond(json_response(403, error_json(message)));
+        return;
+    }
+    if method == Method::Get && path == "/events" {
+        let events_rx = {
+            let (events_tx, events_rx) = channel();
+            shared.lock().unwrap().subscribers.push(events_tx);
+            events_rx
+        };
+        return websocket::stream(request, events_rx);
+    }
+
+    let is_json = get_header(&request, "Content-Type").is_some_and(|content_type| {
+        content_type.split(';').next().unwrap_or("").trim() == "application/json"
+    });
+    let mut body = String::new();
+    let (status, json) = match request.as_reader().read_to_string(&mut body) {
+        Err(error) => (400, error_json(&format!("Could not read body: {}", error))),
+        Ok(_) => match method {
+            Method::Get => get(&path, shared),
+            Method::Post if !is_json => (415, error_json("Expected application/json")),
+            Method::Post => post(&path, &body, command_tx),
+            _ => (405, error_json("Method not allowed")),
+        },
+    };
+    let _ = request.respond(json_response(status, json));
+}
+
+fn json_response(status: u16, json: String) -> Response<Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_string(json)
+        .with_status_code(status)
+        .with_header(content_type)
+}
+
+pub(super) fn get_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+///
+/// Checks that the request is for localhost and, if it has an origin, comes from localhost.
+/// Clients other than browsers usually send no origin.
+///
+fn check_local(request: &Request) -> Result<(), &'static str> {
+    if !get_header(request, "Host").is_some_and(is_local) {
+        return Err("Host is not localhost");
+    }
+    let is_local_origin = |origin: &str| {
+        origin
+            .split_once("://")
+            .is_some_and(|(_, host)| is_local(host))
+    };
+    if !get_header(request, "Origin").map_or(true, is_local_origin) {
+        return Err("Origin is not localhost");
+    }
+    Ok(())
+}
+
+///
+/// Whether the host, with or without a port, is one of the local hosts.
+///
+fn is_local(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    LOCAL_HOSTS.contains(&name)
+}
+
+fn get(path: &str, shared: &Mutex<Shared>) -> (u16, String) {
+    let shared = shared.lock().unwrap();
+    let snapshot = match shared.snapshot.as_ref() {
+        Some(snapshot) => snapshot,
+        None => return (503, error_json("The simulation has not started yet")),
+    };
+    let json = match path {
+        "/state" => serde_json::to_string(snapshot),
+        "/actors" => serde_json::to_string(&snapshot.actors),
+        "/accounts" => serde_json::to_string(&snapshot.accounts),
+        "/listings" => serde_json::to_string(&snapshot.listings),
+        "/trades" => serde_json::to_string(&snapshot.trades),
+        "/metrics" => serde_json::to_string(&snapshot.metrics),
+        _ => return (404, error_json("Not found")),
+    };
+    (200, json.unwrap())
+}
+
+fn post(path: &str, body: &str, command_tx: &Sender<SimulationCommand>) -> (u16, String) {
+    let command = match path {
+        "/control/pause" => ControlCommand::Pause,
+        "/control/resume" => ControlCommand::Resume,
+        "/control/step" => ControlCommand::Step,
+        "/control/speed" => match serde_json::from_str::<SpeedBody>(body) {
+            Ok(body) => ControlCommand::SetSpeed(body.speed),
+            Err(error) => return (400, error_json(&error.to_string())),
+        },
+        "/control/jump" => match serde_json::from_str::<JumpBody>(body) {
+            Ok(body) => ControlCommand::JumpToTick(body.tick),
+            Err(error) => return (400, error_json(&error.to_string())),
+        },
+        "/interventions" => return post_intervention(body, command_tx),
+        _ => return (404, error_json("Not found")),
+    };
+    match command_tx.send(command.into()) {
+        Ok(_) => (202, r#"{"accepted":true}"#.to_string()),
+        Err(_) => (503, error_json("The simulation has stopped")),
+    }
+}
+
+///
+/// Sends the intervention to the simulation and waits for it to be applied or rejected.
+///
+fn post_intervention(body: &str, command_tx: &Sender<SimulationCommand>) -> (u16, String) {
+    let intervention = match serde_json::from_str::<Intervention>(body) {
+        Ok(intervention) => intervention,
+        Err(error) => return (400, error_json(&error.to_string())),
+    };
+    let (reply_tx, reply_rx) = channel();
+    let request = InterventionRequest::new(intervention, Some(reply_tx));
+    if command_tx.send(request.into()).is_err() {
+        return (503, error_json("The simulation has stopped"));
+    }
+    match reply_rx.recv_timeout(INTERVENTION_TIMEOUT) {
+        Ok(Ok(message)) => (200, serde_json::json!({ "message": message }).to_string()),
+        Ok(Err(reason)) => (422, error_json(&reason)),
+        Err(_) => (
+            504,
+            error_json("The simulation did not apply the intervention in time"),
+        ),
+    }
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::scenario::Scenario;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        sync::mpsc::{Receiver, TryRecvError},
+    };
+    use tungstenite::{client::IntoClientRequest, http::HeaderValue};
+
+    fn start() -> (Server, Receiver<SimulationCommand>) {
+        let (command_tx, command_rx) = channel();
+        (Server::start(0, command_tx).unwrap(), command_rx)
+    }
+
+    ///
+    /// Minimal HTTP client sending the given headers, each ending in a line break. Returns the
+    /// status code and body.
+    ///
+    fn send(server: &Server, method: &str, path: &str, headers: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(server.get_address()).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\n{}Connection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            headers,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("").to_string();
+        (status, body)
+    }
+
+    fn request(server: &Server, method: &str, path: &str, body: &str) -> (u16, String) {
+        let headers = "Host: localhost\r\nContent-Type: application/json\r\n";
+        send(server, method, path, headers, body)
+    }
+
+    #[test]
+    fn get_state() {
+        let (server, _command_rx) = start();
+        assert_eq!(request(&server, "GET", "/metrics", "").0, 503);
+
+        let mut world = World::new(&Scenario::default());
+        world.tick();
+        server.publish(&world);
+        let (status, body) = request(&server, "GET", "/metrics", "");
+        assert_eq!(status, 200);
+        let metrics: Metrics = serde_json::from_str(&body).unwrap();
+        assert_eq!(metrics.tick, 1);
+        let (status, body) = request(&server, "GET", "/actors", "");
+        assert_eq!(status, 200);
+        let actors: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(actors.as_array().unwrap().len(), metrics.actor_count);
+        assert_eq!(request(&server, "GET", "/unknown", "").0, 404);
+    }
+
+    #[test]
+    fn control() {
+        let (server, command_rx) = start();
+        assert_eq!(request(&server, "POST", "/control/pause", "").0, 202);
+        assert_eq!(
+            request(&server, "POST", "/control/speed", r#"{"speed": 2.5}"#).0,
+            202
+        );
+        assert_eq!(
+            request(&server, "POST", "/control/speed", r#"{"fast": true}"#).0,
+            400
+        );
+        let commands = command_rx.try_iter().collect::<Vec<_>>();
+        assert!(matches!(
+            commands.as_slice(),
+            [
+                SimulationCommand::Control(ControlCommand::Pause),
+                SimulationCommand::Control(ControlCommand::SetSpeed(speed))
+            ] if *speed == 2.5
+        ));
+        assert!(matches!(command_rx.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[test]
+    fn intervention() {
+        let (server, command_rx) = start();
+        let simulation = thread::spawn(move || {
+            let mut world = World::new(&Scenario::default());
+            for command in command_rx.iter().take(2) {
+                if let SimulationCommand::Intervention(request) = command {
+                    let result = world.apply_intervention(&request.intervention);
+                    request.reply(result);
+                }
+            }
+        });
+        let (status, body) = request(
+            &server,
+            "POST",
+            "/interventions",
+            r#"{"type": "grant_money", "account": "Nobody", "amount": 10}"#,
+        );
+        assert_eq!(status, 422, "{}", body);
+        let (status, body) = request(
+            &server,
+            "POST",
+            "/interventions",
+            r#"{"type": "set_interest_rate", "rate": 0.01}"#,
+        );
+        assert_eq!(status, 200, "{}", body);
+        simulation.join().unwrap();
+    }
+
+    #[test]
+    fn only_local() {
+        let (server, command_rx) = start();
+        let address = server.get_address();
+        let get = |headers: &str| send(&server, "GET", "/metrics", headers, "").0;
+        // Not started yet, but let through
+        assert_eq!(get(&format!("Host: {}\r\n", address)), 503);
+        assert_eq!(
+            get("Host: localhost\r\nOrigin: http://localhost:3000\r\n"),
+            503
+        );
+        assert_eq!(get("Host: evil.example\r\n"), 403);
+        assert_eq!(get("Host: localhost.evil.example\r\n"), 403);
+        assert_eq!(get(""), 403);
+        assert_eq!(
+            get("Host: localhost\r\nOrigin: http://evil.example\r\n"),
+            403
+        );
+        assert_eq!(get("Host: localhost\r\nOrigin: null\r\n"), 403);
+
+        let post = |headers: &str| send(&server, "POST", "/control/pause", headers, "").0;
+        assert_eq!(post("Host: localhost\r\n"), 415);
+        assert_eq!(post("Host: localhost\r\nContent-Type: text/plain\r\n"), 415);
+        assert_eq!(
+            post("Host: localhost\r\nContent-Type: application/json; charset=utf-8\r\n"),
+            202
+        );
+        assert_eq!(command_rx.try_iter().count(), 1);
+
+        let url = format!("ws://{}/events", address);
+        let mut events = url.into_client_request().unwrap();
+        let origin = HeaderValue::from_static("http://evil.example");
+        events.headers_mut().insert("Origin", origin);
+        assert!(tungstenite::connect(events).is_err());
+        assert!(server.shared.lock().unwrap().subscribers.is_empty());
+    }
+
+    #[test]
+    fn events() {
+        let (server, _command_rx) = start();
+        let url = format!("ws://{}/events", server.get_address());
+        let (mut socket, _) = tungstenite::connect(url).unwrap();
+
+        let mut world = World::new(&Scenario::default());
+        // Wait for the WebSocket to be subscribed
+        while server.shared.lock().unwrap().subscribers.is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        world.tick();
+        server.publish(&world);
+        // The same tick again, for example after a command while paused, is not a new event
+        server.publish(&world);
+        world.tick();
+        server.publish(&world);
+
+        for tick in [1, 2] {
+            let message = socket.read_message().unwrap();
+            let event: serde_json::Value =
+                serde_json::from_str(message.to_text().unwrap()).unwrap();
+            assert_eq!(event["tick"], tick);
+        }
+    }
+}
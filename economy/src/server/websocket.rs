@@ -0,0 +1,31 @@
+use super::get_header;
+use std::sync::mpsc::Receiver;
+use tiny_http::{Header, Request, Response};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+///
+/// Completes the WebSocket handshake of the request, then forwards every event to the client
+/// until either side goes away.
+///
+pub(super) fn stream(request: Request, events_rx: Receiver<String>) {
+    let key = get_header(&request, "Sec-WebSocket-Key").map(str::to_string);
+    let key = match key {
+        Some(key) => key,
+        None => {
+            let _ = request
+                .respond(Response::from_string("Expected a WebSocket").with_status_code(400));
+            return;
+        }
+    };
+
+    let accept =
+        Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes())).unwrap();
+    let connection = request.upgrade("websocket", Response::empty(101).with_header(accept));
+    let mut socket = WebSocket::from_raw_socket(connection, Role::Server, None);
+    for event in events_rx.iter() {
+        if socket.write_message(Message::Text(event)).is_err() {
+            return;
+        }
+    }
+    let _ = socket.close(None);
+}
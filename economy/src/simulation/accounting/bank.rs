@@ -1,87 +1,170 @@
-use super::{account::Account, loan::Loan, transaction::Transaction};
-use std::{
-    cell::RefCell,
-    rc::{Rc, Weak},
+use super::{
+    account::{Account, AccountId},
+    error::BankError,
+    loan::{Loan, LoanId},
+    transaction::{Transaction, TransactionId},
 };
+use slotmap::SlotMap;
+use tracing::trace;
 
+///
+/// Holds every account, loan and transaction, which refer to each other by id. Lookups of ids
+/// which are not in the bank fail rather than panic.
+///
+#[derive(Clone)]
 pub struct Bank {
-    weak_self: Weak<RefCell<Bank>>,
     name: String,
-    accounts: Vec<Rc<RefCell<Account>>>,
-    loans: Vec<Rc<RefCell<Loan>>>,
-    transactions: Vec<Rc<Transaction>>,
+    accounts: SlotMap<AccountId, Account>,
+    loans: SlotMap<LoanId, Loan>,
+    transactions: SlotMap<TransactionId, Transaction>,
+    interest_rate: f64,
 }
 impl Bank {
-    pub fn new(name: &str) -> Rc<RefCell<Bank>> {
-        Rc::new_cyclic(|bank_weak| {
-            RefCell::new(Self {
-                weak_self: bank_weak.clone(),
-                name: name.to_string(),
-                accounts: Vec::new(),
-                loans: Vec::new(),
-                transactions: Vec::new(),
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            accounts: SlotMap::with_key(),
+            loans: SlotMap::with_key(),
+            transactions: SlotMap::with_key(),
+            interest_rate: 0.0,
+        }
+    }
+    pub fn open_account(&mut self, name: &str) -> AccountId {
+        self.accounts.insert(Account::new(name))
+    }
+    pub(crate) fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    pub(crate) fn get_accounts(&self) -> impl Iterator<Item = (AccountId, &Account)> {
+        self.accounts.iter()
+    }
+    pub(crate) fn get_account(&self, account: AccountId) -> Option<&Account> {
+        self.accounts.get(account)
+    }
+    ///
+    /// Get the balance of the account from all its transactions. Accounts which are not in the
+    /// bank have none.
+    ///
+    pub(crate) fn get_balance(&self, account: AccountId) -> i64 {
+        let transactions = match self.accounts.get(account) {
+            Some(account) => account.get_transactions(),
+            None => return 0,
+        };
+        transactions
+            .iter()
+            .filter_map(|id| self.transactions.get(*id))
+            .fold(0, |acc, transaction| {
+                if transaction.from == account {
+                    acc - transaction.amount as i64
+                } else if transaction.to == account {
+                    acc + transaction.amount as i64
+                } else {
+                    acc
+                }
             })
+    }
+    ///
+    /// Get the amount due on every outstanding loan of the account.
+    ///
+    pub(crate) fn get_loan_dues(&self, account: AccountId) -> Vec<u64> {
+        self.accounts.get(account).map_or(Vec::new(), |account| {
+            account
+                .get_loans()
+                .iter()
+                .filter_map(|loan| self.loans.get(*loan))
+                .map(|loan| loan.get_due())
+                .collect()
         })
     }
-    pub fn open_account(&mut self, name: &str) -> Weak<RefCell<Account>> {
-        let account = Account::new(name, self.weak_self.clone());
-        self.accounts.push(account.clone());
-        Rc::downgrade(&account)
+    pub(crate) fn get_interest_rate(&self) -> f64 {
+        self.interest_rate
     }
-    pub fn issue_loan(
-        &mut self,
-        account: Weak<RefCell<Account>>,
-        amount: u64,
-    ) -> Option<Weak<RefCell<Loan>>> {
-        let account = account.upgrade()?;
-        let loan = Loan::new(self.weak_self.clone(), Rc::downgrade(&account), amount);
-        let weak_loan = Rc::downgrade(&loan);
-        self.loans.push(loan);
-        account.borrow_mut().add_loan(weak_loan.clone());
-        Some(weak_loan)
+    pub(crate) fn set_interest_rate(&mut self, interest_rate: f64) {
+        self.interest_rate = interest_rate;
     }
+    ///
+    /// Charges interest on every outstanding loan.
+    ///
+    pub(crate) fn tick(&mut self) {
+        for loan in self.loans.values_mut() {
+            loan.accrue_interest(self.interest_rate);
+        }
+    }
+    pub fn issue_loan(&mut self, account: AccountId, amount: u64) -> Result<LoanId, BankError> {
+        self.ensure_open(account)?;
+        let loan = self.loans.insert(Loan::new(amount));
+        self.accounts[account].add_loan(loan);
+        Ok(loan)
+    }
+    ///
+    /// Moves the amount from one account to the other. Accounts may run a deficit, so only
+    /// accounts which are not in the bank are turned down.
+    ///
     pub fn process_transaction(
-        from: Weak<RefCell<Account>>,
-        to: Weak<RefCell<Account>>,
+        &mut self,
+        from: AccountId,
+        to: AccountId,
         amount: u64,
-    ) -> bool {
-        let transaction = Transaction::new(from.clone(), to.clone(), amount);
-
-        // Ensure validity of accounts
-        let from_account = from.upgrade();
-        let to_account = to.upgrade();
-        if from_account.is_none() || to_account.is_none() {
-            return false;
+    ) -> Result<TransactionId, BankError> {
+        self.ensure_open(from)?;
+        self.ensure_open(to)?;
+        trace!(
+            from = %self.accounts[from].get_name(),
+            to = %self.accounts[to].get_name(),
+            amount,
+            "transaction"
+        );
+        let transaction = self.transactions.insert(Transaction::new(from, to, amount));
+        self.accounts[from].add_transaction(transaction);
+        self.accounts[to].add_transaction(transaction);
+        Ok(transaction)
+    }
+    ///
+    /// Checks that every transaction is recorded by both its accounts and that no money appeared
+    /// or vanished, so that all balances add up to zero. Money is only created by paying it out
+    /// of an account, which goes into deficit by as much.
+    ///
+    pub(crate) fn check(&self) -> Result<(), String> {
+        for (id, account) in self.accounts.iter() {
+            for transaction in account.get_transactions() {
+                match self.transactions.get(*transaction) {
+                    Some(Transaction { from, to, .. }) if *from == id || *to == id => {}
+                    _ => {
+                        return Err(format!(
+                            "Account {} records {:?} which is not its own",
+                            account.get_name(),
+                            transaction
+                        ))
+                    }
+                }
+            }
         }
-        let from_account = from_account.unwrap();
-        let to_account = to_account.unwrap();
-
-        // Ensure validity of banks
-        let from_bank = from_account.borrow_mut().get_bank().upgrade();
-        let to_bank = to_account.borrow_mut().get_bank().upgrade();
-        if from_bank.is_none() || to_bank.is_none() {
-            return false;
+        for (id, transaction) in self.transactions.iter() {
+            for account in [transaction.from, transaction.to] {
+                let is_recorded = self
+                    .accounts
+                    .get(account)
+                    .is_some_and(|account| account.get_transactions().contains(&id));
+                if !is_recorded {
+                    return Err(format!("{:?} is missing from {:?}", id, account));
+                }
+            }
+        }
+        let total = self
+            .accounts
+            .keys()
+            .map(|account| self.get_balance(account))
+            .sum::<i64>();
+        match total {
+            0 => Ok(()),
+            total => Err(format!("Balances add up to {} rather than 0", total)),
+        }
+    }
+    fn ensure_open(&self, account: AccountId) -> Result<(), BankError> {
+        match self.accounts.contains_key(account) {
+            true => Ok(()),
+            false => Err(BankError::AccountClosed(account)),
         }
-        let from_bank = from_bank.unwrap();
-        let to_bank = to_bank.unwrap();
-
-        // Add strong references to banks
-        from_bank
-            .borrow_mut()
-            .transactions
-            .push(transaction.clone());
-        to_bank.borrow_mut().transactions.push(transaction.clone());
-
-        // Add weak references to accounts
-        from_account
-            .borrow_mut()
-            .add_transaction(Rc::downgrade(&transaction));
-        to_account
-            .borrow_mut()
-            .add_transaction(Rc::downgrade(&transaction));
-
-        // Return success
-        true
     }
 }
 
@@ -91,54 +174,83 @@ mod tests {
 
     #[test]
     fn open_account() {
-        let bank = Bank::new("Federal Reserve");
-        let account = bank.borrow_mut().open_account("Jeff");
+        let mut bank = Bank::new("Federal Reserve");
+        let account = bank.open_account("Jeff");
         assert_eq!(
-            account.upgrade().unwrap().borrow().get_name(),
+            bank.get_account(account).unwrap().get_name(),
             "Jeff".to_string()
         );
-        assert_eq!(bank.borrow().accounts.len(), 1);
+        assert_eq!(bank.accounts.len(), 1);
     }
 
     #[test]
     fn issue_loan() {
-        let bank = Bank::new("Federal Reserve");
-        let account = bank.borrow_mut().open_account("Jeff");
-        let loan = bank.borrow_mut().issue_loan(account, 500);
-        assert!(loan.is_some());
-        assert_eq!(loan.unwrap().upgrade().unwrap().borrow().get_due(), 500);
+        let mut bank = Bank::new("Federal Reserve");
+        let account = bank.open_account("Jeff");
+        let loan = bank.issue_loan(account, 500);
+        assert!(loan.is_ok());
+        assert_eq!(bank.loans[loan.unwrap()].get_due(), 500);
+        assert_eq!(bank.get_loan_dues(account), vec![500]);
+        // Loans can only go to accounts of the bank
+        assert_eq!(
+            bank.issue_loan(AccountId::default(), 500),
+            Err(BankError::AccountClosed(AccountId::default()))
+        );
+    }
+
+    #[test]
+    fn accrue_interest() {
+        let mut bank = Bank::new("Federal Reserve");
+        let account = bank.open_account("Jeff");
+        let loan = bank.issue_loan(account, 500).unwrap();
+        bank.tick();
+        assert_eq!(bank.loans[loan].get_due(), 500);
+        bank.set_interest_rate(0.1);
+        bank.tick();
+        bank.tick();
+        assert_eq!(bank.loans[loan].get_due(), 605);
+    }
+
+    #[test]
+    fn overdraft() {
+        let mut bank = Bank::new("Federal Reserve");
+        let payer = bank.open_account("Jeff");
+        let payee = bank.open_account("Ben");
+        // Payments go through whatever the balance, and deficits grow as they do
+        assert!(bank.process_transaction(payer, payee, 500).is_ok());
+        assert!(bank.process_transaction(payer, payee, 1_000_000).is_ok());
+        assert_eq!(bank.get_balance(payer), -1_000_500);
+        assert_eq!(bank.get_balance(payee), 1_000_500);
+        assert_eq!(bank.check(), Ok(()));
     }
 
     #[test]
     fn process_transaction() {
-        let bank_a = Bank::new("Federal Reserve");
-        let bank_b = Bank::new("Bank of America");
-        let fed_account = bank_a.borrow_mut().open_account("FED");
-        let boa_account = bank_b.borrow_mut().open_account("BOA");
-        let was_transaction_success =
-            Bank::process_transaction(fed_account.clone(), boa_account.clone(), 500);
-        assert!(was_transaction_success);
-        assert_eq!(bank_a.borrow().transactions.len(), 1);
-        assert_eq!(bank_b.borrow().transactions.len(), 1);
-        assert_eq!(fed_account.upgrade().unwrap().borrow().get_balance(), -500);
-        assert_eq!(boa_account.upgrade().unwrap().borrow().get_balance(), 500);
-        assert_eq!(
-            fed_account
-                .upgrade()
-                .unwrap()
-                .borrow()
-                .get_transactions()
-                .len(),
-            1
-        );
+        let mut bank = Bank::new("Federal Reserve");
+        let fed_account = bank.open_account("FED");
+        let boa_account = bank.open_account("BOA");
+        let transaction = bank.process_transaction(fed_account, boa_account, 500);
+        assert!(transaction.is_ok());
+        assert_eq!(bank.transactions.len(), 1);
+        assert_eq!(bank.get_balance(fed_account), -500);
+        assert_eq!(bank.get_balance(boa_account), 500);
+        assert_eq!(bank.accounts[fed_account].get_transactions().len(), 1);
+        assert_eq!(bank.accounts[boa_account].get_transactions().len(), 1);
+
+        // Unknown accounts are turned down
         assert_eq!(
-            boa_account
-                .upgrade()
-                .unwrap()
-                .borrow()
-                .get_transactions()
-                .len(),
-            1
+            bank.process_transaction(fed_account, AccountId::default(), 500),
+            Err(BankError::AccountClosed(AccountId::default()))
         );
+        assert_eq!(bank.transactions.len(), 1);
+        assert_eq!(bank.get_balance(AccountId::default()), 0);
+        assert_eq!(bank.check(), Ok(()));
+
+        // Transactions only one side knows of make money out of nothing
+        let transaction = bank
+            .transactions
+            .insert(Transaction::new(fed_account, boa_account, 5));
+        bank.accounts[boa_account].add_transaction(transaction);
+        assert!(bank.check().unwrap_err().contains("missing"));
     }
 }
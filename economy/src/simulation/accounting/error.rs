@@ -0,0 +1,24 @@
+use super::account::AccountId;
+use std::fmt::Display;
+
+///
+/// Why the bank turned a request down. Only closed accounts are, as there is no
+/// `InsufficientFunds` or `BankUnavailable` by design:
+///
+/// - Accounts may run a deficit without limit. Buyers pay on credit and the treasury spends
+///   before it collects, so turning down payments for a lack of funds would stall the economy.
+///   Actors see their balance and keep it in check themselves.
+/// - Every world holds exactly one bank for as long as it runs, so the bank is never away.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BankError {
+    /// The account is not, or no longer, held by the bank.
+    AccountClosed(AccountId),
+}
+impl Display for BankError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BankError::AccountClosed(account) => write!(f, "account {:?} is closed", account),
+        }
+    }
+}
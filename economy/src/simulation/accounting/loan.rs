@@ -1,27 +1,24 @@
-use super::{account::Account, bank::Bank};
-use std::{
-    cell::RefCell,
-    rc::{Rc, Weak},
-};
+use slotmap::new_key_type;
 
+new_key_type! {
+    pub struct LoanId;
+}
+
+#[derive(Clone)]
 pub struct Loan {
-    issuer: Weak<RefCell<Bank>>,
-    to: Weak<RefCell<Account>>,
     due: u64,
 }
 impl Loan {
-    pub fn new(
-        issuer: Weak<RefCell<Bank>>,
-        to: Weak<RefCell<Account>>,
-        amount: u64,
-    ) -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Self {
-            issuer,
-            to,
-            due: amount,
-        }))
+    pub fn new(amount: u64) -> Self {
+        Self { due: amount }
     }
     pub(super) fn get_due(&self) -> u64 {
         self.due
     }
+    ///
+    /// Adds interest to the amount due, rounded to the nearest whole unit.
+    ///
+    pub(super) fn accrue_interest(&mut self, rate: f64) {
+        self.due += (self.due as f64 * rate).round() as u64;
+    }
 }
@@ -1,4 +1,5 @@
 pub(super) mod account;
 pub(super) mod bank;
+pub(super) mod error;
 pub(super) mod loan;
 pub(super) mod transaction;
@@ -1,32 +1,18 @@
-use super::account::Account;
-use std::{
-    cell::RefCell,
-    fmt::Debug,
-    rc::{Rc, Weak},
-};
+use super::account::AccountId;
+use slotmap::new_key_type;
 
+new_key_type! {
+    pub struct TransactionId;
+}
+
+#[derive(Clone, Debug)]
 pub struct Transaction {
-    pub(super) from: Weak<RefCell<Account>>,
-    pub(super) to: Weak<RefCell<Account>>,
+    pub(super) from: AccountId,
+    pub(super) to: AccountId,
     pub(super) amount: u64,
 }
 impl Transaction {
-    pub(super) fn new(
-        from: Weak<RefCell<Account>>,
-        to: Weak<RefCell<Account>>,
-        amount: u64,
-    ) -> Rc<Self> {
-        Rc::new(Transaction { from, to, amount })
-    }
-}
-impl Debug for Transaction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Transaction of {} from {} to {}",
-            self.amount,
-            self.from.upgrade().unwrap().borrow().get_name(),
-            self.to.upgrade().unwrap().borrow().get_name(),
-        )
+    pub(super) fn new(from: AccountId, to: AccountId, amount: u64) -> Self {
+        Transaction { from, to, amount }
     }
 }
@@ -1,33 +1,252 @@
 use super::{
-    accounting::account::Account,
-    actor::Actor,
+    accounting::{account::AccountId, bank::Bank},
+    actor::{ActorId, ActorView},
     book::Book,
-    logbook::LogBook,
-    market::{listing::Listing, Market},
+    flow::TaskFlow,
+    geography::{Geography, Journey},
+    logbook::{Category, LogBook, LogFields, Severity},
+    market::{bid::BidResult, error::MarketError, listing::Owner, Market},
     store::Store,
     task::Task,
 };
-use std::{cell::RefCell, rc::Weak};
 
+pub(super) mod trade_action;
 pub(super) mod work_action;
 
+///
+/// What an actor decided to do during a tick. Intents are decided by every actor at once against
+/// the world as it was at the start of the tick, and carried out one actor at a time afterwards.
+/// Whatever an earlier actor took is no longer there for a later one.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Intent {
+    /// Take up to the count of own listings of the item kind off the market.
+    TakeBack { item_kind: String, count: isize },
+    /// Buy up to the count of the item kind at up to the limit each, or bid for them at auction.
+    Buy {
+        item_kind: String,
+        count: usize,
+        limit: i64,
+    },
+    /// Withdraw every open bid.
+    CancelBids,
+    /// List up to the count of the item kind at the price.
+    List {
+        item_kind: String,
+        count: isize,
+        price: i64,
+        ttl: Option<u64>,
+    },
+    /// Move every own listing in the market to the price.
+    Reprice { price: i64 },
+    /// Set off to another market, paying the carrier the fare. Stays put if the fare can not be
+    /// paid.
+    Travel {
+        destination: String,
+        ticks: u64,
+        fare: u64,
+    },
+    /// Put work into the task, using up its inputs first if they were not yet.
+    Produce,
+}
+
 pub(super) enum ActionResult {
     InProgress,
     Done(Box<dyn Action>),
 }
 pub(super) struct ActionPayload<'a> {
-    pub(super) actor_weak: Weak<RefCell<Actor>>,
+    pub(super) actor_id: ActorId,
     pub(super) name: &'a mut String,
     pub(super) log: &'a mut LogBook,
-    pub(super) account: &'a mut Weak<RefCell<Account>>,
+    pub(super) account: AccountId,
+    pub(super) bank: &'a mut Bank,
     pub(super) book: &'a mut Book,
-    pub(super) submitted_listings: &'a mut Vec<Weak<Listing>>,
     pub(super) store_actual: &'a mut Store,
     pub(super) store_target: &'a mut Store,
     pub(super) market: &'a mut Market,
+    pub(super) location: &'a str,
+    pub(super) journey: &'a mut Option<Journey>,
+    pub(super) geography: &'a Geography,
+    pub(super) carrier_account: AccountId,
     pub(super) task: &'a Option<Task>,
+    pub(super) flow: &'a mut TaskFlow,
 }
-pub(super) trait Action {
-    fn tick(&mut self, payload: ActionPayload) -> ActionResult;
+impl ActionPayload<'_> {
+    ///
+    /// Carries out an intent which does not depend on the action, against the market as it is
+    /// now. Returns how many of the items the intent is about were dealt with, which may be less
+    /// than intended if others got to the market first. Travelling deals with one journey.
+    ///
+    pub(super) fn resolve(&mut self, intent: &Intent) -> isize {
+        match intent {
+            Intent::TakeBack { item_kind, count } => {
+                let own_listings = self
+                    .market
+                    .get_listings_of_owner(self.actor_id)
+                    .filter(|listing| listing.item.kind == *item_kind)
+                    .take(*count as usize)
+                    .map(|listing| listing.id)
+                    .collect::<Vec<_>>();
+                let mut dealt_with = 0;
+                for id in own_listings {
+                    if let Ok(item) = self.market.cancel_listing(id) {
+                        self.store_actual.add_item(item);
+                        dealt_with += 1;
+                    }
+                }
+                dealt_with
+            }
+            Intent::Buy {
+                item_kind,
+                count,
+                limit,
+            } => {
+                let (mut bought, mut placed) = (0, 0);
+                for _ in 0..*count {
+                    match self.market.place_bid(
+                        self.actor_id,
+                        self.account,
+                        item_kind,
+                        *limit,
+                        self.bank,
+                    ) {
+                        BidResult::Bought(item) => {
+                            self.store_actual.add_item(item);
+                            bought += 1;
+                        }
+                        BidResult::Placed(_) => placed += 1,
+                        // Others got to the cheaper listings first, trying again next tick
+                        BidResult::Rejected(MarketError::NothingAtLimit { .. }) => break,
+                        BidResult::Rejected(error) => {
+                            self.log.add(
+                                Severity::Warning,
+                                Category::Trade,
+                                format!("Could not buy {}: {}", item_kind, error),
+                                LogFields::item(item_kind).with_price(*limit),
+                            );
+                            break;
+                        }
+                    }
+                }
+                if bought > 0 {
+                    self.log.add(
+                        Severity::Info,
+                        Category::Trade,
+                        format!("Bought {} of {} at up to {}", bought, item_kind, limit),
+                        LogFields::item(item_kind)
+                            .with_count(bought)
+                            .with_price(*limit),
+                    );
+                }
+                if placed > 0 {
+                    self.log.add(
+                        Severity::Debug,
+                        Category::Trade,
+                        format!("Bid for {} of {} at up to {}", placed, item_kind, limit),
+                        LogFields::item(item_kind)
+                            .with_count(placed)
+                            .with_price(*limit),
+                    );
+                }
+                bought + placed
+            }
+            Intent::CancelBids => {
+                let count = self.market.get_bids_of(self.actor_id).count();
+                self.market.cancel_bids_of(self.actor_id);
+                count as isize
+            }
+            Intent::List {
+                item_kind,
+                count,
+                price,
+                ttl,
+            } => {
+                // Hand the items over to the market, which holds them until sold
+                let items = self.store_actual.take_items(item_kind, *count);
+                let listed = items.len() as isize;
+                let owner = Owner {
+                    actor: self.actor_id,
+                    account: self.account,
+                    name: self.name.clone(),
+                    is_producer: self.task.as_ref().is_some_and(|task| {
+                        task.outputs.iter().any(|(output, _)| output == item_kind)
+                    }),
+                };
+                for item in items {
+                    self.market
+                        .list_item(Some(owner.clone()), item, *price, *ttl, self.bank);
+                }
+                listed
+            }
+            Intent::Reprice { price } => {
+                let own_listings = self
+                    .market
+                    .get_listings_of_owner(self.actor_id)
+                    .map(|listing| listing.id)
+                    .collect::<Vec<_>>();
+                own_listings
+                    .into_iter()
+                    .filter(|id| self.market.reprice_listing(*id, *price).is_ok())
+                    .count() as isize
+            }
+            Intent::Travel {
+                destination,
+                ticks,
+                fare,
+            } => {
+                if *fare > 0 {
+                    if let Err(error) =
+                        self.bank
+                            .process_transaction(self.account, self.carrier_account, *fare)
+                    {
+                        self.log.add(
+                            Severity::Warning,
+                            Category::Finance,
+                            format!("Could not pay fare of {}: {}", fare, error),
+                            LogFields::market(destination).with_price(*fare as i64),
+                        );
+                        return 0;
+                    }
+                }
+                *self.journey = Some(Journey {
+                    destination: destination.clone(),
+                    ticks_left: *ticks,
+                });
+                1
+            }
+            // Only the action knows how to work its task
+            Intent::Produce => 0,
+        }
+    }
+}
+///
+/// Actions decide in parallel and are carried out one after the other, so they must be free of
+/// shared state.
+///
+pub(super) trait Action: Send + Sync {
+    ///
+    /// Decides what the actor does this tick from its state and the prices at the start of the
+    /// tick. Must not depend on anything else, so that every actor can decide at once.
+    ///
+    fn decide(&self, view: &ActorView, geography: &Geography) -> Vec<Intent>;
+    ///
+    /// Carries out the intents decided on this tick, in turn with the other actors.
+    ///
+    fn tick(&mut self, payload: ActionPayload, intents: Vec<Intent>) -> ActionResult;
     fn get_name(&self) -> String;
+    ///
+    /// Work points put into the task so far, for actions which work towards a task.
+    ///
+    fn get_progress_points(&self) -> Option<u64> {
+        None
+    }
+    ///
+    /// Copies the action, so that the actor and the world it is in can be copied.
+    ///
+    fn clone_box(&self) -> Box<dyn Action>;
+}
+impl Clone for Box<dyn Action> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
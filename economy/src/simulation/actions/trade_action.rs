@@ -0,0 +1,310 @@
+use super::{Action, ActionPayload, ActionResult, Intent};
+use crate::simulation::{
+    actor::ActorView,
+    geography::Geography,
+    logbook::{Category, LogFields, Severity},
+};
+use std::mem;
+
+///
+/// Most items carried on one trip.
+///
+const CARGO_SIZE: usize = 5;
+///
+/// Markup on what carried items cost to buy and bring to market.
+///
+const MARGIN: f64 = 0.1;
+
+#[derive(Clone)]
+enum Stage {
+    /// Looking for a market worth carrying the item kind to.
+    Idle,
+    /// Bids were placed for the cargo at up to the given price.
+    Buying { destination: String, price: i64 },
+    /// On the way, each item having cost the given amount to buy and carry.
+    Carrying { destination: String, unit_cost: i64 },
+}
+
+///
+/// Buys an item kind in the market the actor is in and carries it to a connected market where it
+/// sells for more than it cost to buy and carry there. Markets where the item kind is not listed
+/// at all are always worth a trip.
+///
+#[derive(Clone)]
+pub struct TradeAction {
+    item_kind: String,
+    stage: Stage,
+}
+impl TradeAction {
+    pub fn new(item_kind: &str) -> Self {
+        Self {
+            item_kind: item_kind.to_string(),
+            stage: Stage::Idle,
+        }
+    }
+    ///
+    /// Picks the connected market with the largest profit per item, if any is profitable.
+    ///
+    fn choose_destination(
+        &self,
+        geography: &Geography,
+        location: &str,
+        price: i64,
+    ) -> Option<String> {
+        geography
+            .get_routes_from(location)
+            .filter_map(|(destination, route)| {
+                let cost = price + route.cost as i64;
+                let profit = match geography.get_best_ask(destination, &self.item_kind) {
+                    Some(best_ask) => best_ask - cost,
+                    None => get_selling_price(cost) - cost,
+                };
+                (profit > 0).then(|| (profit, destination))
+            })
+            .max_by_key(|(profit, _)| *profit)
+            .map(|(_, destination)| destination.to_string())
+    }
+    ///
+    /// Picks the connected market where the item kind is listed cheapest, if it is listed in any.
+    ///
+    fn choose_source(&self, geography: &Geography, location: &str) -> Option<(String, u64)> {
+        geography
+            .get_routes_from(location)
+            .filter_map(|(source, route)| {
+                let best_ask = geography.get_best_ask(source, &self.item_kind)?;
+                Some((best_ask, source, route.ticks))
+            })
+            .min_by_key(|(best_ask, _, _)| *best_ask)
+            .map(|(_, source, ticks)| (source.to_string(), ticks))
+    }
+}
+
+impl Action for TradeAction {
+    fn decide(&self, view: &ActorView, geography: &Geography) -> Vec<Intent> {
+        match &self.stage {
+            Stage::Idle => {
+                let destination = geography
+                    .get_best_ask(&view.location, &self.item_kind)
+                    .and_then(|price| {
+                        self.choose_destination(geography, &view.location, price)?;
+                        Some(price)
+                    });
+                match destination {
+                    Some(price) => vec![Intent::Buy {
+                        item_kind: self.item_kind.clone(),
+                        count: CARGO_SIZE,
+                        limit: price,
+                    }],
+                    // Nothing worth carrying from here, go where there is
+                    None => self
+                        .choose_source(geography, &view.location)
+                        .map(|(source, ticks)| Intent::Travel {
+                            destination: source,
+                            ticks,
+                            fare: 0,
+                        })
+                        .into_iter()
+                        .collect(),
+                }
+            }
+            Stage::Buying { destination, .. } => {
+                // Leave with whatever was bought so far
+                let cargo = view.store_actual.count(&self.item_kind);
+                let route = geography
+                    .get_routes_from(&view.location)
+                    .find(|(candidate, _)| candidate == destination)
+                    .map(|(_, route)| route);
+                let mut intents = vec![Intent::CancelBids];
+                if let Some(route) = route.filter(|_| cargo > 0) {
+                    intents.push(Intent::Travel {
+                        destination: destination.clone(),
+                        ticks: route.ticks,
+                        fare: route.cost * cargo as u64,
+                    });
+                }
+                intents
+            }
+            Stage::Carrying {
+                destination,
+                unit_cost,
+            } if view.location == *destination => {
+                // Bring what is still unsold here from earlier trips to the same price, listings
+                // in other markets are left alone by this one
+                let price = get_selling_price(*unit_cost);
+                let mut intents = vec![Intent::Reprice { price }];
+                let cargo = view.store_actual.count(&self.item_kind);
+                if cargo > 0 {
+                    intents.push(Intent::List {
+                        item_kind: self.item_kind.clone(),
+                        count: cargo,
+                        price,
+                        ttl: None,
+                    });
+                }
+                intents
+            }
+            Stage::Carrying { .. } => Vec::new(),
+        }
+    }
+    fn tick(&mut self, mut payload: ActionPayload, intents: Vec<Intent>) -> ActionResult {
+        for intent in intents.iter() {
+            let dealt_with = payload.resolve(intent);
+            self.stage = match (mem::replace(&mut self.stage, Stage::Idle), intent) {
+                (Stage::Idle, Intent::Buy { limit, .. }) => {
+                    let destination =
+                        self.choose_destination(payload.geography, payload.location, *limit);
+                    let destination = match destination {
+                        Some(destination) => destination,
+                        None => {
+                            payload.resolve(&Intent::CancelBids);
+                            payload.log.add(
+                                Severity::Warning,
+                                Category::Decision,
+                                format!("No market left worth carrying {} to", self.item_kind),
+                                LogFields::item(&self.item_kind).with_price(*limit),
+                            );
+                            continue;
+                        }
+                    };
+                    payload.log.add(
+                        Severity::Info,
+                        Category::Decision,
+                        format!(
+                            "Buying {} at up to {} for {}",
+                            self.item_kind, limit, destination
+                        ),
+                        LogFields::item(&self.item_kind)
+                            .with_price(*limit)
+                            .with_market(&destination),
+                    );
+                    Stage::Buying {
+                        destination,
+                        price: *limit,
+                    }
+                }
+                (Stage::Idle, Intent::Travel { destination, .. }) => {
+                    payload.log.add(
+                        Severity::Info,
+                        Category::Decision,
+                        format!("Travelling to {} for {}", destination, self.item_kind),
+                        LogFields::item(&self.item_kind).with_market(destination),
+                    );
+                    Stage::Idle
+                }
+                // Nothing bought, or no way to the destination
+                (Stage::Buying { .. }, Intent::CancelBids) if intents.len() == 1 => Stage::Idle,
+                // The fare could not be paid, try again next tick
+                (stage @ Stage::Buying { .. }, Intent::Travel { .. }) if dealt_with == 0 => stage,
+                (
+                    Stage::Buying { price, .. },
+                    Intent::Travel {
+                        destination, fare, ..
+                    },
+                ) => {
+                    let cargo = payload.store_actual.count(&self.item_kind);
+                    payload.log.add(
+                        Severity::Info,
+                        Category::Decision,
+                        format!(
+                            "Carrying {} of {} to {}",
+                            cargo, self.item_kind, destination
+                        ),
+                        LogFields::item(&self.item_kind)
+                            .with_count(cargo)
+                            .with_market(destination),
+                    );
+                    Stage::Carrying {
+                        destination: destination.clone(),
+                        unit_cost: price + (*fare / cargo.max(1) as u64) as i64,
+                    }
+                }
+                (Stage::Carrying { .. }, Intent::List { price, .. }) => {
+                    payload.log.add(
+                        Severity::Info,
+                        Category::Trade,
+                        format!("Listed {} of {} at {}", dealt_with, self.item_kind, price),
+                        LogFields::item(&self.item_kind)
+                            .with_count(dealt_with)
+                            .with_price(*price),
+                    );
+                    Stage::Idle
+                }
+                (stage, _) => stage,
+            };
+        }
+        ActionResult::InProgress
+    }
+    fn get_name(&self) -> String {
+        format!("Trading {}", self.item_kind)
+    }
+    fn clone_box(&self) -> Box<dyn Action> {
+        Box::new(self.clone())
+    }
+}
+
+fn get_selling_price(unit_cost: i64) -> i64 {
+    (unit_cost as f64 * (1.0 + MARGIN)).ceil() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        accounting::{account::AccountId, bank::Bank},
+        actor::ActorId,
+        book::Book,
+        flow::TaskFlow,
+        logbook::LogBook,
+        market::Market,
+        store::Store,
+    };
+
+    #[test]
+    fn stay_put_when_fare_is_not_paid() {
+        let mut bank = Bank::new("Bank");
+        let mut log = LogBook::new(10);
+        let mut store_actual = Store::new();
+        store_actual.set("Apple", 2);
+        let mut market = Market::new("Market");
+        let mut journey = None;
+        let geography = Geography::new(Vec::new());
+        let mut action = TradeAction::new("Apple");
+        action.stage = Stage::Buying {
+            destination: "Town".to_string(),
+            price: 5,
+        };
+        let payload = ActionPayload {
+            actor_id: ActorId::default(),
+            name: &mut "Trader".to_string(),
+            log: &mut log,
+            account: bank.open_account("Trader"),
+            bank: &mut bank,
+            book: &mut Book::new(),
+            store_actual: &mut store_actual,
+            store_target: &mut Store::new(),
+            market: &mut market,
+            location: "Market",
+            journey: &mut journey,
+            geography: &geography,
+            // Not held by the bank, so the fare can not be paid
+            carrier_account: AccountId::default(),
+            task: &None,
+            flow: &mut TaskFlow::default(),
+        };
+        let intents = vec![
+            Intent::CancelBids,
+            Intent::Travel {
+                destination: "Town".to_string(),
+                ticks: 3,
+                fare: 10,
+            },
+        ];
+        action.tick(payload, intents);
+        assert_eq!(journey, None);
+        assert!(matches!(action.stage, Stage::Buying { .. }));
+        assert!(log
+            .get_entries()
+            .iter()
+            .any(|entry| entry.contains("Could not pay fare")));
+    }
+}
@@ -1,10 +1,20 @@
-use super::{Action, ActionPayload, ActionResult};
+use super::{Action, ActionPayload, ActionResult, Intent};
 use crate::simulation::{
-    item_count_list::ItemCountList, market::item::Item, store::Store, task::Task,
+    actor::ActorView,
+    geography::Geography,
+    item_count_list::ItemCountList,
+    logbook::{Category, LogFields, Severity},
+    store::Store,
+    task::Task,
 };
-use std::collections::HashSet;
-use uuid::Uuid;
+use std::collections::BTreeSet;
 
+///
+/// Price items are listed at.
+///
+const PRICE: i64 = 500;
+
+#[derive(Clone)]
 pub struct WorkAction {
     has_used_material: bool,
     progress_points: u64,
@@ -16,93 +26,11 @@ impl WorkAction {
             progress_points: 0,
         }
     }
-}
-
-impl Action for WorkAction {
-    fn tick(&mut self, payload: ActionPayload) -> ActionResult {
-        // Break to different action if there is no longer a task
-        if payload.task.is_none() {
-            return ActionResult::InProgress;
-        }
-        let task = payload.task.clone().unwrap();
-
-        // Remove listings which no longer exist in the market
-        payload
-            .submitted_listings
-            .retain(|listing| listing.upgrade().is_some());
-
-        // Update target storage
-        update_storage_target(payload.store_target, &task, 2.5);
-
-        // Determine how many of each item should be bought or sold
-        let storage_deltas = get_storage_deltas(&payload.store_target, &payload.store_actual);
-
-        // Try to sequentially trade deltas
-        for delta in storage_deltas.iter() {
-            let item_kind = delta.0.clone();
-            let amount = delta.1;
-            if amount > 0 {
-                // Buy
-                payload
-                    .log
-                    .add_entry(&format!("Need to buy {} of {}", amount, item_kind));
-                let listings_available = payload.market.get_listings_of_kind(&item_kind);
-                if let Some(listing_to_buy) = listings_available.first() {
-                    let listing_to_buy_strong = listing_to_buy.upgrade();
-                    if listing_to_buy_strong.is_none() {
-                        continue;
-                    }
-                    let successful_buy = payload
-                        .market
-                        .buy_listing(listing_to_buy.clone(), payload.account.clone());
-                    if successful_buy {
-                        payload.store_actual.add(&item_kind, 1);
-                    }
-                }
-            } else if amount < 0 {
-                // Check if actor already listed item
-                let existing_listings_for_item_kind_count = payload
-                    .submitted_listings
-                    .iter()
-                    .filter(|listing| {
-                        // Safe becaue listings are cleaned up before tick
-                        let listing = listing
-                            .upgrade()
-                            .expect("Could not upgrade listing, should be cleaned up before tick");
-                        listing.item.kind == item_kind
-                    })
-                    .count() as isize;
-
-                let amount_to_list = (-amount) - existing_listings_for_item_kind_count;
-
-                if amount_to_list > 0 {
-                    // List
-                    payload
-                        .log
-                        .add_entry(&format!("Need to list {} of {}", amount_to_list, item_kind));
-
-                    // Remove the amount to list from the store
-                    payload.store_actual.take(&item_kind, amount_to_list);
-
-                    // Add listing for each item to market
-                    for _ in 0..amount_to_list {
-                        // List item in market
-                        let listing = payload.market.list_item(
-                            Some(payload.actor_weak.clone()),
-                            Item {
-                                id: Uuid::new_v4(),
-                                kind: item_kind.clone(),
-                            },
-                            500,
-                        );
-
-                        // Record listing on actor
-                        payload.submitted_listings.push(listing);
-                    }
-                }
-            }
-        }
-
+    ///
+    /// Uses up the inputs of the task if they were not yet and puts work into it, producing the
+    /// outputs once enough work was put in.
+    ///
+    fn produce(&mut self, payload: &mut ActionPayload, task: &Task) -> ActionResult {
         // Ensure material usage
         if !self.has_used_material {
             let has_enough_material = task
@@ -111,9 +39,12 @@ impl Action for WorkAction {
                 .all(|input| payload.store_actual.has_count(&input.0, input.1));
 
             if !has_enough_material {
-                payload
-                    .log
-                    .add_entry(&format!("Does not have enough material yet...",));
+                payload.log.add(
+                    Severity::Debug,
+                    Category::Production,
+                    "Does not have enough material yet...".to_string(),
+                    LogFields::default(),
+                );
                 return ActionResult::InProgress;
             }
 
@@ -122,16 +53,21 @@ impl Action for WorkAction {
                 payload
                     .store_actual
                     .take(&input_item_kind, *input_item_needed_count);
+                payload
+                    .flow
+                    .add_consumed(input_item_kind, *input_item_needed_count);
             }
 
             self.has_used_material = true;
         }
 
         self.progress_points = self.progress_points + 4;
-        payload.log.add_entry(&format!(
-            "Working progress at {} points",
-            self.progress_points
-        ));
+        payload.log.add(
+            Severity::Debug,
+            Category::Production,
+            "Working".to_string(),
+            LogFields::default().with_progress(self.progress_points),
+        );
 
         if self.progress_points > task.work_points {
             // Produce output
@@ -139,16 +75,128 @@ impl Action for WorkAction {
                 payload
                     .store_actual
                     .add(&output_item_kind, *output_item_count);
+                payload
+                    .flow
+                    .add_produced(output_item_kind, *output_item_count);
             }
 
             return ActionResult::Done(Box::new(WorkAction::new()));
         }
 
+        ActionResult::InProgress
+    }
+}
+
+impl Action for WorkAction {
+    fn decide(&self, view: &ActorView, _geography: &Geography) -> Vec<Intent> {
+        let task = match view.task.as_ref() {
+            Some(task) => task,
+            None => return Vec::new(),
+        };
+
+        // Determine how many of each item should be bought or sold
+        let mut store_target = Store::new();
+        update_storage_target(&mut store_target, task, task.safety_factor);
+        let storage_deltas = get_storage_deltas(&store_target, &view.store_actual);
+
+        let mut intents = Vec::new();
+        for (item_kind, amount) in storage_deltas {
+            if amount > 0 {
+                // Take back own listings before buying from others
+                let own_count = view.get_listed_count(&item_kind).min(amount);
+                if own_count > 0 {
+                    intents.push(Intent::TakeBack {
+                        item_kind: item_kind.clone(),
+                        count: own_count,
+                    });
+                }
+
+                // Wait for bids still open at auction to be filled
+                let missing = amount - own_count;
+                if missing > 0 && view.get_bidding_count(&item_kind) < missing {
+                    intents.push(Intent::Buy {
+                        item_kind,
+                        count: 1,
+                        limit: task.max_price,
+                    });
+                }
+            } else if amount < 0 {
+                // Check if actor already listed item
+                let amount_to_list = (-amount) - view.get_listed_count(&item_kind);
+                if amount_to_list > 0 {
+                    intents.push(Intent::List {
+                        item_kind,
+                        count: amount_to_list,
+                        price: PRICE,
+                        ttl: task.listing_ttl,
+                    });
+                }
+            }
+        }
+        intents.push(Intent::Produce);
+        intents
+    }
+    fn tick(&mut self, mut payload: ActionPayload, intents: Vec<Intent>) -> ActionResult {
+        // Break to different action if there is no longer a task
+        if payload.task.is_none() {
+            return ActionResult::InProgress;
+        }
+        let task = payload.task.clone().unwrap();
+
+        // Update target storage
+        update_storage_target(payload.store_target, &task, task.safety_factor);
+
+        // Try to sequentially trade deltas
+        for intent in intents.iter() {
+            match intent {
+                Intent::TakeBack { item_kind, .. } => {
+                    if payload.resolve(intent) > 0 {
+                        payload.log.add(
+                            Severity::Info,
+                            Category::Trade,
+                            format!("Took back listings of {}", item_kind),
+                            LogFields::item(item_kind),
+                        );
+                    }
+                }
+                Intent::Buy { item_kind, .. } => {
+                    payload.log.add(
+                        Severity::Debug,
+                        Category::Decision,
+                        format!("Need to buy {}", item_kind),
+                        LogFields::item(item_kind),
+                    );
+                    payload.resolve(intent);
+                }
+                Intent::List {
+                    item_kind, count, ..
+                } => {
+                    payload.log.add(
+                        Severity::Debug,
+                        Category::Decision,
+                        format!("Need to list {} of {}", count, item_kind),
+                        LogFields::item(item_kind).with_count(*count),
+                    );
+                    payload.resolve(intent);
+                }
+                Intent::Produce => return self.produce(&mut payload, &task),
+                intent => {
+                    payload.resolve(intent);
+                }
+            }
+        }
+
         ActionResult::InProgress
     }
     fn get_name(&self) -> String {
         String::from("Idle")
     }
+    fn get_progress_points(&self) -> Option<u64> {
+        Some(self.progress_points)
+    }
+    fn clone_box(&self) -> Box<dyn Action> {
+        Box::new(self.clone())
+    }
 }
 
 ///
@@ -170,24 +218,21 @@ fn update_storage_target(storage_target: &mut Store, task: &Task, safety_factor:
 ///
 /// A negative number indicates that the actor has too many of the item.
 ///
-/// The number indicates what the actor must do in order to reach the target.
+/// The number indicates what the actor must do in order to reach the target. Item kinds are in
+/// order, so actors go about them the same way every time.
 ///
 fn get_storage_deltas(storage_target: &Store, storage_actual: &Store) -> ItemCountList {
-    let item_kinds_target = storage_target
-        .get_item_kinds()
-        .into_iter()
-        .collect::<HashSet<_>>();
-    let item_kinds_actual = storage_actual
+    let item_kinds = storage_target
         .get_item_kinds()
         .into_iter()
-        .collect::<HashSet<_>>();
-    let item_kinds = item_kinds_actual.union(&item_kinds_target);
+        .chain(storage_actual.get_item_kinds())
+        .collect::<BTreeSet<_>>();
     item_kinds
         .into_iter()
         .map(|item_kind| {
             (
-                (*item_kind).clone(),
-                (storage_target.count(&item_kind) - storage_actual.count(&item_kind)) as isize,
+                item_kind.clone(),
+                (storage_target.count(item_kind) - storage_actual.count(item_kind)) as isize,
             )
         })
         .collect()
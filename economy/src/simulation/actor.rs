@@ -1,85 +1,206 @@
 use super::{
-    accounting::{account::Account, bank::Bank},
-    actions::{work_action::WorkAction, Action, ActionPayload, ActionResult},
+    accounting::{account::AccountId, bank::Bank},
+    actions::trade_action::TradeAction,
+    actions::{work_action::WorkAction, Action, ActionPayload, ActionResult, Intent},
     book::Book,
-    logbook::LogBook,
-    market::{listing::Listing, Market},
+    flow::TaskFlow,
+    geography::{Geography, Journey},
+    logbook::{Category, LogBook, LogEntry, LogFields, LogQuery, Severity, RECENT_ENTRIES},
+    market::{item::Item, Market},
     store::Store,
     task::Task,
 };
-use std::{
-    cell::{Ref, RefCell},
-    fmt::Debug,
-    rc::{Rc, Weak},
-};
+use slotmap::new_key_type;
+use std::{collections::BTreeMap, fmt::Debug};
+use tracing::debug_span;
 use uuid::Uuid;
 
+new_key_type! {
+    pub struct ActorId;
+}
+
+///
+/// State of an actor at the start of the tick, as far as its action needs it to decide. Unlike
+/// the actor itself it can be shared between threads.
+///
+pub(super) struct ActorView {
+    pub(super) location: String,
+    pub(super) task: Option<Task>,
+    pub(super) store_actual: Store,
+    /// Number of own listings by item kind, in any market.
+    pub(super) listed: BTreeMap<String, isize>,
+    /// Number of open bids by item kind.
+    pub(super) bidding: BTreeMap<String, isize>,
+}
+impl ActorView {
+    pub(super) fn get_listed_count(&self, item_kind: &str) -> isize {
+        self.listed.get(item_kind).copied().unwrap_or(0)
+    }
+    pub(super) fn get_bidding_count(&self, item_kind: &str) -> isize {
+        self.bidding.get(item_kind).copied().unwrap_or(0)
+    }
+}
+
+///
+/// Works a task or trades in the markets. Actors are stored by the world and refer to their
+/// account, listings and bids by id.
+///
+#[derive(Clone)]
 pub struct Actor {
-    weak_self: Weak<RefCell<Actor>>,
     pub(super) id: Uuid,
     name: String,
     log: LogBook,
-    account: Weak<RefCell<Account>>,
+    account: AccountId,
     book: Book,
-    submitted_listings: Vec<Weak<Listing>>,
     store_actual: Store,
     store_target: Store,
     action: Box<dyn Action>,
     task: Option<Task>,
+    is_trading: bool,
+    flow: TaskFlow,
+    /// Name of the market the actor trades in.
+    location: String,
+    journey: Option<Journey>,
 }
 impl Actor {
     pub(super) fn new(
         name: &str,
-        bank: Rc<RefCell<Bank>>,
+        bank: &mut Bank,
         task: Option<Task>,
-    ) -> Rc<RefCell<Self>> {
-        let account = bank.borrow_mut().open_account(name);
-        Rc::new_cyclic(|weak_self| {
-            RefCell::new(Self {
-                weak_self: weak_self.clone(),
-                id: Uuid::new_v4(),
-                name: name.to_string(),
-                log: LogBook::default(),
-                account,
-                book: Book::new(),
-                submitted_listings: Vec::new(),
-                store_actual: Store::new(),
-                store_target: Store::new(),
-                action: Box::new(WorkAction::new()),
-                task,
-            })
+        location: &str,
+        log_retention: usize,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            log: LogBook::new(log_retention),
+            account: bank.open_account(name),
+            book: Book::new(),
+            store_actual: Store::new(),
+            store_target: Store::new(),
+            action: Box::new(WorkAction::new()),
+            task,
+            is_trading: false,
+            flow: TaskFlow::default(),
+            location: location.to_string(),
+            journey: None,
+        }
+    }
+    ///
+    /// Gets the actor ready to decide what to do this tick, and returns what it decides from.
+    /// Travelling actors only get closer to their destination and have nothing to decide, until
+    /// they arrive and act in its market.
+    ///
+    pub(super) fn prepare(
+        &mut self,
+        id: ActorId,
+        markets: &BTreeMap<String, Market>,
+    ) -> Option<ActorView> {
+        if let Some(journey) = self.journey.as_mut() {
+            journey.ticks_left = journey.ticks_left.saturating_sub(1);
+            if journey.ticks_left > 0 {
+                return None;
+            }
+            self.location = journey.destination.clone();
+            self.journey = None;
+            self.log.add(
+                Severity::Info,
+                Category::Decision,
+                format!("Arrived at {}", self.location),
+                LogFields::market(&self.location),
+            );
+        }
+        if !markets.contains_key(&self.location) {
+            return None;
+        }
+
+        let mut listed = BTreeMap::<String, isize>::new();
+        let mut bidding = BTreeMap::<String, isize>::new();
+        for market in markets.values() {
+            for listing in market.get_listings_of_owner(id) {
+                *listed.entry(listing.item.kind.clone()).or_default() += 1;
+            }
+            for bid in market.get_bids_of(id) {
+                *bidding.entry(bid.item_kind.clone()).or_default() += 1;
+            }
+        }
+        Some(ActorView {
+            location: self.location.clone(),
+            task: self.task.clone(),
+            store_actual: self.store_actual.clone(),
+            listed,
+            bidding,
         })
     }
+    pub(super) fn get_action(&self) -> &dyn Action {
+        self.action.as_ref()
+    }
     ///
-    /// Dispatches the current state of the actor to the current action held by the actor. The
-    /// action will either tick and return a 'InProgress' state or will return a 'Done' state
-    /// containing the next action. This tick method is responsible for replacing the old action
-    /// with the newly returned action in preparation for the next call to this tick method.
+    /// Dispatches the current state of the actor and the intents it decided on to the current
+    /// action held by the actor. The action will either tick and return a 'InProgress' state or
+    /// will return a 'Done' state containing the next action. This tick method is responsible for
+    /// replacing the old action with the newly returned action in preparation for the next call
+    /// to this tick method.
     ///
-    pub(super) fn tick(&mut self, market: &mut Market) {
-        // Clean up listings
-        self.submitted_listings
-            .retain(|listing| listing.upgrade().is_some());
+    pub(super) fn tick(
+        &mut self,
+        id: ActorId,
+        markets: &mut BTreeMap<String, Market>,
+        bank: &mut Bank,
+        geography: &Geography,
+        carrier_account: AccountId,
+        intents: Vec<Intent>,
+    ) {
+        let _span =
+            debug_span!("actor_tick", actor = %self.name, location = %self.location).entered();
+        let market = match markets.get_mut(&self.location) {
+            Some(market) => market,
+            None => return,
+        };
 
-        let action_result = self.action.tick(ActionPayload {
-            actor_weak: self.weak_self.clone(),
-            name: &mut self.name,
-            log: &mut self.log,
-            account: &mut self.account,
-            book: &mut self.book,
-            submitted_listings: &mut self.submitted_listings,
-            store_actual: &mut self.store_actual,
-            store_target: &mut self.store_target,
-            market,
-            task: &self.task,
-        });
+        let action_result = self.action.tick(
+            ActionPayload {
+                actor_id: id,
+                name: &mut self.name,
+                log: &mut self.log,
+                account: self.account,
+                bank,
+                book: &mut self.book,
+                store_actual: &mut self.store_actual,
+                store_target: &mut self.store_target,
+                market,
+                location: &self.location,
+                journey: &mut self.journey,
+                geography,
+                carrier_account,
+                task: &self.task,
+                flow: &mut self.flow,
+            },
+            intents,
+        );
         match action_result {
             ActionResult::InProgress => (),
             ActionResult::Done(next_action) => self.action = next_action,
         };
     }
-    pub(super) fn get_account(&self) -> Weak<RefCell<Account>> {
-        self.account.clone()
+    ///
+    /// Makes the actor carry the item kind between markets for profit, rather than work a task.
+    ///
+    pub(super) fn start_trading(&mut self, item_kind: &str) {
+        self.action = Box::new(TradeAction::new(item_kind));
+        self.is_trading = true;
+    }
+    ///
+    /// Whether the actor neither works a task nor trades.
+    ///
+    pub(super) fn is_unemployed(&self) -> bool {
+        self.task.is_none() && !self.is_trading
+    }
+    pub(super) fn get_location(&self) -> &str {
+        &self.location
+    }
+    pub(super) fn get_account(&self) -> AccountId {
+        self.account
     }
     pub(super) fn get_name(&self) -> String {
         self.name.clone()
@@ -87,22 +208,93 @@ impl Actor {
     pub(super) fn get_log(&self) -> Vec<String> {
         self.log.get_entries()
     }
+    pub(super) fn get_recent_log(&self) -> Vec<String> {
+        self.log.get_recent_entries(RECENT_ENTRIES)
+    }
+    pub(super) fn query_log(&self, query: &LogQuery) -> impl Iterator<Item = &LogEntry> {
+        self.log.query(query)
+    }
+    ///
+    /// Sets the tick the actor logs entries at.
+    ///
+    pub(super) fn set_tick(&mut self, tick: u64) {
+        self.log.set_tick(tick);
+    }
     pub(super) fn get_store_actual(&self) -> Store {
         self.store_actual.clone()
     }
+    ///
+    /// Changes whenever the actual store of the actor changes.
+    ///
+    pub(super) fn get_store_revision(&self) -> u64 {
+        self.store_actual.get_revision()
+    }
+    ///
+    /// Changes whenever the actor logs an entry.
+    ///
+    pub(super) fn get_log_revision(&self) -> u64 {
+        self.log.get_revision()
+    }
+    pub(super) fn get_task(&self) -> Option<&Task> {
+        self.task.as_ref()
+    }
+    pub(super) fn get_flow(&self) -> &TaskFlow {
+        &self.flow
+    }
     pub(super) fn get_store_target(&self) -> Store {
         self.store_target.clone()
     }
+    ///
+    /// Adds an item bought at auction to the actual store.
+    ///
+    pub(super) fn receive_item(&mut self, item: Item, price: i64) {
+        self.log.add(
+            Severity::Info,
+            Category::Trade,
+            format!("Bought 1 of {} at {}", item.kind, price),
+            LogFields::item(&item.kind).with_count(1).with_price(price),
+        );
+        self.store_actual.add_item(item);
+    }
+    ///
+    /// Puts the item of an expired listing back into the actual store.
+    ///
+    pub(super) fn take_back_item(&mut self, item: Item) {
+        self.log.add(
+            Severity::Warning,
+            Category::Trade,
+            format!("Listing of {} expired unsold", item.kind),
+            LogFields::item(&item.kind),
+        );
+        self.store_actual.add_item(item);
+    }
+    ///
+    /// Destroys the given fraction of an item kind in the actual store, rounded down. Returns the
+    /// number of items destroyed.
+    ///
+    pub(super) fn destroy_stock(&mut self, item_kind: &str, fraction: f64) -> isize {
+        let count = (self.store_actual.count(item_kind) as f64 * fraction).floor() as isize;
+        self.store_actual.take(item_kind, count)
+    }
+    pub(super) fn get_action_name(&self) -> String {
+        match self.journey.as_ref() {
+            Some(journey) => format!("Travelling to {}", journey.destination),
+            None => self.action.get_name(),
+        }
+    }
+    ///
+    /// Fraction of the work points of the task the current action has completed, if it is
+    /// working towards the task.
+    ///
+    pub(super) fn get_action_progress(&self) -> Option<f32> {
+        let progress_points = self.action.get_progress_points()?;
+        let work_points = self.task.as_ref()?.work_points.max(1);
+        Some((progress_points as f32 / work_points as f32).min(1.0))
+    }
 }
 
 impl Debug for Actor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} - {} - {:?}",
-            self.name,
-            self.account.upgrade().unwrap().borrow().get_balance(),
-            self.book,
-        )
+        write!(f, "{} - {:?}", self.name, self.book,)
     }
 }
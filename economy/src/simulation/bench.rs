@@ -0,0 +1,79 @@
+use super::{
+    accounting::{account::AccountId, bank::Bank},
+    market::{item::Item, Market},
+};
+use uuid::Uuid;
+
+///
+/// Item kinds spread over the listings of the benchmark markets.
+///
+const ITEM_KINDS: [&str; 4] = ["Apple", "Orange", "Banana", "FoodBox"];
+
+///
+/// Get a market with the given number of listings waiting in its queue, spread over a few item
+/// kinds at varying prices.
+///
+pub fn create_market_with_queue(listing_count: usize) -> Market {
+    let mut market = Market::new("Benchmark");
+    // Listings without an owner are not charged a fee
+    let mut bank = Bank::new("Benchmark");
+    for i in 0..listing_count {
+        let item = Item {
+            id: Uuid::new_v4(),
+            kind: ITEM_KINDS[i % ITEM_KINDS.len()].to_string(),
+        };
+        market.list_item(None, item, 100 + (i * 7919 % 1000) as i64, None, &mut bank);
+    }
+    market
+}
+///
+/// Get a market with the given number of active listings, all of a single item kind.
+///
+pub fn create_market_with_book(listing_count: usize) -> Market {
+    let mut market = Market::new("Benchmark");
+    let mut bank = Bank::new("Benchmark");
+    for i in 0..listing_count {
+        let item = Item {
+            id: Uuid::new_v4(),
+            kind: ITEM_KINDS[0].to_string(),
+        };
+        market.list_item(None, item, 100 + (i * 7919 % 1000) as i64, None, &mut bank);
+    }
+    tick_market(&mut market);
+    market
+}
+pub fn tick_market(market: &mut Market) {
+    market.tick();
+}
+///
+/// Walks the whole book of the item kind and returns the total asked price.
+///
+pub fn sum_listings_of_kind(market: &Market, item_kind: &str) -> i64 {
+    market
+        .get_listings_of_kind(item_kind)
+        .map(|listing| listing.price)
+        .sum()
+}
+pub fn get_book_item_kind() -> &'static str {
+    ITEM_KINDS[0]
+}
+///
+/// Get an account which took part in the given number of transactions, together with the bank
+/// holding it.
+///
+pub fn create_account_with_history(transaction_count: usize) -> (Bank, AccountId) {
+    let mut bank = Bank::new("Benchmark");
+    let account = bank.open_account("Benchmark");
+    let other = bank.open_account("Other");
+    for i in 0..transaction_count {
+        if i % 2 == 0 {
+            bank.process_transaction(other, account, 10).unwrap();
+        } else {
+            bank.process_transaction(account, other, 5).unwrap();
+        }
+    }
+    (bank, account)
+}
+pub fn get_balance(bank: &Bank, account: AccountId) -> i64 {
+    bank.get_balance(account)
+}
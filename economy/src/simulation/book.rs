@@ -1,6 +1,6 @@
 use super::recipe::Recipe;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Book {
     recipes: Vec<Recipe>,
 }
@@ -0,0 +1,258 @@
+use super::intervention::InterventionRequest;
+use std::{
+    sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+///
+/// Everything that can be sent to the simulation thread. Control commands are handled by the
+/// clock, everything else is handed to the world between ticks.
+///
+pub enum SimulationCommand {
+    Control(ControlCommand),
+    Intervention(InterventionRequest),
+    /// Select the actor whose details are published along with the renderable state.
+    Inspect(Option<Uuid>),
+}
+impl From<ControlCommand> for SimulationCommand {
+    fn from(command: ControlCommand) -> Self {
+        SimulationCommand::Control(command)
+    }
+}
+impl From<InterventionRequest> for SimulationCommand {
+    fn from(request: InterventionRequest) -> Self {
+        SimulationCommand::Intervention(request)
+    }
+}
+
+///
+/// Commands which change how time passes in a running simulation.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    /// Run a single tick, then stay paused.
+    Step,
+    /// Multiplier on the base tick rate. `f64::INFINITY` runs as fast as possible.
+    SetSpeed(f64),
+    /// Run as fast as possible until the given tick is reached. Ticks in the past are ignored.
+    JumpToTick(u64),
+}
+
+pub const MIN_SPEED: f64 = 0.1;
+
+///
+/// Decides when the next tick is due. Applies control commands as they arrive and sleeps between
+/// ticks according to the tick rate and speed, without missing commands while it waits.
+/// Other commands arriving in the meantime, also while paused, are passed on as they come.
+///
+pub struct Clock {
+    rx: Option<Receiver<SimulationCommand>>,
+    tick_rate: Option<f64>,
+    speed: f64,
+    is_paused: bool,
+    pending_steps: u64,
+    target_tick: Option<u64>,
+    last_tick_at: Option<Instant>,
+}
+impl Clock {
+    pub fn new(tick_rate: Option<f64>, rx: Option<Receiver<SimulationCommand>>) -> Self {
+        Self {
+            rx,
+            tick_rate,
+            speed: 1.0,
+            is_paused: false,
+            pending_steps: 0,
+            target_tick: None,
+            last_tick_at: None,
+        }
+    }
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+    ///
+    /// Blocks until the tick following `current_tick` should run. Commands other than control
+    /// commands received while waiting are passed to `on_command`.
+    ///
+    pub fn wait_for_tick(
+        &mut self,
+        current_tick: u64,
+        mut on_command: impl FnMut(SimulationCommand),
+    ) {
+        loop {
+            self.apply_pending_commands(&mut on_command);
+
+            // Fast forward
+            if let Some(target_tick) = self.target_tick {
+                if current_tick < target_tick {
+                    return self.mark_tick();
+                }
+                self.target_tick = None;
+            }
+
+            // Single step
+            if self.pending_steps > 0 {
+                self.pending_steps -= 1;
+                return self.mark_tick();
+            }
+
+            // Paused, wait for a command
+            if self.is_paused {
+                match self.rx.as_ref().map(|rx| rx.recv()) {
+                    Some(Ok(command)) => self.apply(command, &mut on_command),
+                    // Nobody left to resume the simulation
+                    _ => {
+                        self.rx = None;
+                        self.is_paused = false;
+                    }
+                }
+                continue;
+            }
+
+            // Running, wait out the tick duration while listening for commands
+            let due_at = match (self.get_tick_duration(), self.last_tick_at) {
+                (Some(duration), Some(last_tick_at)) => last_tick_at + duration,
+                _ => return self.mark_tick(),
+            };
+            let now = Instant::now();
+            if now >= due_at {
+                return self.mark_tick();
+            }
+            match self.rx.as_ref() {
+                Some(rx) => match rx.recv_timeout(due_at - now) {
+                    Ok(command) => self.apply(command, &mut on_command),
+                    Err(RecvTimeoutError::Timeout) => return self.mark_tick(),
+                    Err(RecvTimeoutError::Disconnected) => self.rx = None,
+                },
+                None => {
+                    std::thread::sleep(due_at - now);
+                    return self.mark_tick();
+                }
+            }
+        }
+    }
+    fn get_tick_duration(&self) -> Option<Duration> {
+        let ticks_per_second = self.tick_rate? * self.speed;
+        if ticks_per_second.is_infinite() || ticks_per_second <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(1.0 / ticks_per_second))
+    }
+    fn mark_tick(&mut self) {
+        self.last_tick_at = Some(Instant::now());
+    }
+    fn apply_pending_commands(&mut self, on_command: &mut impl FnMut(SimulationCommand)) {
+        while let Some(rx) = self.rx.as_ref() {
+            match rx.try_recv() {
+                Ok(command) => self.apply(command, on_command),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.rx = None,
+            }
+        }
+    }
+    fn apply(
+        &mut self,
+        command: SimulationCommand,
+        on_command: &mut impl FnMut(SimulationCommand),
+    ) {
+        let command = match command {
+            SimulationCommand::Control(command) => command,
+            command => return on_command(command),
+        };
+        match command {
+            ControlCommand::Pause => self.is_paused = true,
+            ControlCommand::Resume => self.is_paused = false,
+            ControlCommand::Step => {
+                self.is_paused = true;
+                self.pending_steps += 1;
+            }
+            ControlCommand::SetSpeed(speed) => self.speed = speed.max(MIN_SPEED),
+            ControlCommand::JumpToTick(tick) => self.target_tick = Some(tick),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::intervention::Intervention;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn commands() {
+        let (tx, rx) = channel();
+        let mut clock = Clock::new(Some(1.0), Some(rx));
+        tx.send(ControlCommand::SetSpeed(0.01).into()).unwrap();
+        tx.send(ControlCommand::Pause.into()).unwrap();
+        clock.apply_pending_commands(&mut |_| ());
+        assert!(clock.is_paused());
+        assert_eq!(clock.get_speed(), MIN_SPEED);
+        tx.send(ControlCommand::SetSpeed(f64::INFINITY).into())
+            .unwrap();
+        tx.send(ControlCommand::Resume.into()).unwrap();
+        clock.apply_pending_commands(&mut |_| ());
+        assert!(!clock.is_paused());
+        assert_eq!(clock.get_tick_duration(), None);
+    }
+
+    #[test]
+    fn step_while_paused() {
+        let (tx, rx) = channel();
+        let mut clock = Clock::new(Some(1000.0), Some(rx));
+        tx.send(ControlCommand::Step.into()).unwrap();
+        tx.send(ControlCommand::Step.into()).unwrap();
+        clock.wait_for_tick(0, |_| ());
+        clock.wait_for_tick(1, |_| ());
+        assert!(clock.is_paused());
+        assert_eq!(clock.pending_steps, 0);
+        // Dropping the controller lets a paused clock carry on
+        drop(tx);
+        clock.wait_for_tick(2, |_| ());
+        assert!(!clock.is_paused());
+    }
+
+    #[test]
+    fn jump_to_tick() {
+        let (tx, rx) = channel();
+        // One tick per hour, a jump must not wait for any of them
+        let mut clock = Clock::new(Some(1.0 / 3600.0), Some(rx));
+        tx.send(ControlCommand::Pause.into()).unwrap();
+        tx.send(ControlCommand::JumpToTick(3).into()).unwrap();
+        for tick in 0..3 {
+            clock.wait_for_tick(tick, |_| ());
+        }
+        assert_eq!(clock.target_tick, Some(3));
+        tx.send(ControlCommand::Step.into()).unwrap();
+        clock.wait_for_tick(3, |_| ());
+        assert_eq!(clock.target_tick, None);
+        assert!(clock.is_paused());
+    }
+
+    #[test]
+    fn interventions_while_paused() {
+        let (tx, rx) = channel();
+        let mut clock = Clock::new(Some(1000.0), Some(rx));
+        tx.send(ControlCommand::Pause.into()).unwrap();
+        for amount in [10, 20] {
+            let intervention = Intervention::GrantMoney {
+                account: "A".to_string(),
+                amount,
+            };
+            tx.send(InterventionRequest::new(intervention, None).into())
+                .unwrap();
+        }
+        tx.send(ControlCommand::Step.into()).unwrap();
+        let mut received = Vec::new();
+        clock.wait_for_tick(0, |command| {
+            if let SimulationCommand::Intervention(request) = command {
+                received.push(request.intervention);
+            }
+        });
+        assert_eq!(received.len(), 2);
+    }
+}
@@ -0,0 +1,43 @@
+use super::{flow::FlowGraph, scenario::Scenario, world::World};
+
+///
+/// Get the scenario of regional markets connected by routes, with traders between them.
+///
+pub fn regions() -> Scenario {
+    Scenario::parse(include_str!("../../scenarios/regions.toml")).unwrap()
+}
+///
+/// Get the scenario which clears its item kinds at auction.
+///
+pub fn auctions() -> Scenario {
+    Scenario::parse(include_str!("../../scenarios/auctions.toml")).unwrap()
+}
+///
+/// Get a world of the scenario after it ran for the number of ticks.
+///
+pub fn run(scenario: &Scenario, ticks: u64) -> World {
+    let mut world = World::new(scenario);
+    advance(&mut world, ticks);
+    world
+}
+pub fn advance(world: &mut World, ticks: u64) {
+    for _ in 0..ticks {
+        world.tick();
+    }
+}
+pub fn get_actor_balance(world: &World, name: &str) -> i64 {
+    world
+        .get_snapshot()
+        .actors
+        .into_iter()
+        .find(|actor| actor.name == name)
+        .map(|actor| actor.balance)
+        .unwrap()
+}
+pub fn get_edge_total(graph: &FlowGraph, from: &str, to: &str) -> Option<u64> {
+    graph
+        .edges
+        .iter()
+        .find(|edge| edge.from == from && edge.to == to)
+        .map(|edge| edge.total)
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+///
+/// Running totals of the items an actor has consumed and produced while working its task.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskFlow {
+    pub consumed: BTreeMap<String, u64>,
+    pub produced: BTreeMap<String, u64>,
+}
+impl TaskFlow {
+    pub(super) fn add_consumed(&mut self, item_kind: &str, count: isize) {
+        *self.consumed.entry(item_kind.to_string()).or_default() += count.max(0) as u64;
+    }
+    pub(super) fn add_produced(&mut self, item_kind: &str, count: isize) {
+        *self.produced.entry(item_kind.to_string()).or_default() += count.max(0) as u64;
+    }
+    pub(super) fn add(&mut self, other: &TaskFlow) {
+        for (item_kind, count) in other.consumed.iter() {
+            *self.consumed.entry(item_kind.clone()).or_default() += count;
+        }
+        for (item_kind, count) in other.produced.iter() {
+            *self.produced.entry(item_kind.clone()).or_default() += count;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowNodeKind {
+    Item,
+    Task,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowNode {
+    pub name: String,
+    pub kind: FlowNodeKind,
+    /// Items held and listed across all actors. Only set for item nodes.
+    pub inventory: isize,
+}
+
+///
+/// An item kind going into a task, or coming out of one.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowEdge {
+    pub from: String,
+    pub to: String,
+    /// Items moved along the edge since the start of the simulation.
+    pub total: u64,
+}
+
+///
+/// The production network, with task and item kind nodes and edges from the task inputs and
+/// outputs.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FlowGraph {
+    pub tick: u64,
+    pub nodes: Vec<FlowNode>,
+    pub edges: Vec<FlowEdge>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{
+        fixture::{get_edge_total, run},
+        intervention::Intervention,
+        scenario::Scenario,
+    };
+
+    #[test]
+    fn flow_graph() {
+        let mut world = run(&Scenario::default(), 200);
+        let graph = world.get_flow_graph();
+        let total = |from: &str, to: &str| get_edge_total(&graph, from, to);
+        assert_eq!(graph.nodes.len(), 4);
+        let apples_produced = total("farmer", "Apple").unwrap();
+        let apples_consumed = total("Apple", "packer").unwrap();
+        assert!(apples_produced > 0);
+        assert!(apples_consumed > 0);
+        assert!(total("packer", "FoodBox").unwrap() > 0);
+
+        // Removing an actor does not lose what it produced
+        let remove = Intervention::RemoveActor {
+            name: "Actor_1_farmer".to_string(),
+        };
+        assert!(world.apply_intervention(&remove).is_ok());
+        let graph = world.get_flow_graph();
+        assert_eq!(
+            graph
+                .edges
+                .iter()
+                .find(|edge| edge.from == "farmer")
+                .map(|edge| edge.total),
+            Some(apples_produced)
+        );
+    }
+}
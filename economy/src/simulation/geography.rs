@@ -0,0 +1,156 @@
+use super::market::Market;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+///
+/// Name of the only market of scenarios which do not name any.
+///
+pub const DEFAULT_MARKET: &str = "Market";
+
+///
+/// Connection between two markets, travelled in both directions.
+///
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Route {
+    pub from: String,
+    pub to: String,
+    /// Ticks it takes to get from one end to the other.
+    pub ticks: u64,
+    /// Paid per item carried along.
+    #[serde(default)]
+    pub cost: u64,
+}
+
+///
+/// Trip of an actor to another market. Actors do nothing else until they arrive.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Journey {
+    pub destination: String,
+    pub ticks_left: u64,
+}
+
+///
+/// How the markets of a world are connected, and what their goods cost at the start of the tick.
+/// Actors decide from it on several threads at once.
+///
+#[derive(Clone)]
+pub struct Geography {
+    routes: Vec<Route>,
+    /// Lowest listed price of every item kind by market.
+    best_asks: BTreeMap<String, BTreeMap<String, i64>>,
+}
+impl Geography {
+    pub(super) fn new(routes: Vec<Route>) -> Self {
+        Self {
+            routes,
+            best_asks: BTreeMap::new(),
+        }
+    }
+    ///
+    /// Records the lowest listed price of every item kind in every market.
+    ///
+    pub(super) fn update_prices(&mut self, markets: &BTreeMap<String, Market>) {
+        self.best_asks = markets
+            .iter()
+            .map(|(name, market)| {
+                let best_asks = market
+                    .get_listed_item_kinds()
+                    .filter_map(|item_kind| {
+                        let price = market.get_best_ask(item_kind)?;
+                        Some((item_kind.clone(), price))
+                    })
+                    .collect();
+                (name.clone(), best_asks)
+            })
+            .collect();
+    }
+    ///
+    /// Get the lowest price the item kind was listed at in the market at the start of the tick.
+    ///
+    pub(super) fn get_best_ask(&self, market: &str, item_kind: &str) -> Option<i64> {
+        self.best_asks.get(market)?.get(item_kind).copied()
+    }
+    ///
+    /// Get every route leading away from the market, along with the market at its other end.
+    ///
+    pub(super) fn get_routes_from<'a>(
+        &'a self,
+        market: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a Route)> {
+        self.routes.iter().filter_map(move |route| {
+            if route.from == market {
+                Some((route.to.as_str(), route))
+            } else if route.to == market {
+                Some((route.from.as_str(), route))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        fixture::{regions, run},
+        intervention::Intervention,
+    };
+
+    #[test]
+    fn get_routes_from() {
+        let route = |from: &str, to: &str| Route {
+            from: from.to_string(),
+            to: to.to_string(),
+            ticks: 3,
+            cost: 10,
+        };
+        let geography = Geography::new(vec![route("A", "B"), route("C", "A"), route("B", "C")]);
+        let destinations = geography
+            .get_routes_from("A")
+            .map(|(destination, _)| destination)
+            .collect::<Vec<_>>();
+        assert_eq!(destinations, vec!["B", "C"]);
+        assert_eq!(geography.get_routes_from("D").count(), 0);
+    }
+
+    #[test]
+    fn regional_markets() {
+        let mut world = run(&regions(), 300);
+        // Spawned like actors of the scenario, in the first market it declares
+        let spawn = Intervention::SpawnActor {
+            name: "Newcomer".to_string(),
+            task: None,
+            balance: 0,
+            location: None,
+        };
+        assert!(world.apply_intervention(&spawn).is_ok());
+        let snapshot = world.get_snapshot();
+        let newcomer = snapshot
+            .actors
+            .iter()
+            .find(|actor| actor.name == "Newcomer")
+            .unwrap();
+        assert_eq!(newcomer.location, "Riverside");
+        let packers_hold = snapshot
+            .actors
+            .iter()
+            .filter(|actor| actor.location == "Hilltop")
+            .map(|actor| actor.store_actual.get("FoodBox").copied().unwrap_or(0))
+            .sum::<isize>();
+        assert!(packers_hold > 0);
+        // Apples only reach the packers through the traders, who pass on the cost of the trip
+        assert!(snapshot
+            .listings
+            .iter()
+            .filter(|listing| listing.market == "Hilltop" && listing.item_kind == "Apple")
+            .all(|listing| listing.price > 500));
+        let carriers = snapshot
+            .accounts
+            .iter()
+            .find(|account| account.name == "Carriers")
+            .unwrap();
+        assert!(carriers.balance > 0);
+    }
+}
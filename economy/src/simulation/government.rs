@@ -0,0 +1,269 @@
+use super::{
+    accounting::{account::AccountId, bank::Bank},
+    actor::{Actor, ActorId},
+    market::Market,
+};
+use serde::{Deserialize, Serialize};
+use slotmap::SlotMap;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+///
+/// Taxes and spending of the government. Every part is off unless the scenario sets it.
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// Fraction of the price buyers pay on top of every trade.
+    pub sales_tax: f64,
+    /// Fraction of the price sellers pay when selling what their task produced.
+    pub income_tax: f64,
+    /// Paid by the owner of every listing as it goes up on the market.
+    pub listing_fee: u64,
+    /// Paid every tick to each actor which neither works a task nor trades.
+    pub unemployment_benefit: u64,
+    /// Number of items of each kind bought every tick, wherever they are cheapest.
+    pub purchases: BTreeMap<String, usize>,
+}
+
+///
+/// Taxes collected over one tick, or added up over a run.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revenue {
+    pub sales_tax: u64,
+    pub income_tax: u64,
+    pub listing_fees: u64,
+}
+impl Revenue {
+    pub fn get_total(&self) -> u64 {
+        self.sales_tax + self.income_tax + self.listing_fees
+    }
+    pub(super) fn add(&mut self, other: &Revenue) {
+        self.sales_tax += other.sales_tax;
+        self.income_tax += other.income_tax;
+        self.listing_fees += other.listing_fees;
+    }
+}
+
+///
+/// Money the government spent over one tick, or added up over a run.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spending {
+    pub benefits: u64,
+    pub purchases: u64,
+}
+impl Spending {
+    pub fn get_total(&self) -> u64 {
+        self.benefits + self.purchases
+    }
+    pub(super) fn add(&mut self, other: &Spending) {
+        self.benefits += other.benefits;
+        self.purchases += other.purchases;
+    }
+}
+
+///
+/// Collects the taxes the markets charge into its account and spends them on benefits and public
+/// purchases. The account may run a deficit.
+///
+#[derive(Clone)]
+pub struct Government {
+    account: AccountId,
+    unemployment_benefit: u64,
+    purchases: BTreeMap<String, usize>,
+    revenue: Revenue,
+    spending: Spending,
+    total_revenue: Revenue,
+    total_spending: Spending,
+    /// Items bought so far by kind, which are gone from the economy.
+    used_up: BTreeMap<String, isize>,
+}
+impl Government {
+    pub(super) fn new(policy: &Policy, account: AccountId) -> Self {
+        Self {
+            account,
+            unemployment_benefit: policy.unemployment_benefit,
+            purchases: policy.purchases.clone(),
+            revenue: Revenue::default(),
+            spending: Spending::default(),
+            total_revenue: Revenue::default(),
+            total_spending: Spending::default(),
+            used_up: BTreeMap::new(),
+        }
+    }
+    ///
+    /// Pays benefits and makes the public purchases, then adds up the taxes the markets collected
+    /// this tick, including those on the purchases. Bought items are used up by the government.
+    ///
+    pub(super) fn tick(
+        &mut self,
+        markets: &mut BTreeMap<String, Market>,
+        actors: &SlotMap<ActorId, Actor>,
+        bank: &mut Bank,
+    ) {
+        self.spending = Spending::default();
+
+        // Pay benefits
+        if self.unemployment_benefit > 0 {
+            for actor in actors.values().filter(|actor| actor.is_unemployed()) {
+                let benefit = self.unemployment_benefit;
+                match bank.process_transaction(self.account, actor.get_account(), benefit) {
+                    Ok(_) => self.spending.benefits += benefit,
+                    Err(error) => warn!(actor = %actor.get_name(), %error, "benefit not paid"),
+                }
+            }
+        }
+
+        // Buy the cheapest listings in any market
+        for (item_kind, count) in self.purchases.iter() {
+            for _ in 0..*count {
+                let cheapest = markets
+                    .values_mut()
+                    .filter_map(|market| Some((market.get_best_ask(item_kind)?, market)))
+                    .min_by_key(|(price, _)| *price);
+                let (price, market) = match cheapest {
+                    Some(cheapest) => cheapest,
+                    None => break,
+                };
+                let listing = market.get_best_listing(item_kind).unwrap();
+                match market.buy_listing(listing, self.account, bank) {
+                    Ok(_) => {
+                        self.spending.purchases += price.max(0) as u64;
+                        *self.used_up.entry(item_kind.clone()).or_default() += 1;
+                    }
+                    Err(error) => warn!(%item_kind, %error, "purchase failed"),
+                }
+            }
+        }
+
+        self.revenue = Revenue::default();
+        for market in markets.values() {
+            self.revenue.add(market.get_revenue());
+        }
+        self.total_revenue.add(&self.revenue);
+        self.total_spending.add(&self.spending);
+    }
+    ///
+    /// Get the taxes collected during the last tick.
+    ///
+    pub(super) fn get_revenue(&self) -> Revenue {
+        self.revenue
+    }
+    ///
+    /// Get the money spent during the last tick.
+    ///
+    pub(super) fn get_spending(&self) -> Spending {
+        self.spending
+    }
+    ///
+    /// Get the taxes collected since the start.
+    ///
+    pub(super) fn get_total_revenue(&self) -> Revenue {
+        self.total_revenue
+    }
+    ///
+    /// Get the money spent since the start.
+    ///
+    pub(super) fn get_total_spending(&self) -> Spending {
+        self.total_spending
+    }
+    ///
+    /// Get the number of items of every kind the government bought and used up so far.
+    ///
+    pub(super) fn get_used_up(&self) -> &BTreeMap<String, isize> {
+        &self.used_up
+    }
+}
+
+///
+/// Get the part of the amount owed at the given rate, rounded to whole units.
+///
+pub(super) fn get_tax(amount: i64, rate: f64) -> u64 {
+    if amount <= 0 || rate <= 0.0 {
+        return 0;
+    }
+    (amount as f64 * rate).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        fixture::get_actor_balance, intervention::Intervention, scenario::Scenario, world::World,
+    };
+
+    #[test]
+    fn get_tax() {
+        assert_eq!(super::get_tax(500, 0.1), 50);
+        assert_eq!(super::get_tax(5, 0.25), 1);
+        assert_eq!(super::get_tax(500, 0.0), 0);
+        assert_eq!(super::get_tax(-500, 0.1), 0);
+    }
+
+    #[test]
+    fn government() {
+        let scenario = Scenario {
+            government: Policy {
+                sales_tax: 0.1,
+                income_tax: 0.1,
+                listing_fee: 5,
+                unemployment_benefit: 20,
+                purchases: BTreeMap::from([("FoodBox".to_string(), 1)]),
+            },
+            ..Scenario::default()
+        };
+        let mut world = World::new(&scenario);
+        let spawn = Intervention::SpawnActor {
+            name: "Jobless".to_string(),
+            task: None,
+            balance: 0,
+            location: None,
+        };
+        assert!(world.apply_intervention(&spawn).is_ok());
+        let mut revenue = Vec::new();
+        let mut spending = Vec::new();
+        for _ in 0..200 {
+            world.tick();
+            let metrics = world.get_metrics();
+            revenue.push(metrics.tax_revenue);
+            spending.push(metrics.government_spending);
+        }
+        assert!(revenue.iter().any(|revenue| revenue.sales_tax > 0));
+        assert!(revenue.iter().any(|revenue| revenue.income_tax > 0));
+        assert!(revenue.iter().any(|revenue| revenue.listing_fees > 0));
+        assert!(spending.iter().all(|spending| spending.benefits == 20));
+        assert!(spending.iter().any(|spending| spending.purchases > 0));
+        assert_eq!(get_actor_balance(&world, "Jobless"), 20 * 200);
+
+        // Money only moves between accounts
+        let snapshot = world.get_snapshot();
+        let balance = |name: &str| {
+            snapshot
+                .accounts
+                .iter()
+                .find(|account| account.name == name)
+                .map(|account| account.balance)
+                .unwrap()
+        };
+        let collected = revenue
+            .iter()
+            .map(|revenue| revenue.get_total())
+            .sum::<u64>();
+        let spent = spending
+            .iter()
+            .map(|spending| spending.get_total())
+            .sum::<u64>();
+        assert_eq!(balance("Government"), collected as i64 - spent as i64);
+        let metrics = world.get_metrics();
+        assert_eq!(metrics.total_tax_revenue.get_total(), collected);
+        assert_eq!(metrics.total_government_spending.get_total(), spent);
+        let total = snapshot
+            .accounts
+            .iter()
+            .map(|account| account.balance)
+            .sum::<i64>();
+        assert_eq!(total, 0);
+    }
+}
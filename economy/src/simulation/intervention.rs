@@ -0,0 +1,257 @@
+use serde::Deserialize;
+use std::sync::mpsc::Sender;
+
+///
+/// A change injected into a running world from the outside, either by the user through the GUI
+/// or by a script.
+///
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Intervention {
+    /// Add an actor working the named scenario task, funded out of the bank reserve. It starts out
+    /// in the named market, or else in the first one.
+    SpawnActor {
+        name: String,
+        task: Option<String>,
+        #[serde(default)]
+        balance: u64,
+        #[serde(default)]
+        location: Option<String>,
+    },
+    /// Remove an actor from the world. Its open listings are taken off the market.
+    RemoveActor { name: String },
+    /// Move money from the bank reserve into the named account.
+    GrantMoney { account: String, amount: u64 },
+    /// Interest charged on outstanding loans per tick, as a fraction of the amount due.
+    SetInterestRate { rate: f64 },
+    /// Cap the price of an item kind, or lift the cap with `None`.
+    SetPriceCeiling {
+        item_kind: String,
+        ceiling: Option<i64>,
+    },
+    /// Destroy a fraction of an item kind held by one actor, or by every actor.
+    DestroyStock {
+        actor: Option<String>,
+        item_kind: String,
+        fraction: f64,
+    },
+}
+
+///
+/// Outcome of an intervention. Holds a description of what was done when it was acknowledged, or
+/// the reason it was rejected.
+///
+pub type InterventionResult = Result<String, String>;
+
+///
+/// An intervention sent to the simulation thread along with where to send the outcome.
+///
+pub struct InterventionRequest {
+    pub intervention: Intervention,
+    pub reply_tx: Option<Sender<InterventionResult>>,
+}
+impl InterventionRequest {
+    pub fn new(intervention: Intervention, reply_tx: Option<Sender<InterventionResult>>) -> Self {
+        Self {
+            intervention,
+            reply_tx,
+        }
+    }
+    ///
+    /// Sends the outcome back to the requester, if it is still listening.
+    ///
+    pub fn reply(self, result: InterventionResult) {
+        if let Some(reply_tx) = self.reply_tx {
+            let _ = reply_tx.send(result);
+        }
+    }
+}
+
+///
+/// An intervention scheduled for a given tick, as read from a script file.
+///
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScheduledIntervention {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub intervention: Intervention,
+}
+
+#[derive(Deserialize)]
+struct Script {
+    #[serde(default)]
+    interventions: Vec<ScheduledIntervention>,
+}
+
+///
+/// Parses a TOML script of interventions. The result is ordered by tick, interventions on the
+/// same tick keep the order of the script.
+///
+pub fn parse_script(contents: &str) -> Result<Vec<ScheduledIntervention>, String> {
+    let script: Script = toml::from_str(contents)
+        .map_err(|error| format!("Could not parse interventions: {}", error))?;
+    let mut interventions = script.interventions;
+    interventions.sort_by_key(|scheduled| scheduled.tick);
+    Ok(interventions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        fixture::{advance, get_actor_balance, run},
+        scenario::Scenario,
+        world::World,
+    };
+
+    #[test]
+    fn parse() {
+        let interventions = parse_script(
+            r#"
+            [[interventions]]
+            tick = 20
+            type = "destroy_stock"
+            item_kind = "Apple"
+            fraction = 0.5
+
+            [[interventions]]
+            tick = 10
+            type = "spawn_actor"
+            name = "Newcomer"
+            task = "farmer"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(interventions.len(), 2);
+        assert_eq!(interventions[0].tick, 10);
+        assert_eq!(
+            interventions[0].intervention,
+            Intervention::SpawnActor {
+                name: "Newcomer".to_string(),
+                task: Some("farmer".to_string()),
+                balance: 0,
+                location: None,
+            }
+        );
+        assert_eq!(
+            interventions[1].intervention,
+            Intervention::DestroyStock {
+                actor: None,
+                item_kind: "Apple".to_string(),
+                fraction: 0.5,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_unknown_type() {
+        let result = parse_script(
+            r#"
+            [[interventions]]
+            tick = 1
+            type = "summon_dragon"
+            "#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn spawn_and_remove_actor() {
+        let mut world = World::new(&Scenario::default());
+        let spawn = Intervention::SpawnActor {
+            name: "Newcomer".to_string(),
+            task: Some("farmer".to_string()),
+            balance: 300,
+            location: None,
+        };
+        assert!(world.apply_intervention(&spawn).is_ok());
+        assert_eq!(world.get_snapshot().actors.len(), 7);
+        assert_eq!(get_actor_balance(&world, "Newcomer"), 300);
+        // Names are unique
+        assert!(world.apply_intervention(&spawn).is_err());
+        // Tasks must exist
+        let unknown_task = Intervention::SpawnActor {
+            name: "Miner".to_string(),
+            task: Some("miner".to_string()),
+            balance: 0,
+            location: None,
+        };
+        assert!(world.apply_intervention(&unknown_task).is_err());
+
+        advance(&mut world, 20);
+        let remove = Intervention::RemoveActor {
+            name: "Actor_1_farmer".to_string(),
+        };
+        assert!(world.apply_intervention(&remove).is_ok());
+        let snapshot = world.get_snapshot();
+        assert_eq!(snapshot.actors.len(), 6);
+        assert!(snapshot
+            .listings
+            .iter()
+            .all(|listing| listing.owner.as_deref() != Some("Actor_1_farmer")));
+        assert!(world.apply_intervention(&remove).is_err());
+    }
+
+    #[test]
+    fn grant_money() {
+        let mut world = World::new(&Scenario::default());
+        let grant = Intervention::GrantMoney {
+            account: "Actor_5_packer".to_string(),
+            amount: 1000,
+        };
+        assert!(world.apply_intervention(&grant).is_ok());
+        assert_eq!(get_actor_balance(&world, "Actor_5_packer"), 1000);
+        let grant = Intervention::GrantMoney {
+            account: "Nobody".to_string(),
+            amount: 1000,
+        };
+        assert!(world.apply_intervention(&grant).is_err());
+    }
+
+    #[test]
+    fn price_ceiling_and_interest_rate() {
+        let mut world = World::new(&Scenario::default());
+        let ceiling = Intervention::SetPriceCeiling {
+            item_kind: "Apple".to_string(),
+            ceiling: Some(300),
+        };
+        assert!(world.apply_intervention(&ceiling).is_ok());
+        advance(&mut world, 20);
+        let snapshot = world.get_snapshot();
+        assert!(snapshot
+            .listings
+            .iter()
+            .filter(|listing| listing.item_kind == "Apple")
+            .all(|listing| listing.price <= 300));
+
+        let rate = Intervention::SetInterestRate { rate: -0.1 };
+        assert!(world.apply_intervention(&rate).is_err());
+        let rate = Intervention::SetInterestRate { rate: 0.01 };
+        assert!(world.apply_intervention(&rate).is_ok());
+        assert_eq!(world.get_snapshot().interest_rate, 0.01);
+    }
+
+    #[test]
+    fn destroy_stock() {
+        let mut world = run(&Scenario::default(), 40);
+        let held = |world: &World| world.get_metrics().held_by_item_kind["FoodBox"];
+        let before = held(&world);
+        let shock = Intervention::DestroyStock {
+            actor: None,
+            item_kind: "FoodBox".to_string(),
+            fraction: 1.0,
+        };
+        assert!(before > 0);
+        assert!(world.apply_intervention(&shock).is_ok());
+        assert!(!world
+            .get_metrics()
+            .held_by_item_kind
+            .contains_key("FoodBox"));
+        let shock = Intervention::DestroyStock {
+            actor: None,
+            item_kind: "FoodBox".to_string(),
+            fraction: 1.5,
+        };
+        assert!(world.apply_intervention(&shock).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+///
+/// The first thing found wrong with a world by `World::check_invariants`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// Items by kind which were produced but are not held, listed, used up or removed, or the
+    /// other way around if negative.
+    GoodsNotConserved(BTreeMap<String, isize>),
+    /// Accounts and transactions of the bank do not agree, or money appeared out of nothing.
+    MoneyNotConserved(String),
+    /// Listings or bids of the market are not where its indexes say they are.
+    MarketInconsistent { market: String, context: String },
+    /// An actor holds fewer than no items of a kind.
+    NegativeInventory {
+        actor: String,
+        item_kind: String,
+        count: isize,
+    },
+}
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::GoodsNotConserved(unaccounted) => {
+                let counts = unaccounted
+                    .iter()
+                    .map(|(item_kind, count)| format!("{} {}", count, item_kind))
+                    .collect::<Vec<_>>();
+                write!(f, "goods not conserved, unaccounted {}", counts.join(", "))
+            }
+            Violation::MoneyNotConserved(context) => {
+                write!(f, "money not conserved, {}", context)
+            }
+            Violation::MarketInconsistent { market, context } => {
+                write!(f, "market {} inconsistent, {}", market, context)
+            }
+            Violation::NegativeInventory {
+                actor,
+                item_kind,
+                count,
+            } => write!(f, "{} holds {} of {}", actor, count, item_kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let unaccounted = BTreeMap::from([("Apple".to_string(), -2), ("FoodBox".to_string(), 1)]);
+        assert_eq!(
+            Violation::GoodsNotConserved(unaccounted).to_string(),
+            "goods not conserved, unaccounted -2 Apple, 1 FoodBox"
+        );
+        let inventory = Violation::NegativeInventory {
+            actor: "Jeff".to_string(),
+            item_kind: "Apple".to_string(),
+            count: -1,
+        };
+        assert_eq!(inventory.to_string(), "Jeff holds -1 of Apple");
+    }
+}
@@ -1,18 +1,449 @@
-use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt::Display, io::Write};
 
-#[derive(Default)]
+///
+/// Number of entries kept per log unless the scenario says otherwise, older entries are dropped.
+///
+pub(super) const DEFAULT_RETENTION: usize = 1000;
+
+///
+/// Number of entries considered recent, enough to show what an actor is currently up to.
+///
+pub(super) const RECENT_ENTRIES: usize = 10;
+
+///
+/// Number of newest entries an entry is looked up among to count it as a repeat. Actions log
+/// the same few entries every tick, which would otherwise push everything else out.
+///
+const REPEAT_WINDOW: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Routine steps an actor takes every tick.
+    Debug,
+    Info,
+    /// Something did not go as the actor intended.
+    Warning,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Listing, buying and selling.
+    Trade,
+    /// Working tasks.
+    Production,
+    /// Payments outside of trades.
+    Finance,
+    /// What the actor set out to do, and where it went.
+    Decision,
+}
+
+///
+/// Details of a log entry which can be queried, whichever apply.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<isize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    /// How far along a task is. Changes every time it is logged, so it does not keep an entry
+    /// from counting as a repeat, and the entry keeps the latest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<u64>,
+}
+impl LogFields {
+    pub(super) fn item(item_kind: &str) -> Self {
+        Self {
+            item_kind: Some(item_kind.to_string()),
+            ..Self::default()
+        }
+    }
+    pub(super) fn market(market: &str) -> Self {
+        Self {
+            market: Some(market.to_string()),
+            ..Self::default()
+        }
+    }
+    pub(super) fn with_count(self, count: isize) -> Self {
+        Self {
+            count: Some(count),
+            ..self
+        }
+    }
+    pub(super) fn with_price(self, price: i64) -> Self {
+        Self {
+            price: Some(price),
+            ..self
+        }
+    }
+    pub(super) fn with_market(self, market: &str) -> Self {
+        Self {
+            market: Some(market.to_string()),
+            ..self
+        }
+    }
+    pub(super) fn with_progress(self, progress: u64) -> Self {
+        Self {
+            progress: Some(progress),
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Tick the entry was first logged at.
+    pub tick: u64,
+    /// Tick the entry was last repeated at.
+    pub last_tick: u64,
+    /// Number of times the entry was logged.
+    pub repeats: u32,
+    pub severity: Severity,
+    pub category: Category,
+    pub message: String,
+    #[serde(flatten)]
+    pub fields: LogFields,
+}
+impl LogEntry {
+    fn is_repeated_by(&self, other: &LogEntry) -> bool {
+        self.severity == other.severity
+            && self.category == other.category
+            && self.message == other.message
+            && self.fields.item_kind == other.fields.item_kind
+            && self.fields.count == other.fields.count
+            && self.fields.price == other.fields.price
+            && self.fields.market == other.fields.market
+    }
+}
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.repeats {
+            1 => write!(f, "{}", self.message),
+            repeats => write!(f, "{} (x{})", self.message, repeats),
+        }
+    }
+}
+
+///
+/// Selects log entries. Every condition which is set must hold.
+///
+#[derive(Clone, Debug, Default)]
+pub struct LogQuery {
+    pub min_severity: Option<Severity>,
+    pub category: Option<Category>,
+    pub item_kind: Option<String>,
+    /// Only entries logged or repeated at or after the tick.
+    pub since_tick: Option<u64>,
+}
+impl LogQuery {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.min_severity
+            .map_or(true, |severity| entry.severity >= severity)
+            && self
+                .category
+                .map_or(true, |category| entry.category == category)
+            && self.item_kind.as_ref().map_or(true, |item_kind| {
+                entry.fields.item_kind.as_ref() == Some(item_kind)
+            })
+            && self.since_tick.map_or(true, |tick| entry.last_tick >= tick)
+    }
+}
+
+///
+/// Entries an actor logged, most recently logged first. An entry logged again shortly after is
+/// counted as a repeat and moves to the front, rather than pushing older entries out.
+///
+#[derive(Clone)]
 pub struct LogBook {
-    entries: VecDeque<String>,
+    entries: VecDeque<LogEntry>,
+    retention: usize,
+    /// Tick new entries are logged at.
+    tick: u64,
+    revision: u64,
+}
+impl Default for LogBook {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETENTION)
+    }
 }
 
 impl LogBook {
-    pub(super) fn add_entry(&mut self, entry: &str) {
-        self.entries.push_front(entry.to_string());
-        if self.entries.len() > 10 {
-            self.entries.pop_back();
+    pub(super) fn new(retention: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            retention,
+            tick: 0,
+            revision: 0,
+        }
+    }
+    pub(super) fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+    pub(super) fn add(
+        &mut self,
+        severity: Severity,
+        category: Category,
+        message: String,
+        fields: LogFields,
+    ) {
+        self.revision += 1;
+        let entry = LogEntry {
+            tick: self.tick,
+            last_tick: self.tick,
+            repeats: 1,
+            severity,
+            category,
+            message,
+            fields,
+        };
+        let repeated = self
+            .entries
+            .iter()
+            .take(REPEAT_WINDOW)
+            .position(|recent| recent.is_repeated_by(&entry));
+        match repeated.and_then(|position| self.entries.remove(position)) {
+            Some(mut recent) => {
+                recent.repeats += 1;
+                recent.last_tick = self.tick;
+                recent.fields.progress = entry.fields.progress;
+                self.entries.push_front(recent);
+            }
+            None => {
+                self.entries.push_front(entry);
+                self.entries.truncate(self.retention);
+            }
         }
     }
     pub(super) fn get_entries(&self) -> Vec<String> {
-        self.entries.clone().into()
+        self.entries.iter().map(|entry| entry.to_string()).collect()
+    }
+    ///
+    /// Get the most recent entries, newest first.
+    ///
+    pub(super) fn get_recent_entries(&self, count: usize) -> Vec<String> {
+        self.entries
+            .iter()
+            .take(count)
+            .map(|entry| entry.to_string())
+            .collect()
+    }
+    ///
+    /// Get the entries matching the query, least recently logged first.
+    ///
+    pub(super) fn query(&self, query: &LogQuery) -> impl Iterator<Item = &LogEntry> {
+        let query = query.clone();
+        self.entries
+            .iter()
+            .rev()
+            .filter(move |entry| query.matches(entry))
+    }
+    pub(super) fn get_revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+///
+/// Entry in the JSON lines export of actor logs.
+///
+#[derive(Serialize)]
+struct ActorLogEntry<'a> {
+    actor: &'a str,
+    #[serde(flatten)]
+    entry: &'a LogEntry,
+}
+
+///
+/// Writes the entry as one line of JSON, along with the name of the actor who logged it.
+///
+pub fn write_log_entry(
+    writer: &mut impl Write,
+    actor: &str,
+    entry: &LogEntry,
+) -> Result<(), String> {
+    serde_json::to_writer(&mut *writer, &ActorLogEntry { actor, entry })
+        .map_err(|error| format!("Could not write log entry: {}", error))?;
+    writeln!(writer).map_err(|error| format!("Could not write log entry: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{fixture::run, scenario::Scenario};
+
+    fn add_info(log: &mut LogBook, message: &str, fields: LogFields) {
+        log.add(Severity::Info, Category::Trade, message.to_string(), fields);
+    }
+
+    #[test]
+    fn repeats_and_retention() {
+        let mut log = LogBook::new(2);
+        log.set_tick(1);
+        add_info(&mut log, "Need to buy Apple", LogFields::item("Apple"));
+        log.set_tick(2);
+        add_info(&mut log, "Need to buy Apple", LogFields::item("Apple"));
+        assert_eq!(log.get_entries(), vec!["Need to buy Apple (x2)"]);
+        let entry = log.query(&LogQuery::default()).next().unwrap();
+        assert_eq!((entry.tick, entry.last_tick), (1, 2));
+
+        // Entries with other fields are not repeats
+        add_info(&mut log, "Need to buy Apple", LogFields::item("Orange"));
+        add_info(&mut log, "Sold", LogFields::default());
+        assert_eq!(log.get_entries(), vec!["Sold", "Need to buy Apple"]);
+        assert_eq!(log.get_revision(), 4);
+    }
+
+    #[test]
+    fn repeats_every_tick() {
+        let mut log = LogBook::new(3);
+        add_info(&mut log, "Expired", LogFields::default());
+        for tick in 1..=20 {
+            log.set_tick(tick);
+            add_info(&mut log, "Need to buy Apple", LogFields::item("Apple"));
+            add_info(&mut log, "Need to list 1 of Box", LogFields::item("Box"));
+        }
+        assert_eq!(
+            log.get_entries(),
+            vec![
+                "Need to list 1 of Box (x20)",
+                "Need to buy Apple (x20)",
+                "Expired"
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_with_progress() {
+        let mut log = LogBook::default();
+        for progress in [4, 8, 12] {
+            log.set_tick(progress / 4);
+            log.add(
+                Severity::Debug,
+                Category::Production,
+                "Working".to_string(),
+                LogFields::default().with_progress(progress),
+            );
+        }
+        assert_eq!(log.get_entries(), vec!["Working (x3)"]);
+        let entry = log.query(&LogQuery::default()).next().unwrap();
+        assert_eq!(entry.fields.progress, Some(12));
+    }
+
+    #[test]
+    fn query() {
+        let mut log = LogBook::default();
+        log.set_tick(1);
+        add_info(&mut log, "Bought", LogFields::item("Apple"));
+        log.set_tick(2);
+        log.add(
+            Severity::Debug,
+            Category::Production,
+            "Working".to_string(),
+            LogFields::default(),
+        );
+        log.add(
+            Severity::Warning,
+            Category::Trade,
+            "Expired".to_string(),
+            LogFields::item("Apple"),
+        );
+        let get_messages = |query: LogQuery| {
+            log.query(&query)
+                .map(|entry| entry.message.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            get_messages(LogQuery::default()),
+            vec!["Bought", "Working", "Expired"]
+        );
+        assert_eq!(
+            get_messages(LogQuery {
+                min_severity: Some(Severity::Info),
+                ..LogQuery::default()
+            }),
+            vec!["Bought", "Expired"]
+        );
+        assert_eq!(
+            get_messages(LogQuery {
+                category: Some(Category::Trade),
+                since_tick: Some(2),
+                ..LogQuery::default()
+            }),
+            vec!["Expired"]
+        );
+        assert_eq!(
+            get_messages(LogQuery {
+                item_kind: Some("Orange".to_string()),
+                ..LogQuery::default()
+            }),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn entry_as_json() {
+        let entry = LogEntry {
+            tick: 3,
+            last_tick: 3,
+            repeats: 1,
+            severity: Severity::Info,
+            category: Category::Trade,
+            message: "Bought 1 of Apple at 300".to_string(),
+            fields: LogFields::item("Apple").with_count(1).with_price(300),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""category":"trade""#));
+        assert!(json.contains(r#""price":300"#));
+        assert!(!json.contains("market"));
+        assert_eq!(serde_json::from_str::<LogEntry>(&json).unwrap(), entry);
+
+        let mut line = Vec::new();
+        write_log_entry(&mut line, "Farmer", &entry).unwrap();
+        let line = String::from_utf8(line).unwrap();
+        assert!(line.starts_with(r#"{"actor":"Farmer","tick":3,"#));
+        assert!(line.ends_with("}\n"));
+    }
+
+    #[test]
+    fn actor_log() {
+        let world = run(&Scenario::default(), 50);
+        let trades = LogQuery {
+            category: Some(Category::Trade),
+            since_tick: Some(10),
+            ..LogQuery::default()
+        };
+        let log = world.get_actor_log("Actor_5_packer", &trades).unwrap();
+        assert!(!log.is_empty());
+        for entry in log.iter() {
+            assert_eq!(entry.category, Category::Trade);
+            assert!((10..50).contains(&entry.last_tick));
+            assert!(entry.tick <= entry.last_tick);
+        }
+        // Bought every tick, yet counted rather than logged over and over
+        assert!(log.iter().any(|entry| entry.repeats > 1));
+        assert!(world.get_actor_log("Nobody", &trades).is_none());
+
+        let mut export = Vec::new();
+        world.write_actor_logs(&mut export, &trades).unwrap();
+        let lines = String::from_utf8(export).unwrap();
+        let actors = lines
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .map(|entry| entry["actor"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert!(actors.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(
+            actors
+                .iter()
+                .filter(|actor| *actor == "Actor_5_packer")
+                .count(),
+            log.len()
+        );
     }
 }
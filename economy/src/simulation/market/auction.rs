@@ -0,0 +1,495 @@
+use super::{
+    bid::Bid,
+    delivery::Delivery,
+    listing::{Listing, ListingId},
+    Market,
+};
+use crate::simulation::accounting::bank::Bank;
+use rand::{rngs::StdRng, seq::SliceRandom};
+use std::{cmp::Reverse, collections::HashSet};
+
+enum Order {
+    Ask(ListingId),
+    Bid(Bid),
+}
+
+impl Market {
+    ///
+    /// Matches the highest bids with the cheapest listings for as long as they cross, and fills
+    /// all of them at the price halfway between the last bid and listing matched. Bids which are
+    /// not filled are dropped.
+    ///
+    pub(super) fn clear_call_auction(
+        &mut self,
+        item_kind: &str,
+        mut bids: Vec<Bid>,
+        bank: &mut Bank,
+        rng: &mut StdRng,
+    ) {
+        let mut asks = self.get_asks(item_kind);
+        shuffle_fairly(
+            &mut asks,
+            |listing| (get_owner_name(listing), listing.price),
+            rng,
+        );
+        shuffle_fairly(&mut bids, |bid| (bid.buyer_name.clone(), bid.limit), rng);
+
+        // Orders at the same price stay in their shuffled order
+        asks.sort_by_key(|listing| self.get_capped_price(item_kind, listing.price));
+        bids.sort_by_key(|bid| Reverse(bid.limit));
+        let ask_prices = asks
+            .iter()
+            .map(|listing| self.get_capped_price(item_kind, listing.price))
+            .collect::<Vec<_>>();
+        let bid_limits = bids.iter().map(|bid| bid.limit).collect::<Vec<_>>();
+        let asks = asks.iter().map(|listing| listing.id).collect::<Vec<_>>();
+        if let Some((count, price)) = get_uniform_price(&ask_prices, &bid_limits) {
+            for (listing, bid) in asks.iter().zip(bids.iter()).take(count) {
+                self.fill(*listing, bid, price, bank);
+            }
+        }
+        for bid in bids {
+            self.bids.remove(bid.id);
+        }
+    }
+    ///
+    /// Matches the bids placed and the listings which arrived during the tick, one order at a
+    /// time, against the orders resting in the book. Orders which do not cross rest in turn.
+    ///
+    pub(super) fn clear_double_auction(
+        &mut self,
+        item_kind: &str,
+        bids: Vec<Bid>,
+        arrived_listings: Vec<ListingId>,
+        bank: &mut Bank,
+        rng: &mut StdRng,
+    ) {
+        let mut orders = arrived_listings
+            .into_iter()
+            .map(Order::Ask)
+            .chain(bids.into_iter().map(Order::Bid))
+            .collect::<Vec<_>>();
+        shuffle_fairly(
+            &mut orders,
+            |order| match order {
+                Order::Ask(id) => {
+                    let listing = self.listings.get(*id).unwrap();
+                    (get_owner_name(listing), listing.price)
+                }
+                Order::Bid(bid) => (bid.buyer_name.clone(), bid.limit),
+            },
+            rng,
+        );
+
+        // Listings which arrived this tick only rest in the book once it is their turn
+        let mut waiting = orders
+            .iter()
+            .filter_map(|order| match order {
+                Order::Ask(id) => Some(*id),
+                Order::Bid(_) => None,
+            })
+            .collect::<HashSet<_>>();
+        let mut resting_bids = self
+            .resting_bids
+            .remove(item_kind)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| self.bids.get(id).cloned())
+            .collect::<Vec<_>>();
+
+        for order in orders {
+            match order {
+                Order::Bid(bid) => {
+                    let (asks, keys) = self.get_best_asks(item_kind, bid.limit, &waiting);
+                    match choose_best(&keys, rng) {
+                        Some(index) => self.fill(asks[index], &bid, keys[index].0, bank),
+                        None => resting_bids.push(bid),
+                    }
+                }
+                Order::Ask(id) => {
+                    waiting.remove(&id);
+                    let price = match self.listings.get(id) {
+                        Some(listing) => self.get_capped_price(item_kind, listing.price),
+                        None => continue,
+                    };
+                    let keys = resting_bids
+                        .iter()
+                        .map(|bid| (-bid.limit, bid.buyer_name.clone()))
+                        .collect::<Vec<_>>();
+                    match choose_best(&keys, rng) {
+                        Some(index) if resting_bids[index].limit >= price => {
+                            let bid = resting_bids.remove(index);
+                            self.fill(id, &bid, bid.limit, bank);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if !resting_bids.is_empty() {
+            let ids = resting_bids.into_iter().map(|bid| bid.id).collect();
+            self.resting_bids.insert(item_kind.to_string(), ids);
+        }
+    }
+    ///
+    /// Get the active listings of the item kind which have a seller to pay.
+    ///
+    fn get_asks(&self, item_kind: &str) -> Vec<&Listing> {
+        self.get_listings_of_kind(item_kind)
+            .filter(|listing| listing.owner.is_some())
+            .collect()
+    }
+    ///
+    /// Get the cheapest listings of the item kind which have a seller to pay, as long as they are
+    /// at or below the limit, along with their price and owner name. Walks the book from the
+    /// cheapest listing and stops at the first one which costs more, skipping those which are
+    /// still waiting for their turn.
+    ///
+    fn get_best_asks(
+        &self,
+        item_kind: &str,
+        limit: i64,
+        waiting: &HashSet<ListingId>,
+    ) -> (Vec<ListingId>, Vec<(i64, String)>) {
+        let mut asks = Vec::new();
+        let mut keys = Vec::new();
+        let listings = self
+            .get_listings_of_kind(item_kind)
+            .filter(|listing| listing.owner.is_some() && !waiting.contains(&listing.id));
+        for listing in listings {
+            // Capping keeps the order of the book, so nothing further on is any cheaper
+            let price = self.get_capped_price(item_kind, listing.price);
+            let best = keys.first().map_or(limit, |(best, _)| *best);
+            if price > best {
+                break;
+            }
+            asks.push(listing.id);
+            keys.push((price, get_owner_name(listing)));
+        }
+        (asks, keys)
+    }
+    ///
+    /// Sells the listing to the bidder at the given price and hands the item out for delivery.
+    /// The bid is used up either way.
+    ///
+    fn fill(&mut self, listing: ListingId, bid: &Bid, price: i64, bank: &mut Bank) {
+        self.bids.remove(bid.id);
+        let price = self.get_capped_price(&bid.item_kind, price);
+        if let Ok(item) = self.settle(listing, bid.account, price, bank) {
+            self.deliveries.push(Delivery::Bought {
+                buyer: bid.buyer,
+                item,
+                price,
+            });
+        }
+    }
+}
+
+fn get_owner_name(listing: &Listing) -> String {
+    listing
+        .owner
+        .as_ref()
+        .map(|owner| owner.name.clone())
+        .unwrap_or_default()
+}
+
+///
+/// Shuffles the orders after sorting them by who placed them at what price, so the outcome only
+/// depends on the random number generator and not on the order they were placed in.
+///
+fn shuffle_fairly<T>(orders: &mut [T], get_key: impl Fn(&T) -> (String, i64), rng: &mut StdRng) {
+    orders.sort_by_cached_key(get_key);
+    orders.shuffle(rng);
+}
+
+///
+/// Get the number of trades and the price they clear at, given listing prices in ascending and
+/// bid limits in descending order.
+///
+fn get_uniform_price(ask_prices: &[i64], bid_limits: &[i64]) -> Option<(usize, i64)> {
+    let count = ask_prices
+        .iter()
+        .zip(bid_limits.iter())
+        .take_while(|(ask, bid)| ask <= bid)
+        .count();
+    if count == 0 {
+        return None;
+    }
+    Some((count, (ask_prices[count - 1] + bid_limits[count - 1]) / 2))
+}
+
+///
+/// Picks one of the orders at the lowest price at random. Keys are the price and the name of who
+/// placed the order, which puts tied orders in an order of their own before picking.
+///
+fn choose_best(keys: &[(i64, String)], rng: &mut StdRng) -> Option<usize> {
+    let best = keys.iter().map(|(price, _)| *price).min()?;
+    let mut tied = (0..keys.len())
+        .filter(|index| keys[*index].0 == best)
+        .collect::<Vec<_>>();
+    tied.sort_by(|a, b| keys[*a].1.cmp(&keys[*b].1));
+    tied.choose(rng).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        accounting::account::AccountId,
+        actor::ActorId,
+        fixture::run,
+        market::{
+            bid::{BidId, BidResult},
+            item::Item,
+            listing::Owner,
+            mechanism::Mechanism,
+        },
+        scenario::Scenario,
+        world::World,
+    };
+    use rand::SeedableRng;
+    use slotmap::SlotMap;
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    struct Auction {
+        market: Market,
+        bank: Bank,
+        rng: StdRng,
+        actors: SlotMap<ActorId, (String, AccountId)>,
+    }
+    impl Auction {
+        fn new(mechanism: Mechanism) -> Self {
+            let mut market = Market::new("Market");
+            market.set_mechanism("Apple", mechanism);
+            Self {
+                market,
+                bank: Bank::new("Bank"),
+                rng: StdRng::seed_from_u64(0),
+                actors: SlotMap::with_key(),
+            }
+        }
+        fn add_actor(&mut self, name: &str) -> (ActorId, AccountId) {
+            let account = self.bank.open_account(name);
+            (self.actors.insert((name.to_string(), account)), account)
+        }
+        fn list(&mut self, name: &str, price: i64) {
+            let (actor, account) = self.add_actor(name);
+            let owner = Owner {
+                actor,
+                account,
+                name: name.to_string(),
+                is_producer: false,
+            };
+            let item = Item {
+                id: Uuid::new_v4(),
+                kind: "Apple".to_string(),
+            };
+            self.market
+                .list_item(Some(owner), item, price, None, &mut self.bank);
+        }
+        fn bid(&mut self, name: &str, limit: i64) -> BidId {
+            let (actor, account) = self.add_actor(name);
+            match self
+                .market
+                .place_bid(actor, account, "Apple", limit, &mut self.bank)
+            {
+                BidResult::Placed(bid) => bid,
+                _ => panic!("Auctioned bids are placed"),
+            }
+        }
+        fn tick(&mut self) {
+            self.market.tick();
+        }
+        fn clear(&mut self) {
+            self.market.clear_auctions(&mut self.bank, &mut self.rng);
+        }
+        fn get_balance(&self, name: &str) -> i64 {
+            let (_, account) = self
+                .actors
+                .values()
+                .find(|(actor_name, _)| actor_name == name)
+                .unwrap();
+            self.bank.get_balance(*account)
+        }
+    }
+
+    #[test]
+    fn call_auction() {
+        let mut auction = Auction::new(Mechanism::CallAuction);
+        auction.list("A", 300);
+        auction.list("B", 400);
+        auction.list("C", 700);
+        auction.tick();
+        auction.bid("X", 600);
+        auction.bid("Y", 450);
+        let unfilled = auction.bid("Z", 350);
+        auction.clear();
+
+        // Both trades clear halfway between the last listing and bid which cross
+        let prices = auction
+            .market
+            .get_trades()
+            .iter()
+            .map(|trade| trade.price)
+            .collect::<Vec<_>>();
+        assert_eq!(prices, vec![425, 425]);
+        assert_eq!(auction.get_balance("A"), 425);
+        assert_eq!(auction.get_balance("C"), 0);
+        assert_eq!(auction.get_balance("X"), -425);
+        let buyer = auction.actors.keys().nth(3).unwrap();
+        assert!(auction.market.take_deliveries().iter().any(|delivery| matches!(
+            delivery,
+            Delivery::Bought { buyer: to, item, price: 425 } if *to == buyer && item.kind == "Apple"
+        )));
+        assert_eq!(auction.market.get_listed_count("Apple"), 1);
+        // Bids which were not filled are dropped
+        assert!(!auction.market.bids.contains_key(unfilled));
+    }
+
+    #[test]
+    fn double_auction() {
+        let mut auction = Auction::new(Mechanism::DoubleAuction);
+        auction.list("A", 300);
+        auction.tick();
+        auction.clear();
+        assert!(auction.market.get_trades().is_empty());
+
+        // A bid trades at the price of the listing resting in the book
+        auction.tick();
+        let filled = auction.bid("X", 500);
+        let resting = auction.bid("Y", 200);
+        auction.clear();
+        assert!(!auction.market.bids.contains_key(filled));
+        assert!(auction.market.bids.contains_key(resting));
+        assert_eq!(auction.get_balance("X"), -300);
+
+        // A listing trades at the price of the bid resting in the book
+        auction.list("B", 150);
+        auction.tick();
+        auction.clear();
+        assert!(!auction.market.bids.contains_key(resting));
+        assert_eq!(auction.get_balance("B"), 200);
+        assert_eq!(auction.market.get_trades().len(), 1);
+
+        // Cancelled bids no longer rest in the book
+        let cancelled = auction.bid("Z", 400);
+        auction.clear();
+        let buyer = auction.market.bids[cancelled].buyer;
+        auction.market.cancel_bids_of(buyer);
+        auction.list("C", 100);
+        auction.tick();
+        auction.clear();
+        assert!(auction.market.get_trades().is_empty());
+        assert_eq!(auction.market.get_listed_count("Apple"), 1);
+    }
+
+    #[test]
+    fn get_best_asks() {
+        let mut auction = Auction::new(Mechanism::DoubleAuction);
+        for (name, price) in [("A", 100), ("B", 100), ("C", 150), ("D", 300), ("E", 50)] {
+            auction.list(name, price);
+        }
+        let item = Item {
+            id: Uuid::new_v4(),
+            kind: "Apple".to_string(),
+        };
+        auction
+            .market
+            .list_item(None, item, 10, None, &mut auction.bank);
+        auction.tick();
+        let key = |price: i64, name: &str| (price, name.to_string());
+
+        // Listings without a seller are passed over
+        let mut waiting = HashSet::new();
+        let best_asks = |market: &Market, limit: i64, waiting: &HashSet<ListingId>| {
+            market.get_best_asks("Apple", limit, waiting).1
+        };
+        assert_eq!(
+            best_asks(&auction.market, 200, &waiting),
+            vec![key(50, "E")]
+        );
+        // So are listings waiting for their turn, and only the cheapest at or below the limit
+        // are kept
+        let listing = auction.market.get_listings_of_kind("Apple").nth(1).unwrap();
+        waiting.insert(listing.id);
+        assert_eq!(
+            best_asks(&auction.market, 200, &waiting),
+            vec![key(100, "A"), key(100, "B")]
+        );
+        assert!(best_asks(&auction.market, 99, &waiting).is_empty());
+        // Listings above the ceiling tie at it
+        auction.market.set_price_ceiling("Apple", Some(120));
+        assert_eq!(
+            best_asks(&auction.market, 200, &waiting),
+            vec![key(100, "A"), key(100, "B")]
+        );
+        auction.market.set_price_ceiling("Apple", Some(40));
+        assert_eq!(
+            best_asks(&auction.market, 200, &waiting),
+            vec![key(40, "A"), key(40, "B"), key(40, "C"), key(40, "D")]
+        );
+    }
+
+    #[test]
+    fn uniform_price() {
+        // Two pairs cross, the third bid is too low for the third listing
+        assert_eq!(
+            get_uniform_price(&[100, 200, 300], &[400, 250, 150]),
+            Some((2, 225))
+        );
+        assert_eq!(get_uniform_price(&[100, 100], &[100]), Some((1, 100)));
+        assert_eq!(get_uniform_price(&[300], &[200]), None);
+        assert_eq!(get_uniform_price(&[], &[200]), None);
+    }
+
+    #[test]
+    fn choose_best_breaks_ties_by_chance() {
+        let keys = vec![
+            (200, "C".to_string()),
+            (100, "B".to_string()),
+            (100, "A".to_string()),
+        ];
+        let reversed = keys.iter().cloned().rev().collect::<Vec<_>>();
+        let mut picked = HashSet::new();
+        for seed in 0..20 {
+            let index = choose_best(&keys, &mut StdRng::seed_from_u64(seed)).unwrap();
+            let reversed_index = choose_best(&reversed, &mut StdRng::seed_from_u64(seed)).unwrap();
+            // The same order is picked however the keys are ordered
+            assert_eq!(keys[index], reversed[reversed_index]);
+            picked.insert(keys[index].1.clone());
+        }
+        assert_eq!(picked.len(), 2);
+        assert!(choose_best(&[], &mut StdRng::seed_from_u64(0)).is_none());
+    }
+
+    #[test]
+    fn auctions_do_not_depend_on_actor_order() {
+        for mechanism in [Mechanism::CallAuction, Mechanism::DoubleAuction] {
+            let scenario = Scenario {
+                mechanisms: BTreeMap::from([
+                    ("Apple".to_string(), mechanism),
+                    ("FoodBox".to_string(), mechanism),
+                ]),
+                ..Scenario::default()
+            };
+            let mut reversed_scenario = scenario.clone();
+            reversed_scenario.actors.reverse();
+            let world = run(&scenario, 200);
+            let reversed = run(&reversed_scenario, 200);
+            let get_outcome = |world: &World| {
+                let mut actors = world
+                    .get_snapshot()
+                    .actors
+                    .into_iter()
+                    .map(|actor| (actor.name, actor.balance, actor.store_actual))
+                    .collect::<Vec<_>>();
+                actors.sort();
+                actors
+            };
+            assert!(world.get_metrics().trade_count > 0);
+            assert_eq!(get_outcome(&world), get_outcome(&reversed));
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use super::{error::MarketError, item::Item};
+use crate::simulation::{accounting::account::AccountId, actor::ActorId};
+use slotmap::new_key_type;
+
+new_key_type! {
+    pub struct BidId;
+}
+
+///
+/// Order to buy one item of a kind for at most the limit price.
+///
+#[derive(Clone)]
+pub struct Bid {
+    pub id: BidId,
+    pub buyer: ActorId,
+    pub buyer_name: String,
+    pub account: AccountId,
+    pub item_kind: String,
+    pub limit: i64,
+}
+
+///
+/// What became of a bid placed on the market.
+///
+pub enum BidResult {
+    /// A listing was bought right away, handing over its item.
+    Bought(Item),
+    /// The bid waits for an auction. The item is delivered to the buyer once it is filled.
+    Placed(BidId),
+    /// Nothing was listed at or below the limit, or the listing could not be bought.
+    Rejected(MarketError),
+}
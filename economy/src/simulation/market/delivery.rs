@@ -0,0 +1,18 @@
+use super::item::Item;
+use crate::simulation::actor::ActorId;
+
+///
+/// Item the market hands to an actor outside of its turn, delivered by the world once the market
+/// is done.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// The listing of the owner expired unsold.
+    Returned { owner: ActorId, item: Item },
+    /// A bid of the buyer was filled at auction.
+    Bought {
+        buyer: ActorId,
+        item: Item,
+        price: i64,
+    },
+}
@@ -0,0 +1,42 @@
+use super::listing::ListingId;
+use crate::simulation::accounting::error::BankError;
+use std::fmt::Display;
+
+///
+/// Why the market turned a request down.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarketError {
+    /// The listing was bought, cancelled or expired, or never was in this market.
+    ListingNotFound(ListingId),
+    /// The listing is queued and only goes up at the next tick.
+    ListingNotActive(ListingId),
+    /// The listing has no owner who could be paid.
+    SellerGone(ListingId),
+    /// Nothing of the item kind is listed at or below the limit of a bid.
+    NothingAtLimit { item_kind: String, limit: i64 },
+    /// The bank did not move the money.
+    Payment(BankError),
+}
+impl Display for MarketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarketError::ListingNotFound(listing) => write!(f, "listing {:?} not found", listing),
+            MarketError::ListingNotActive(listing) => {
+                write!(f, "listing {:?} is not up yet", listing)
+            }
+            MarketError::SellerGone(listing) => {
+                write!(f, "listing {:?} has no seller to pay", listing)
+            }
+            MarketError::NothingAtLimit { item_kind, limit } => {
+                write!(f, "no {} listed at or below {}", item_kind, limit)
+            }
+            MarketError::Payment(error) => write!(f, "payment failed, {}", error),
+        }
+    }
+}
+impl From<BankError> for MarketError {
+    fn from(error: BankError) -> Self {
+        MarketError::Payment(error)
+    }
+}
@@ -0,0 +1,362 @@
+use super::listing::{Listing, ListingId};
+use crate::simulation::actor::ActorId;
+use slotmap::{SecondaryMap, SlotMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+///
+/// Position of a listing among the listings of its item kind. Cheapest first, and first come first
+/// served at the same price.
+///
+type PriceKey = (i64, u64);
+
+///
+/// Listings of a market, whether they went up yet or not. Active ones are ordered by price within
+/// every item kind, and all are grouped by their owner. Inserting, removing and finding the
+/// cheapest listing take logarithmic time.
+///
+#[derive(Clone, Default)]
+pub(super) struct ListingIndex {
+    listings: SlotMap<ListingId, Listing>,
+    /// Price keys of the active listings.
+    keys: SecondaryMap<ListingId, PriceKey>,
+    by_item_kind: HashMap<String, BTreeMap<PriceKey, ListingId>>,
+    by_owner: HashMap<ActorId, BTreeSet<ListingId>>,
+    next_sequence: u64,
+}
+impl ListingIndex {
+    ///
+    /// Stores a listing which is not active yet, and gives it its id.
+    ///
+    pub(super) fn insert(&mut self, listing: Listing) -> ListingId {
+        let owner = listing.owner.as_ref().map(|owner| owner.actor);
+        let id = self
+            .listings
+            .insert_with_key(|id| Listing { id, ..listing });
+        if let Some(owner) = owner {
+            self.by_owner.entry(owner).or_default().insert(id);
+        }
+        id
+    }
+    ///
+    /// Puts a stored listing up behind every listing of its kind at the same price. Returns false
+    /// if there is no such listing or it is active already.
+    ///
+    pub(super) fn activate(&mut self, id: ListingId) -> bool {
+        let listing = match self.listings.get(id) {
+            Some(listing) if !self.keys.contains_key(id) => listing,
+            _ => return false,
+        };
+        let key = (listing.price, self.next_sequence);
+        self.next_sequence += 1;
+        self.by_item_kind
+            .entry(listing.item.kind.clone())
+            .or_default()
+            .insert(key, id);
+        self.keys.insert(id, key);
+        true
+    }
+    ///
+    /// Removes the listing with the given id, returning it if it was in the index.
+    ///
+    pub(super) fn remove(&mut self, id: ListingId) -> Option<Listing> {
+        let listing = self.listings.remove(id)?;
+        if let Some(key) = self.keys.remove(id) {
+            if let Some(listings) = self.by_item_kind.get_mut(&listing.item.kind) {
+                listings.remove(&key);
+            }
+        }
+        if let Some(owner) = listing.owner.as_ref().map(|owner| owner.actor) {
+            if let Some(ids) = self.by_owner.get_mut(&owner) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.by_owner.remove(&owner);
+                }
+            }
+        }
+        Some(listing)
+    }
+    ///
+    /// Changes the price of a listing. Active listings move to their new price and keep their
+    /// turn among listings at that price. Returns false if no listing with the id is in the index.
+    ///
+    pub(super) fn set_price(&mut self, id: ListingId, price: i64) -> bool {
+        let listing = match self.listings.get_mut(id) {
+            Some(listing) => listing,
+            None => return false,
+        };
+        listing.price = price;
+        if let Some(key) = self.keys.get_mut(id) {
+            let listings = self
+                .by_item_kind
+                .get_mut(&listing.item.kind)
+                .expect("Active listing has no item kind entry");
+            listings.remove(key);
+            *key = (price, key.1);
+            listings.insert(*key, id);
+        }
+        true
+    }
+    pub(super) fn get(&self, id: ListingId) -> Option<&Listing> {
+        self.listings.get(id)
+    }
+    pub(super) fn is_active(&self, id: ListingId) -> bool {
+        self.keys.contains_key(id)
+    }
+    ///
+    /// Get the cheapest active listing of the item kind.
+    ///
+    pub(super) fn get_best(&self, item_kind: &str) -> Option<&Listing> {
+        let id = self.by_item_kind.get(item_kind)?.values().next()?;
+        self.listings.get(*id)
+    }
+    ///
+    /// Get the active listings of the item kind, cheapest first.
+    ///
+    pub(super) fn get_of_kind<'a>(&'a self, item_kind: &str) -> impl Iterator<Item = &'a Listing> {
+        self.by_item_kind
+            .get(item_kind)
+            .into_iter()
+            .flat_map(|listings| listings.values())
+            .filter_map(|id| self.listings.get(*id))
+    }
+    pub(super) fn get_count_of_kind(&self, item_kind: &str) -> usize {
+        self.by_item_kind
+            .get(item_kind)
+            .map_or(0, |listings| listings.len())
+    }
+    ///
+    /// Get the listings of the owner, active or not, in the order of their ids.
+    ///
+    pub(super) fn get_of_owner(&self, owner: ActorId) -> impl Iterator<Item = &Listing> {
+        self.by_owner
+            .get(&owner)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.listings.get(*id))
+    }
+    ///
+    /// Get every item kind which was ever listed, even if none are listed right now.
+    ///
+    pub(super) fn get_item_kinds(&self) -> impl Iterator<Item = &String> {
+        self.by_item_kind.keys()
+    }
+    ///
+    /// Get every active listing.
+    ///
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Listing> {
+        self.keys.keys().filter_map(|id| self.listings.get(id))
+    }
+    ///
+    /// Get every listing, active or not.
+    ///
+    pub(super) fn iter_all(&self) -> impl Iterator<Item = &Listing> {
+        self.listings.values()
+    }
+    ///
+    /// Checks that the price and owner indexes hold exactly the listings they should, describing
+    /// the first listing which is out of place.
+    ///
+    pub(super) fn check(&self) -> Result<(), String> {
+        let mut indexed = 0;
+        for (item_kind, listings) in self.by_item_kind.iter() {
+            for (key, id) in listings.iter() {
+                let listing = self
+                    .listings
+                    .get(*id)
+                    .ok_or(format!("{:?} is listed under {} but gone", id, item_kind))?;
+                if listing.item.kind != *item_kind || self.keys.get(*id) != Some(key) {
+                    return Err(format!(
+                        "{:?} is listed under {} at {:?}",
+                        id, item_kind, key
+                    ));
+                }
+                if key.0 != listing.price {
+                    return Err(format!(
+                        "{:?} is listed at {} but priced at {}",
+                        id, key.0, listing.price
+                    ));
+                }
+                indexed += 1;
+            }
+        }
+        if indexed != self.keys.len() {
+            return Err(format!(
+                "{} listings are active but {} are listed by price",
+                self.keys.len(),
+                indexed
+            ));
+        }
+        for (id, listing) in self.listings.iter() {
+            if listing.id != id {
+                return Err(format!("{:?} is stored as {:?}", listing.id, id));
+            }
+            if let Some(owner) = listing.owner.as_ref() {
+                if !self
+                    .by_owner
+                    .get(&owner.actor)
+                    .is_some_and(|ids| ids.contains(&id))
+                {
+                    return Err(format!("{:?} is missing from its owner", id));
+                }
+            }
+        }
+        for (owner, ids) in self.by_owner.iter() {
+            for id in ids.iter() {
+                let listing_owner = self
+                    .listings
+                    .get(*id)
+                    .and_then(|listing| listing.owner.as_ref());
+                if listing_owner.map(|owner| owner.actor) != Some(*owner) {
+                    return Err(format!(
+                        "{:?} is indexed under {:?} but not theirs",
+                        id, owner
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        accounting::account::AccountId,
+        market::{item::Item, listing::Owner},
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use uuid::Uuid;
+
+    fn create_listing(item_kind: &str, price: i64, owner: Option<ActorId>) -> Listing {
+        Listing::new(
+            owner.map(|actor| Owner {
+                actor,
+                account: AccountId::default(),
+                name: String::new(),
+                is_producer: false,
+            }),
+            Item {
+                id: Uuid::new_v4(),
+                kind: item_kind.to_string(),
+            },
+            price,
+            None,
+        )
+    }
+    fn insert_active(index: &mut ListingIndex, listing: Listing) -> ListingId {
+        let id = index.insert(listing);
+        assert!(index.activate(id));
+        id
+    }
+
+    #[test]
+    fn order_by_price_then_arrival() {
+        let mut index = ListingIndex::default();
+        let first = insert_active(&mut index, create_listing("ABC", 500, None));
+        let second = insert_active(&mut index, create_listing("ABC", 500, None));
+        let cheap = insert_active(&mut index, create_listing("ABC", 250, None));
+        insert_active(&mut index, create_listing("DEF", 100, None));
+        let ids = index.get_of_kind("ABC").map(|listing| listing.id);
+        assert_eq!(ids.collect::<Vec<_>>(), vec![cheap, first, second]);
+        assert_eq!(index.get_best("DEF").unwrap().price, 100);
+        assert!(index.get_best("GHI").is_none());
+
+        // Repricing keeps the turn at the new price
+        index.remove(cheap);
+        assert!(index.set_price(second, 400));
+        assert_eq!(index.get_best("ABC").unwrap().id, second);
+        assert!(index.set_price(second, 500));
+        assert_eq!(index.get_best("ABC").unwrap().id, first);
+        assert!(!index.set_price(cheap, 500));
+        assert!(!index.activate(first));
+
+        // A listing whose price changed behind the index is found out
+        index.listings[first].price = 100;
+        assert!(index.check().unwrap_err().contains("priced at 100"));
+    }
+
+    #[test]
+    fn inactive_listings() {
+        let mut index = ListingIndex::default();
+        let id = index.insert(create_listing("ABC", 500, None));
+        assert!(index.get(id).is_some());
+        assert!(!index.is_active(id));
+        assert_eq!(index.get_count_of_kind("ABC"), 0);
+        assert_eq!(index.iter().count(), 0);
+        assert_eq!(index.iter_all().count(), 1);
+        assert!(index.set_price(id, 400));
+        assert!(index.activate(id));
+        assert_eq!(index.get_best("ABC").unwrap().price, 400);
+    }
+
+    #[test]
+    fn owner_index() {
+        let mut owners = SlotMap::<ActorId, ()>::with_key();
+        let owner_a = owners.insert(());
+        let owner_b = owners.insert(());
+        let mut index = ListingIndex::default();
+        let listing = insert_active(&mut index, create_listing("ABC", 500, Some(owner_a)));
+        insert_active(&mut index, create_listing("ABC", 750, Some(owner_b)));
+        index.insert(create_listing("DEF", 250, Some(owner_a)));
+        assert_eq!(index.get_of_owner(owner_a).count(), 2);
+        assert_eq!(index.get_of_owner(owner_b).count(), 1);
+        index.remove(listing);
+        assert_eq!(index.get_of_owner(owner_a).count(), 1);
+        assert_eq!(index.get_count_of_kind("ABC"), 1);
+        assert!(index.remove(listing).is_none());
+        assert_eq!(index.check(), Ok(()));
+    }
+
+    #[test]
+    fn heavy_churn() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut index = ListingIndex::default();
+        let mut owners = SlotMap::<ActorId, ()>::with_key();
+        let owners = (0..5).map(|_| owners.insert(())).collect::<Vec<_>>();
+        let mut expected = Vec::<(ListingId, String, i64, ActorId)>::new();
+        for _ in 0..5000 {
+            match rng.gen_range(0..4) {
+                0 | 1 => {
+                    let item_kind = ["ABC", "DEF"][rng.gen_range(0..2)];
+                    let price = rng.gen_range(0..50);
+                    let owner = owners[rng.gen_range(0..owners.len())];
+                    let listing = create_listing(item_kind, price, Some(owner));
+                    let id = insert_active(&mut index, listing);
+                    expected.push((id, item_kind.to_string(), price, owner));
+                }
+                2 if !expected.is_empty() => {
+                    let (id, ..) = expected.swap_remove(rng.gen_range(0..expected.len()));
+                    assert!(index.remove(id).is_some());
+                }
+                3 if !expected.is_empty() => {
+                    let position = rng.gen_range(0..expected.len());
+                    let price = rng.gen_range(0..50);
+                    assert!(index.set_price(expected[position].0, price));
+                    expected[position].2 = price;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(index.iter().count(), expected.len());
+        assert_eq!(index.check(), Ok(()));
+        for item_kind in ["ABC", "DEF"] {
+            let mut prices = expected
+                .iter()
+                .filter(|(_, kind, ..)| kind == item_kind)
+                .map(|(_, _, price, _)| *price)
+                .collect::<Vec<_>>();
+            prices.sort();
+            let indexed = index.get_of_kind(item_kind).map(|listing| listing.price);
+            assert_eq!(indexed.collect::<Vec<_>>(), prices);
+            assert_eq!(
+                index.get_best(item_kind).map(|listing| listing.price),
+                prices.first().copied()
+            );
+        }
+        for owner in owners.iter() {
+            let count = expected.iter().filter(|(.., id)| id == owner).count();
+            assert_eq!(index.get_of_owner(*owner).count(), count);
+        }
+    }
+}
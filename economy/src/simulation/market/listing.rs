@@ -1,21 +1,48 @@
 use super::item::Item;
-use crate::simulation::actor::Actor;
-use std::{cell::RefCell, rc::Weak};
-use uuid::Uuid;
+use crate::simulation::{accounting::account::AccountId, actor::ActorId};
+use slotmap::new_key_type;
 
+new_key_type! {
+    pub struct ListingId;
+}
+
+///
+/// Who listed an item, and everything the market needs to know to pay them.
+///
+#[derive(Clone, Debug)]
+pub struct Owner {
+    pub actor: ActorId,
+    pub account: AccountId,
+    pub name: String,
+    /// Whether the owner produced the item, which makes selling it income.
+    pub is_producer: bool,
+}
+
+#[derive(Clone)]
 pub struct Listing {
-    pub id: Uuid,
-    pub owner: Option<Weak<RefCell<Actor>>>,
+    pub id: ListingId,
+    pub owner: Option<Owner>,
     pub item: Item,
     pub price: i64,
+    /// Market tick at the start of which the item goes back to its owner unsold, if ever.
+    pub expires_at: Option<u64>,
 }
 impl Listing {
-    pub(super) fn new(owner: Option<Weak<RefCell<Actor>>>, item: Item, price: i64) -> Self {
+    ///
+    /// Get a listing which gets its id once the market stores it.
+    ///
+    pub(super) fn new(
+        owner: Option<Owner>,
+        item: Item,
+        price: i64,
+        expires_at: Option<u64>,
+    ) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ListingId::default(),
             owner,
             item,
             price,
+            expires_at,
         }
     }
 }
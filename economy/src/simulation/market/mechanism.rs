@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+///
+/// How the market matches buyers with the listings of an item kind.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mechanism {
+    /// Bids buy the cheapest listing right away, so the first buyer of a tick is served first.
+    #[default]
+    Continuous,
+    /// Bids are collected during the tick and cleared together at its end, every trade at the
+    /// same price. Bids which find no seller are dropped.
+    CallAuction,
+    /// Bids and listings rest in a book until they cross, each trade at the price of the order
+    /// which was resting. Orders arriving during a tick are matched in a seeded random sequence.
+    DoubleAuction,
+}
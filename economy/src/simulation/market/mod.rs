@@ -1,435 +1,963 @@
-use self::{item::Item, listing::Listing};
+use self::{
+    bid::{Bid, BidId, BidResult},
+    delivery::Delivery,
+    error::MarketError,
+    index::ListingIndex,
+    item::Item,
+    listing::{Listing, ListingId, Owner},
+    mechanism::Mechanism,
+    trade::Trade,
+};
 use super::{
-    accounting::{account::Account, bank::Bank},
-    actor::Actor,
+    accounting::{account::AccountId, bank::Bank},
+    actor::ActorId,
+    government::{get_tax, Policy, Revenue},
 };
+use rand::rngs::StdRng;
+use slotmap::SlotMap;
 use std::{
-    cell::RefCell,
-    collections::{HashMap, VecDeque},
-    rc::{Rc, Weak},
+    collections::{BTreeMap, HashMap, VecDeque},
+    mem,
 };
-use uuid::Uuid;
+use tracing::{debug, instrument, warn};
 
+mod auction;
+pub mod bid;
+pub mod delivery;
+pub mod error;
+mod index;
 pub mod item;
 pub mod listing;
+pub mod mechanism;
+pub mod trade;
 
+///
+/// Where actors list and buy items. Listed items are held in escrow by their listing until they
+/// are sold, and otherwise go back to the owner, so no item is lost on the way.
+///
+#[derive(Clone)]
 pub struct Market {
-    listings_by_id: HashMap<Uuid, Rc<Listing>>,
-    listings_by_item_kind: HashMap<String, Vec<Weak<Listing>>>,
-    listings_by_owner_id: HashMap<Uuid, Vec<Weak<Listing>>>,
-    listing_queue: VecDeque<Rc<Listing>>,
+    name: String,
+    /// Number of times the market was ticked.
+    tick: u64,
+    listings: ListingIndex,
+    /// Listings which go up at the next tick. Ids of cancelled listings are skipped.
+    listing_queue: VecDeque<ListingId>,
+    /// Ids of active listings by the tick they expire at.
+    expiries: BTreeMap<u64, Vec<ListingId>>,
+    price_ceilings: HashMap<String, i64>,
+    mechanisms: BTreeMap<String, Mechanism>,
+    /// Bids which have not been filled yet.
+    bids: SlotMap<BidId, Bid>,
+    /// Bids placed on auctioned item kinds during the current tick.
+    bid_queue: Vec<BidId>,
+    /// Bids in double auctions which have not found a seller yet.
+    resting_bids: BTreeMap<String, Vec<BidId>>,
+    /// Ids of listings in double auctions which became active this tick.
+    arrived_listings: Vec<ListingId>,
+    /// Items waiting to be handed to actors.
+    deliveries: Vec<Delivery>,
+    /// Taxes charged on trades and listings, paid into the treasury.
+    policy: Policy,
+    treasury: Option<AccountId>,
+    /// Taxes collected during the current tick.
+    revenue: Revenue,
+    trades: Vec<Trade>,
+    trade_count: usize,
+    trade_volume: u64,
 }
 impl Market {
-    pub(super) fn new() -> Self {
+    pub(super) fn new(name: &str) -> Self {
         Self {
-            listings_by_id: HashMap::new(),
-            listings_by_item_kind: HashMap::new(),
-            listings_by_owner_id: HashMap::new(),
+            name: name.to_string(),
+            tick: 0,
+            listings: ListingIndex::default(),
             listing_queue: VecDeque::new(),
+            expiries: BTreeMap::new(),
+            price_ceilings: HashMap::new(),
+            mechanisms: BTreeMap::new(),
+            bids: SlotMap::with_key(),
+            bid_queue: Vec::new(),
+            resting_bids: BTreeMap::new(),
+            arrived_listings: Vec::new(),
+            deliveries: Vec::new(),
+            policy: Policy::default(),
+            treasury: None,
+            revenue: Revenue::default(),
+            trades: Vec::new(),
+            trade_count: 0,
+            trade_volume: 0,
         }
     }
     ///
-    /// Processes all enqueued listings
+    /// Returns expired listings to their owners, processes all enqueued listings and starts a new
+    /// trade record for the tick.
     ///
+    #[instrument(name = "market_tick", level = "debug", skip_all, fields(market = %self.name))]
     pub(super) fn tick(&mut self) {
-        while let Some(pending_listing) = self.listing_queue.pop_front() {
-            let uuid = pending_listing.id;
+        self.tick += 1;
+        self.trades.clear();
+        self.revenue = Revenue::default();
 
-            // Add main listing
-            self.listings_by_id
-                .insert(uuid.clone(), pending_listing.clone());
+        // Return expired listings
+        let later = self.expiries.split_off(&(self.tick + 1));
+        for id in mem::replace(&mut self.expiries, later)
+            .into_values()
+            .flatten()
+        {
+            if let Some(listing) = self.listings.remove(id) {
+                if let Some(owner) = listing.owner {
+                    self.deliveries.push(Delivery::Returned {
+                        owner: owner.actor,
+                        item: listing.item,
+                    });
+                }
+            }
+        }
 
-            // Add to item kind index
-            if !self
-                .listings_by_item_kind
-                .contains_key(&pending_listing.item.kind)
-            {
-                self.listings_by_item_kind.insert(
-                    pending_listing.item.kind.clone(),
-                    vec![Rc::downgrade(&pending_listing)],
-                );
-            } else {
-                self.listings_by_item_kind
-                    .get_mut(&pending_listing.item.kind)
-                    .unwrap()
-                    .push(Rc::downgrade(&pending_listing));
+        while let Some(id) = self.listing_queue.pop_front() {
+            // Skip listings which were cancelled before they went up
+            if !self.listings.activate(id) {
+                continue;
             }
+            let listing = self.listings.get(id).unwrap();
 
-            // Add to owner id index
-            if let Some(owner) = pending_listing
-                .owner
-                .as_ref()
-                .and_then(|owner| owner.upgrade())
-            {
-                if !self.listings_by_owner_id.contains_key(&owner.borrow().id) {
-                    self.listings_by_owner_id
-                        .insert(owner.borrow().id, vec![Rc::downgrade(&pending_listing)]);
-                } else {
-                    self.listings_by_owner_id
-                        .get_mut(&owner.borrow().id)
-                        .unwrap()
-                        .push(Rc::downgrade(&pending_listing));
-                }
+            if self.get_mechanism(&listing.item.kind) == Mechanism::DoubleAuction {
+                self.arrived_listings.push(id);
+            }
+            if let Some(expires_at) = listing.expires_at {
+                self.expiries.entry(expires_at).or_default().push(id);
             }
         }
     }
     ///
-    /// List item on market at a given price. Items that are listed are able to be unlisted.
+    /// List item on market at a given price. Items that are listed are able to be unlisted. The
+    /// price is capped by the price ceiling of the item kind, if there is one.
     ///
+    /// Listings with a time to live stay on the market for that many ticks once they go up, after
+    /// which the item goes back to the owner. Owners pay the listing fee right away, even if they
+    /// cancel the listing before it goes up.
+    ///
+    #[instrument(level = "debug", skip_all, fields(market = %self.name, item_kind = %item.kind, price = price))]
     pub(super) fn list_item(
         &mut self,
-        owner: Option<Weak<RefCell<Actor>>>,
+        owner: Option<Owner>,
         item: Item,
         price: i64,
-    ) -> Weak<Listing> {
-        let listing = Rc::new(Listing::new(owner.clone(), item.clone(), price));
+        ttl: Option<u64>,
+        bank: &mut Bank,
+    ) -> ListingId {
+        // Charge listing fee
+        let fee = self.policy.listing_fee;
+        let owner_account = owner.as_ref().map(|owner| owner.account);
+        if let (Some(owner_account), Some(treasury)) = (owner_account, self.treasury) {
+            if fee > 0 && self.collect("listing fee", owner_account, treasury, fee, bank) {
+                self.revenue.listing_fees += fee;
+            }
+        }
+
+        let price = self.get_capped_price(&item.kind, price);
+        let expires_at = ttl.map(|ttl| self.tick + 1 + ttl.max(1));
+        let id = self
+            .listings
+            .insert(Listing::new(owner, item, price, expires_at));
 
         // Enque listing
-        self.listing_queue.push_back(listing.clone());
+        self.listing_queue.push_back(id);
 
-        Rc::downgrade(&listing)
+        id
     }
     ///
-    /// Unlist an item from the market given a Weak reference to the item. If the Weak reference
-    /// can not be upgraded, no action will be taken.
+    /// Takes a listing off the market, or out of the queue if it did not go up yet, and returns
+    /// its item.
     ///
-    pub(super) fn unlist_item(&mut self, listing: Weak<Listing>) {
-        let listing = listing.upgrade();
-        if listing.is_none() {
-            return;
-        }
-        let listing = listing.unwrap();
-
-        // Ensure listing exists in market
-        if !self.listings_by_id.contains_key(&listing.id) {
-            return;
-        }
-
-        // Remove main listing
-        self.listings_by_id.remove(&listing.id);
-
-        // Remove listing from item kind index
-        self.listings_by_item_kind
-            .get_mut(&listing.item.kind)
-            .unwrap()
-            .retain(|l| l.upgrade().unwrap().id != listing.id);
-
-        // Remove listing from owner id index
-        if let Some(owner) = listing.owner.as_ref().and_then(|owner| owner.upgrade()) {
-            self.listings_by_owner_id
-                .get_mut(&owner.borrow().id)
-                .unwrap()
-                .retain(|l| l.upgrade().unwrap().id != listing.id);
+    pub(super) fn cancel_listing(&mut self, id: ListingId) -> Result<Item, MarketError> {
+        self.listings
+            .remove(id)
+            .map(|listing| listing.item)
+            .ok_or(MarketError::ListingNotFound(id))
+    }
+    ///
+    /// Takes every listing of the owner off the market and out of the queue, and returns their
+    /// items.
+    ///
+    pub(super) fn cancel_listings_of(&mut self, owner: ActorId) -> Vec<Item> {
+        let ids = self
+            .get_listings_of_owner(owner)
+            .map(|listing| listing.id)
+            .collect::<Vec<_>>();
+        ids.into_iter()
+            .filter_map(|id| self.cancel_listing(id).ok())
+            .collect()
+    }
+    ///
+    /// Changes the price of a listing, capped like a new one.
+    ///
+    pub(super) fn reprice_listing(&mut self, id: ListingId, price: i64) -> Result<(), MarketError> {
+        let listing = self
+            .listings
+            .get(id)
+            .ok_or(MarketError::ListingNotFound(id))?;
+        let price = self.get_capped_price(&listing.item.kind, price);
+        self.listings.set_price(id, price);
+        Ok(())
+    }
+    ///
+    /// Get the number of items of every kind held for listings, whether they went up yet or not.
+    ///
+    pub(super) fn get_escrow(&self) -> BTreeMap<String, isize> {
+        let mut escrow = BTreeMap::new();
+        for listing in self.listings.iter_all() {
+            *escrow.entry(listing.item.kind.clone()).or_default() += 1;
         }
+        escrow
     }
     ///
-    /// Get an iterator over each item kind present in the listing map.
+    /// Get an iterator over every item kind which was listed at some point.
     ///
     pub(super) fn get_listed_item_kinds(&self) -> impl Iterator<Item = &String> {
-        self.listings_by_item_kind.keys()
+        self.listings.get_item_kinds()
     }
     ///
-    /// Get a Vec of Weak references to listings for items of the given kind. There are no
-    /// guarentees the Weak references stay valid at any point after the call to this method.
+    /// Get the active listings for items of the given kind, cheapest first.
     ///
-    pub(super) fn get_listings_of_kind(&self, kind: &str) -> Vec<Weak<Listing>> {
-        match self.listings_by_item_kind.get(kind) {
-            None => Vec::new(),
-            Some(listings) => {
-                let mut sorted = listings.clone();
-                sorted.sort_by(|a, b| a.upgrade().unwrap().price.cmp(&b.upgrade().unwrap().price));
-                sorted
-            }
-        }
+    pub(super) fn get_listings_of_kind<'a>(
+        &'a self,
+        kind: &str,
+    ) -> impl Iterator<Item = &'a Listing> {
+        self.listings.get_of_kind(kind)
+    }
+    ///
+    /// Get the number of active listings for items of the given kind.
+    ///
+    pub(super) fn get_listed_count(&self, kind: &str) -> usize {
+        self.listings.get_count_of_kind(kind)
+    }
+    ///
+    /// Get the listings of the actor, whether they went up yet or not.
+    ///
+    pub(super) fn get_listings_of_owner(&self, owner: ActorId) -> impl Iterator<Item = &Listing> {
+        self.listings.get_of_owner(owner)
+    }
+    ///
+    /// Get the cheapest active listing for items of the given kind.
+    ///
+    pub(super) fn get_best_listing(&self, item_kind: &str) -> Option<ListingId> {
+        self.listings.get_best(item_kind).map(|listing| listing.id)
+    }
+    ///
+    /// Get the lowest price an item of the kind can be bought for.
+    ///
+    pub(super) fn get_best_ask(&self, item_kind: &str) -> Option<i64> {
+        let listing = self.listings.get_best(item_kind)?;
+        Some(self.get_capped_price(item_kind, listing.price))
     }
     ///
-    /// Removes the listing from the market and moves payment from buyer to seller
+    /// Removes the listing from the market and moves payment from buyer to seller. Returns the
+    /// item bought.
     ///
+    #[instrument(level = "debug", skip_all, fields(market = %self.name))]
     pub(super) fn buy_listing(
         &mut self,
-        listing: Weak<Listing>,
-        buyer_account: Weak<RefCell<Account>>,
-    ) -> bool {
+        id: ListingId,
+        buyer_account: AccountId,
+        bank: &mut Bank,
+    ) -> Result<Item, MarketError> {
         // Ensure valid listing
-        let listing = listing.upgrade();
-        if listing.is_none() {
-            return false;
+        let listing = self
+            .listings
+            .get(id)
+            .ok_or(MarketError::ListingNotFound(id))?;
+        if !self.listings.is_active(id) {
+            return Err(MarketError::ListingNotActive(id));
         }
-        let listing = listing.unwrap();
-
+        let amount = self.get_capped_price(&listing.item.kind, listing.price);
+        self.settle(id, buyer_account, amount, bank)
+    }
+    ///
+    /// Moves the amount from buyer to seller, removes the listing and records the trade. Returns
+    /// the item of the listing. Nothing changes if the seller can not be paid.
+    ///
+    /// The buyer pays sales tax on top of the amount, unless it is the treasury itself, and the
+    /// seller pays income tax on it if the item is an output of its task.
+    ///
+    fn settle(
+        &mut self,
+        id: ListingId,
+        buyer_account: AccountId,
+        amount: i64,
+        bank: &mut Bank,
+    ) -> Result<Item, MarketError> {
         // Ensure valid seller account
-        let seller_account = listing
+        let seller = self
+            .listings
+            .get(id)
+            .ok_or(MarketError::ListingNotFound(id))?
             .owner
             .clone()
-            .and_then(|owner| owner.upgrade())
-            .and_then(|owner| Some(owner.borrow().get_account()));
-        if seller_account.is_none() {
-            return false;
-        }
-        let seller_account = seller_account.unwrap();
+            .ok_or(MarketError::SellerGone(id))?;
+        let buyer_name = bank
+            .get_account(buyer_account)
+            .map(|account| account.get_name())
+            .unwrap_or_default();
 
         // Process transation in applicable direction
-        let amount = listing.price;
         if amount >= 0 {
-            Bank::process_transaction(buyer_account, seller_account, amount as u64);
+            bank.process_transaction(buyer_account, seller.account, amount as u64)?;
         } else {
-            Bank::process_transaction(seller_account, buyer_account, -amount as u64);
+            bank.process_transaction(seller.account, buyer_account, -amount as u64)?;
+        }
+
+        // Collect taxes
+        if let Some(treasury) = self.treasury {
+            let sales_tax = get_tax(amount, self.policy.sales_tax);
+            if sales_tax > 0
+                && buyer_account != treasury
+                && self.collect("sales tax", buyer_account, treasury, sales_tax, bank)
+            {
+                self.revenue.sales_tax += sales_tax;
+            }
+            let income_tax = get_tax(amount, self.policy.income_tax);
+            if seller.is_producer
+                && income_tax > 0
+                && self.collect("income tax", seller.account, treasury, income_tax, bank)
+            {
+                self.revenue.income_tax += income_tax;
+            }
         }
 
         // Remove listing assuming all went well
-        self.unlist_item(Rc::downgrade(&listing));
+        let listing = self.listings.remove(id).unwrap();
+
+        // Record trade
+        debug!(
+            market = %self.name,
+            item_kind = %listing.item.kind,
+            price = amount,
+            seller = %seller.name,
+            buyer = %buyer_name,
+            "trade"
+        );
+        self.trades.push(Trade {
+            market: self.name.clone(),
+            item_kind: listing.item.kind.clone(),
+            price: amount,
+            seller: Some(seller.name),
+            buyer: buyer_name,
+        });
+        self.trade_count += 1;
+        self.trade_volume += amount.unsigned_abs();
 
-        true
+        Ok(listing.item)
+    }
+    ///
+    /// Moves a tax or fee into the treasury. Returns whether the bank moved it, warning about it
+    /// otherwise, which only happens if the payer or the treasury closed its account.
+    ///
+    fn collect(
+        &self,
+        charge: &str,
+        payer: AccountId,
+        treasury: AccountId,
+        amount: u64,
+        bank: &mut Bank,
+    ) -> bool {
+        match bank.process_transaction(payer, treasury, amount) {
+            Ok(_) => true,
+            Err(error) => {
+                warn!(market = %self.name, amount, %error, "{} not collected", charge);
+                false
+            }
+        }
+    }
+    ///
+    /// Places a bid for one item of the kind at up to the limit price. Item kinds which trade
+    /// continuously buy the cheapest listing right away, auctioned ones fill the bid when the
+    /// auction clears.
+    ///
+    #[instrument(level = "debug", skip_all, fields(market = %self.name, item_kind = %item_kind, limit = limit))]
+    pub(super) fn place_bid(
+        &mut self,
+        buyer: ActorId,
+        buyer_account: AccountId,
+        item_kind: &str,
+        limit: i64,
+        bank: &mut Bank,
+    ) -> BidResult {
+        if self.get_mechanism(item_kind) != Mechanism::Continuous {
+            let buyer_name = bank
+                .get_account(buyer_account)
+                .map(|account| account.get_name())
+                .unwrap_or_default();
+            let id = self.bids.insert_with_key(|id| Bid {
+                id,
+                buyer,
+                buyer_name,
+                account: buyer_account,
+                item_kind: item_kind.to_string(),
+                limit,
+            });
+            self.bid_queue.push(id);
+            return BidResult::Placed(id);
+        }
+        let is_affordable = self
+            .get_best_ask(item_kind)
+            .is_some_and(|price| price <= limit);
+        let cheapest = match self.get_best_listing(item_kind) {
+            Some(id) if is_affordable => id,
+            _ => {
+                return BidResult::Rejected(MarketError::NothingAtLimit {
+                    item_kind: item_kind.to_string(),
+                    limit,
+                })
+            }
+        };
+        match self.buy_listing(cheapest, buyer_account, bank) {
+            Ok(item) => BidResult::Bought(item),
+            Err(error) => BidResult::Rejected(error),
+        }
+    }
+    ///
+    /// Withdraw every bid of the buyer which has not been filled yet.
+    ///
+    pub(super) fn cancel_bids_of(&mut self, buyer: ActorId) {
+        self.bids.retain(|_, bid| bid.buyer != buyer);
+    }
+    ///
+    /// Get the bids of the buyer which have not been filled yet.
+    ///
+    pub(super) fn get_bids_of(&self, buyer: ActorId) -> impl Iterator<Item = &Bid> {
+        self.bids.values().filter(move |bid| bid.buyer == buyer)
+    }
+    ///
+    /// Get an iterator over every bid which has not been filled yet.
+    ///
+    pub(super) fn get_bids(&self) -> impl Iterator<Item = &Bid> {
+        self.bids.values()
+    }
+    ///
+    /// Clears the auctions of every auctioned item kind with the orders of the tick. Ties between
+    /// orders at the same price are broken with the random number generator.
+    ///
+    #[instrument(level = "debug", skip_all, fields(market = %self.name))]
+    pub(super) fn clear_auctions(&mut self, bank: &mut Bank, rng: &mut StdRng) {
+        let mut bids_by_item_kind = BTreeMap::<String, Vec<Bid>>::new();
+        for id in mem::take(&mut self.bid_queue) {
+            if let Some(bid) = self.bids.get(id) {
+                bids_by_item_kind
+                    .entry(bid.item_kind.clone())
+                    .or_default()
+                    .push(bid.clone());
+            }
+        }
+        let mut arrived_by_item_kind = BTreeMap::<String, Vec<ListingId>>::new();
+        for id in mem::take(&mut self.arrived_listings) {
+            if let Some(listing) = self.listings.get(id) {
+                arrived_by_item_kind
+                    .entry(listing.item.kind.clone())
+                    .or_default()
+                    .push(id);
+            }
+        }
+
+        for (item_kind, mechanism) in self.mechanisms.clone() {
+            let bids = bids_by_item_kind.remove(&item_kind).unwrap_or_default();
+            match mechanism {
+                Mechanism::Continuous => {}
+                Mechanism::CallAuction => self.clear_call_auction(&item_kind, bids, bank, rng),
+                Mechanism::DoubleAuction => {
+                    let arrived_listings =
+                        arrived_by_item_kind.remove(&item_kind).unwrap_or_default();
+                    self.clear_double_auction(&item_kind, bids, arrived_listings, bank, rng);
+                }
+            }
+        }
+    }
+    ///
+    /// Checks that the listings and bids of the market are indexed consistently and belong to
+    /// actors which exist, describing the first one which does not.
+    ///
+    pub(super) fn check(&self, is_actor: impl Fn(ActorId) -> bool) -> Result<(), String> {
+        self.listings.check()?;
+        for id in self.listing_queue.iter() {
+            if self.listings.is_active(*id) {
+                return Err(format!("{:?} is queued but up already", id));
+            }
+        }
+        for listing in self.listings.iter_all() {
+            if let Some(owner) = listing
+                .owner
+                .as_ref()
+                .filter(|owner| !is_actor(owner.actor))
+            {
+                return Err(format!(
+                    "{:?} is owned by {} who is gone",
+                    listing.id, owner.name
+                ));
+            }
+        }
+        for (id, bid) in self.bids.iter() {
+            if bid.id != id {
+                return Err(format!("{:?} is stored as {:?}", bid.id, id));
+            }
+            if !is_actor(bid.buyer) {
+                return Err(format!(
+                    "{:?} is placed by {} who is gone",
+                    id, bid.buyer_name
+                ));
+            }
+        }
+        Ok(())
+    }
+    ///
+    /// Get the items handed out since the last call, for the world to deliver.
+    ///
+    pub(super) fn take_deliveries(&mut self) -> Vec<Delivery> {
+        mem::take(&mut self.deliveries)
+    }
+    ///
+    /// Charge the taxes of the policy from now on, paying them into the treasury account.
+    ///
+    pub(super) fn set_policy(&mut self, policy: &Policy, treasury: AccountId) {
+        self.policy = policy.clone();
+        self.treasury = Some(treasury);
+    }
+    ///
+    /// Get the taxes collected since the start of the current tick.
+    ///
+    pub(super) fn get_revenue(&self) -> &Revenue {
+        &self.revenue
+    }
+    pub(super) fn set_mechanism(&mut self, item_kind: &str, mechanism: Mechanism) {
+        self.mechanisms.insert(item_kind.to_string(), mechanism);
+    }
+    pub(super) fn get_mechanism(&self, item_kind: &str) -> Mechanism {
+        self.mechanisms.get(item_kind).copied().unwrap_or_default()
+    }
+    ///
+    /// Cap the price of an item kind, or lift the cap with `None`. New listings are capped when
+    /// listed, existing listings above the ceiling sell at the ceiling.
+    ///
+    pub(super) fn set_price_ceiling(&mut self, item_kind: &str, ceiling: Option<i64>) {
+        match ceiling {
+            Some(ceiling) => self.price_ceilings.insert(item_kind.to_string(), ceiling),
+            None => self.price_ceilings.remove(item_kind),
+        };
+    }
+    fn get_capped_price(&self, item_kind: &str, price: i64) -> i64 {
+        match self.price_ceilings.get(item_kind) {
+            Some(ceiling) => price.min(*ceiling),
+            None => price,
+        }
+    }
+    pub(super) fn get_name(&self) -> &str {
+        &self.name
+    }
+    ///
+    /// Get an iterator over every active listing.
+    ///
+    pub(super) fn get_listings(&self) -> impl Iterator<Item = &Listing> {
+        self.listings.iter()
+    }
+    ///
+    /// Get the trades which happened since the start of the current tick.
+    ///
+    pub(super) fn get_trades(&self) -> &Vec<Trade> {
+        &self.trades
+    }
+    ///
+    /// Get the number of listings bought since the market was created.
+    ///
+    pub(super) fn get_trade_count(&self) -> usize {
+        self.trade_count
+    }
+    ///
+    /// Get the total amount of money which changed hands through bought listings.
+    ///
+    pub(super) fn get_trade_volume(&self) -> u64 {
+        self.trade_volume
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::simulation::accounting::bank::Bank;
+    use crate::simulation::{
+        accounting::error::BankError,
+        fixture::{get_edge_total, run},
+        logbook::{Category, LogQuery, Severity},
+        scenario::Scenario,
+    };
     use uuid::Uuid;
 
+    ///
+    /// Actors and accounts for the owners and buyers of the tests.
+    ///
+    struct Parties {
+        bank: Bank,
+        actors: SlotMap<ActorId, ()>,
+    }
+    impl Parties {
+        fn new() -> Self {
+            Self {
+                bank: Bank::new("Bank"),
+                actors: SlotMap::with_key(),
+            }
+        }
+        fn add(&mut self, name: &str) -> Owner {
+            Owner {
+                actor: self.actors.insert(()),
+                account: self.bank.open_account(name),
+                name: name.to_string(),
+                is_producer: false,
+            }
+        }
+    }
+
+    fn create_item(kind: &str) -> Item {
+        Item {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+        }
+    }
+
     #[test]
     fn list_item() {
-        let mut market = Market::new();
-        market.list_item(
-            None,
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
-            500,
-        );
-        assert_eq!(market.listings_by_item_kind.len(), 1);
+        let mut bank = Bank::new("Bank");
+        let mut market = Market::new("Market");
+        market.list_item(None, create_item("ABC"), 500, None, &mut bank);
+        // Listings go up when the market ticks
+        assert_eq!(market.get_listed_count("ABC"), 0);
+        market.tick();
+        assert_eq!(market.get_listed_item_kinds().count(), 1);
         assert_eq!(
-            market
-                .listings_by_item_kind
-                .get("ABC")
-                .unwrap()
-                .first()
-                .unwrap()
-                .upgrade()
-                .unwrap()
-                .price,
+            market.get_listings_of_kind("ABC").next().unwrap().price,
             500
         );
     }
 
     #[test]
-    fn get_listings_of_kind() {
-        let mut market = Market::new();
-        market.list_item(
-            None,
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+    fn listing_fee() {
+        let mut parties = Parties::new();
+        let owner = parties.add("A");
+        let treasury = parties.bank.open_account("Government");
+        let mut market = Market::new("Market");
+        let policy = Policy {
+            listing_fee: 5,
+            ..Policy::default()
+        };
+        market.set_policy(&policy, treasury);
+        // Owners pay as they list, whether or not the listing ever goes up
+        let listing = market.list_item(
+            Some(owner.clone()),
+            create_item("ABC"),
             500,
-        );
-        market.list_item(
             None,
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
-            750,
-        );
-        market.list_item(
-            None,
-            Item {
-                id: Uuid::new_v4(),
-                kind: "DEF".to_string(),
-            },
-            250,
+            &mut parties.bank,
         );
+        assert_eq!(parties.bank.get_balance(owner.account), -5);
+        assert_eq!(market.get_revenue().listing_fees, 5);
+        assert!(market.cancel_listing(listing).is_ok());
+        assert_eq!(parties.bank.get_balance(treasury), 5);
+        // Listings without an owner are free
+        market.list_item(None, create_item("ABC"), 500, None, &mut parties.bank);
+        assert_eq!(parties.bank.get_balance(treasury), 5);
+        // Revenue is counted per tick
+        market.tick();
+        assert_eq!(market.get_revenue().listing_fees, 0);
+    }
+
+    #[test]
+    fn get_listings_of_kind() {
+        let mut bank = Bank::new("Bank");
+        let mut market = Market::new("Market");
+        market.list_item(None, create_item("ABC"), 750, None, &mut bank);
+        market.list_item(None, create_item("ABC"), 500, None, &mut bank);
+        market.list_item(None, create_item("DEF"), 250, None, &mut bank);
+        market.tick();
         // Two different kinds of listings
-        assert_eq!(market.listings_by_item_kind.len(), 2);
-        // Two listings of kind 'ABC'
-        assert_eq!(market.listings_by_item_kind.get("ABC").unwrap().len(), 2);
-        // Get listings of specific kind
-        let abcs = market.get_listings_of_kind("ABC");
-        assert_eq!(abcs.len(), 2);
+        assert_eq!(market.get_listed_item_kinds().count(), 2);
+        // Two listings of kind 'ABC', cheapest first
+        let abcs = market
+            .get_listings_of_kind("ABC")
+            .map(|listing| listing.price)
+            .collect::<Vec<_>>();
+        assert_eq!(abcs, vec![500, 750]);
+        assert_eq!(market.get_best_ask("ABC"), Some(500));
         // Get listings of non-existing item kind
-        let should_be_empty = market.get_listings_of_kind("GHI");
-        assert_eq!(should_be_empty.len(), 0);
+        assert_eq!(market.get_listings_of_kind("GHI").count(), 0);
+        assert!(market.get_best_ask("GHI").is_none());
     }
 
     #[test]
     fn get_listings_of_owner() {
-        let bank = Bank::new("Bank");
-        let owner_a = Actor::new("A", bank.clone(), None);
-        let owner_b = Actor::new("B", bank.clone(), None);
-        let mut market = Market::new();
+        let mut parties = Parties::new();
+        let owner_a = parties.add("A");
+        let owner_b = parties.add("B");
+        let mut market = Market::new("Market");
         market.list_item(
-            Some(owner_a.borrow().id),
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+            Some(owner_a.clone()),
+            create_item("ABC"),
             500,
+            None,
+            &mut parties.bank,
         );
         market.list_item(
-            Some(owner_b.borrow().id),
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+            Some(owner_b.clone()),
+            create_item("ABC"),
             750,
+            None,
+            &mut parties.bank,
         );
         market.list_item(
-            Some(owner_a.borrow().id),
-            Item {
-                id: Uuid::new_v4(),
-                kind: "DEF".to_string(),
-            },
+            Some(owner_a.clone()),
+            create_item("DEF"),
             250,
+            None,
+            &mut parties.bank,
         );
-        assert_eq!(market.listings_by_owner_id.len(), 2);
-        assert_eq!(
-            market
-                .listings_by_owner_id
-                .get(&owner_a.borrow().id)
-                .unwrap()
-                .len(),
-            2
-        );
+        // Listings count as the owner's before they go up
+        assert_eq!(market.get_listings_of_owner(owner_a.actor).count(), 2);
+        market.tick();
+        assert_eq!(market.get_listings_of_owner(owner_a.actor).count(), 2);
+        assert_eq!(market.get_listings_of_owner(owner_b.actor).count(), 1);
+        assert_eq!(market.get_listings_of_owner(ActorId::default()).count(), 0);
+        assert_eq!(market.cancel_listings_of(owner_a.actor).len(), 2);
+        assert_eq!(market.get_listed_count("ABC"), 1);
     }
 
     #[test]
-    fn unlist_item() {
-        let mut market = Market::new();
-        market.list_item(
-            None,
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+    fn cancel_listing() {
+        let mut parties = Parties::new();
+        let owner = parties.add("A");
+        let mut market = Market::new("Market");
+        let listing = market.list_item(
+            Some(owner.clone()),
+            create_item("ABC"),
             500,
+            None,
+            &mut parties.bank,
         );
         market.list_item(
-            None,
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+            Some(owner),
+            create_item("ABC"),
             750,
+            None,
+            &mut parties.bank,
         );
-        assert_eq!(market.get_listings_of_kind("ABC").len(), 2);
-        let abcs = market.get_listings_of_kind("ABC");
-        let chosen_listing = abcs
-            .iter()
-            .find(|listing| listing.upgrade().unwrap().price == 500)
-            .unwrap()
-            .clone();
-        market.unlist_item(chosen_listing);
-        assert_eq!(market.get_listings_of_kind("ABC").len(), 1);
+        market.tick();
+        assert_eq!(market.get_listed_count("ABC"), 2);
+        assert!(market.cancel_listing(listing).is_ok());
+        assert_eq!(market.get_listed_count("ABC"), 1);
+        assert_eq!(market.get_best_ask("ABC"), Some(750));
     }
 
     #[test]
     fn buy_listing() {
-        let bank = Bank::new("Bank");
-        let owner_a = Actor::new("A", bank.clone(), None);
-        let owner_b = Actor::new("B", bank.clone(), None);
-        let mut market = Market::new();
-        let _listing_a = market.list_item(
-            Some(owner_a.borrow().id),
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+        let mut parties = Parties::new();
+        let owner_a = parties.add("A");
+        let owner_b = parties.add("B");
+        let mut market = Market::new("Market");
+        market.list_item(
+            Some(owner_a.clone()),
+            create_item("ABC"),
             500,
+            None,
+            &mut parties.bank,
         );
-        let _listing_b = market.list_item(
-            Some(owner_b.borrow().id),
-            Item {
-                id: Uuid::new_v4(),
-                kind: "ABC".to_string(),
-            },
+        market.list_item(
+            Some(owner_b.clone()),
+            create_item("ABC"),
             750,
+            None,
+            &mut parties.bank,
         );
+        let item_c = create_item("DEF");
         let listing_c = market.list_item(
-            Some(owner_a.borrow().id),
-            Item {
-                id: Uuid::new_v4(),
-                kind: "DEF".to_string(),
-            },
+            Some(owner_a.clone()),
+            item_c.clone(),
             250,
+            None,
+            &mut parties.bank,
         );
-        assert_eq!(market.get_listings_of_kind("DEF").len(), 1);
-        assert_eq!(market.get_listings_of_kind("ABC").len(), 2);
+        // Listings can only be bought once they went up
         assert_eq!(
-            market
-                .listings_by_owner_id
-                .get(&owner_a.borrow().id)
-                .unwrap()
-                .len(),
-            2
+            market.buy_listing(listing_c, owner_b.account, &mut parties.bank),
+            Err(MarketError::ListingNotActive(listing_c))
         );
+        market.tick();
+        assert_eq!(market.get_listed_count("DEF"), 1);
+        assert_eq!(market.get_listed_count("ABC"), 2);
+        assert_eq!(market.get_listings_of_owner(owner_a.actor).count(), 2);
+        assert_eq!(market.get_listings_of_owner(owner_b.actor).count(), 1);
+        assert_eq!(parties.bank.get_balance(owner_a.account), 0);
+        assert_eq!(parties.bank.get_balance(owner_b.account), 0);
         assert_eq!(
-            market
-                .listings_by_owner_id
-                .get(&owner_b.borrow().id)
-                .unwrap()
-                .len(),
-            1
+            market.buy_listing(listing_c, owner_b.account, &mut parties.bank),
+            Ok(item_c)
         );
+        assert_eq!(market.get_listed_count("DEF"), 0);
+        assert_eq!(market.get_listings_of_owner(owner_a.actor).count(), 1);
+        assert_eq!(market.get_listings_of_owner(owner_b.actor).count(), 1);
+        assert_eq!(parties.bank.get_balance(owner_a.account), 250);
+        assert_eq!(parties.bank.get_balance(owner_b.account), -250);
+        assert_eq!(market.get_trades()[0].buyer, "B");
+        // A listing can only be bought once
         assert_eq!(
-            owner_a
-                .borrow()
-                .get_account()
-                .upgrade()
-                .unwrap()
-                .borrow()
-                .get_balance(),
-            0
+            market.buy_listing(listing_c, owner_b.account, &mut parties.bank),
+            Err(MarketError::ListingNotFound(listing_c))
         );
+    }
+
+    #[test]
+    fn buy_listing_errors() {
+        let mut parties = Parties::new();
+        let owner = parties.add("A");
+        let mut market = Market::new("Market");
+        let unowned = market.list_item(None, create_item("ABC"), 500, None, &mut parties.bank);
+        let listing = market.list_item(
+            Some(owner.clone()),
+            create_item("ABC"),
+            750,
+            None,
+            &mut parties.bank,
+        );
+        market.tick();
         assert_eq!(
-            owner_b
-                .borrow()
-                .get_account()
-                .upgrade()
-                .unwrap()
-                .borrow()
-                .get_balance(),
-            0
+            market.buy_listing(unowned, owner.account, &mut parties.bank),
+            Err(MarketError::SellerGone(unowned))
         );
-        market.buy_listing(listing_c, owner_b.borrow().get_account());
-        assert_eq!(market.get_listings_of_kind("DEF").len(), 0);
+        // The listing stays up if the buyer can not pay
+        let closed = AccountId::default();
         assert_eq!(
-            market
-                .listings_by_owner_id
-                .get(&owner_a.borrow().id)
-                .unwrap()
-                .len(),
-            1
+            market.buy_listing(listing, closed, &mut parties.bank),
+            Err(MarketError::Payment(BankError::AccountClosed(closed)))
         );
+        assert_eq!(market.get_listed_count("ABC"), 2);
+        assert!(market.get_trades().is_empty());
+    }
+
+    #[test]
+    fn place_bid() {
+        let mut parties = Parties::new();
+        let owner = parties.add("A");
+        let buyer = parties.add("B");
+        let mut market = Market::new("Market");
+        let item = create_item("ABC");
+        market.list_item(Some(owner), item.clone(), 500, None, &mut parties.bank);
+        let unowned = market.list_item(None, create_item("DEF"), 100, None, &mut parties.bank);
+        market.tick();
+        let mut bid = |market: &mut Market, item_kind: &str, limit: i64| {
+            market.place_bid(
+                buyer.actor,
+                buyer.account,
+                item_kind,
+                limit,
+                &mut parties.bank,
+            )
+        };
+        assert!(matches!(
+            bid(&mut market, "ABC", 400),
+            BidResult::Rejected(MarketError::NothingAtLimit { limit: 400, .. })
+        ));
+        assert!(matches!(
+            bid(&mut market, "DEF", 400),
+            BidResult::Rejected(MarketError::SellerGone(id)) if id == unowned
+        ));
+        assert!(matches!(
+            bid(&mut market, "ABC", 500),
+            BidResult::Bought(bought) if bought == item
+        ));
+    }
+
+    #[test]
+    fn expire_listing() {
+        let mut parties = Parties::new();
+        let owner = parties.add("A");
+        let mut market = Market::new("Market");
+        let item = create_item("ABC");
+        market.list_item(
+            Some(owner.clone()),
+            item.clone(),
+            500,
+            Some(2),
+            &mut parties.bank,
+        );
+        market.tick();
+        market.tick();
+        assert_eq!(market.get_listed_count("ABC"), 1);
+        assert!(market.take_deliveries().is_empty());
+        market.tick();
+        assert_eq!(market.get_listed_count("ABC"), 0);
         assert_eq!(
-            market
-                .listings_by_owner_id
-                .get(&owner_b.borrow().id)
-                .unwrap()
-                .len(),
-            1
+            market.take_deliveries(),
+            vec![Delivery::Returned {
+                owner: owner.actor,
+                item,
+            }]
         );
+    }
+
+    #[test]
+    fn cancel_and_reprice_listing() {
+        let mut bank = Bank::new("Bank");
+        let mut market = Market::new("Market");
+        let listing = market.list_item(None, create_item("ABC"), 500, None, &mut bank);
+        // Listings can be repriced before they go up
+        assert!(market.reprice_listing(listing, 400).is_ok());
+        market.tick();
+        assert_eq!(market.get_best_ask("ABC"), Some(400));
+        assert!(market.reprice_listing(listing, 300).is_ok());
+        assert_eq!(market.get_listed_count("ABC"), 1);
+        assert_eq!(market.get_best_ask("ABC"), Some(300));
+        let item = market.cancel_listing(listing).unwrap();
+        assert_eq!(item.kind, "ABC");
+        assert!(market.get_best_ask("ABC").is_none());
+        assert!(market.cancel_listing(listing).is_err());
         assert_eq!(
-            owner_a
-                .borrow()
-                .get_account()
-                .upgrade()
-                .unwrap()
-                .borrow()
-                .get_balance(),
-            250
+            market.reprice_listing(listing, 200),
+            Err(MarketError::ListingNotFound(listing))
         );
+
+        // Cancelled listings never go up
+        let listing = market.list_item(None, create_item("ABC"), 500, None, &mut bank);
+        market.cancel_listing(listing).unwrap();
+        market.tick();
+        assert_eq!(market.get_listed_count("ABC"), 0);
+    }
+
+    #[test]
+    fn expired_listings_return_to_owner() {
+        let mut scenario = Scenario::default();
+        scenario.tasks.get_mut("farmer").unwrap().listing_ttl = Some(5);
+        // One packer can not keep up with four farmers
+        scenario.actors.truncate(5);
+        let world = run(&scenario, 200);
+        let expired = LogQuery {
+            min_severity: Some(Severity::Warning),
+            category: Some(Category::Trade),
+            item_kind: Some("Apple".to_string()),
+            ..LogQuery::default()
+        };
+        assert!(world
+            .get_snapshot()
+            .actors
+            .iter()
+            .flat_map(|actor| world.get_actor_log(&actor.name, &expired).unwrap())
+            .any(|entry| entry.message.contains("expired")));
+
+        // Every apple produced is still around or was used up
+        let graph = world.get_flow_graph();
+        let total = |from: &str, to: &str| get_edge_total(&graph, from, to).unwrap();
+        let inventory = graph
+            .nodes
+            .iter()
+            .find(|node| node.name == "Apple")
+            .map(|node| node.inventory)
+            .unwrap();
         assert_eq!(
-            owner_b
-                .borrow()
-                .get_account()
-                .upgrade()
-                .unwrap()
-                .borrow()
-                .get_balance(),
-            -250
+            total("farmer", "Apple"),
+            total("Apple", "packer") + inventory as u64
         );
     }
 }
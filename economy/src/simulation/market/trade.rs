@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+///
+/// Record of a listing which was bought.
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trade {
+    /// Name of the market the listing was bought in.
+    #[serde(default)]
+    pub market: String,
+    pub item_kind: String,
+    pub price: i64,
+    pub seller: Option<String>,
+    pub buyer: String,
+}
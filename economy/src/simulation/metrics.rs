@@ -0,0 +1,91 @@
+use super::government::{Revenue, Spending};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+///
+/// Outcome measurements of a world at a given tick. Used to compare runs against each other.
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Metrics {
+    pub tick: u64,
+    pub actor_count: usize,
+    pub trade_count: usize,
+    pub trade_volume: u64,
+    pub balance_min: i64,
+    pub balance_max: i64,
+    pub held_by_item_kind: BTreeMap<String, isize>,
+    pub listed_by_item_kind: BTreeMap<String, usize>,
+    /// Taxes the government collected during the tick.
+    #[serde(default)]
+    pub tax_revenue: Revenue,
+    /// Money the government spent during the tick.
+    #[serde(default)]
+    pub government_spending: Spending,
+    /// Taxes the government collected since the start.
+    #[serde(default)]
+    pub total_tax_revenue: Revenue,
+    /// Money the government spent since the start.
+    #[serde(default)]
+    pub total_government_spending: Spending,
+}
+impl Metrics {
+    ///
+    /// Get the CSV column names for metrics. Every given item kind gets a held and a listed column
+    /// so that rows of different metrics line up.
+    ///
+    pub fn get_csv_columns(item_kinds: &BTreeSet<String>) -> Vec<String> {
+        let mut columns = [
+            "ticks",
+            "actors",
+            "trades",
+            "trade_volume",
+            "balance_min",
+            "balance_max",
+            "tax_revenue",
+            "government_spending",
+        ]
+        .map(String::from)
+        .to_vec();
+        for item_kind in item_kinds.iter() {
+            columns.push(format!("held_{}", item_kind));
+            columns.push(format!("listed_{}", item_kind));
+        }
+        columns
+    }
+    ///
+    /// Get the CSV values of the metrics, matching the columns of `get_csv_columns`.
+    ///
+    pub fn get_csv_values(&self, item_kinds: &BTreeSet<String>) -> Vec<String> {
+        let mut values = vec![
+            self.tick.to_string(),
+            self.actor_count.to_string(),
+            self.trade_count.to_string(),
+            self.trade_volume.to_string(),
+            self.balance_min.to_string(),
+            self.balance_max.to_string(),
+            self.total_tax_revenue.get_total().to_string(),
+            self.total_government_spending.get_total().to_string(),
+        ];
+        for item_kind in item_kinds.iter() {
+            let held = self.held_by_item_kind.get(item_kind).copied().unwrap_or(0);
+            let listed = self
+                .listed_by_item_kind
+                .get(item_kind)
+                .copied()
+                .unwrap_or(0);
+            values.push(held.to_string());
+            values.push(listed.to_string());
+        }
+        values
+    }
+    ///
+    /// Get every item kind which is either held or listed.
+    ///
+    pub fn get_item_kinds(&self) -> BTreeSet<String> {
+        self.held_by_item_kind
+            .keys()
+            .chain(self.listed_by_item_kind.keys())
+            .cloned()
+            .collect()
+    }
+}
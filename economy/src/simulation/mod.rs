@@ -1,105 +1,123 @@
 use self::{
-    market::{item::Item, Market},
-    task::Task,
+    control::{Clock, SimulationCommand},
+    market::item::Item,
+    metrics::Metrics,
+    scenario::Scenario,
+    world::World,
 };
-use crate::RenderableState;
-use accounting::bank::Bank;
-use actor::Actor;
-use rand::{random, seq::SliceRandom, thread_rng, Rng};
-use std::{sync::mpsc::Sender, thread, time::Duration};
+use rand::{seq::SliceRandom, thread_rng};
+use std::sync::mpsc::Receiver;
 use uuid::Uuid;
 
 pub mod accounting;
 pub mod actions;
 pub mod actor;
+///
+/// Entry points for the benchmarks, which cannot reach the internals themselves.
+///
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod book;
+pub mod control;
+///
+/// Worlds and scenarios shared by the tests of several modules.
+///
+#[cfg(test)]
+pub mod fixture;
+pub mod flow;
+pub mod geography;
+pub mod government;
+pub mod intervention;
+pub mod invariant;
 pub mod item_count_list;
 pub mod logbook;
 pub mod market;
+pub mod metrics;
+pub mod prices;
 pub mod recipe;
+pub mod scenario;
+pub mod snapshot;
 pub mod store;
 pub mod task;
+pub mod world;
 
 ///
-/// Runs in own thread. Responsible for simulation.
+/// How fast and for how long a simulation runs.
 ///
-pub fn simulate(tx: Sender<RenderableState>) {
-    let mut rng = thread_rng();
-
-    // Setup Simulation
-    let mut market = Market::new();
-    let bank = Bank::new("Federal Reserve");
-    let task_farmer = Task {
-        inputs: vec![],
-        outputs: vec![("Apple".to_string(), 1)],
-        work_points: 10,
-    };
-    let task_packer = Task {
-        inputs: vec![("Apple".to_string(), 3)],
-        outputs: vec![("FoodBox".to_string(), 1)],
-        work_points: 14,
-    };
-    let mut actors = vec![
-        Actor::new("Actor_1_farmer", bank.clone(), Some(task_farmer.clone())),
-        Actor::new("Actor_2_farmer", bank.clone(), Some(task_farmer.clone())),
-        Actor::new("Actor_3_farmer", bank.clone(), Some(task_farmer.clone())),
-        Actor::new("Actor_4_farmer", bank.clone(), Some(task_farmer.clone())),
-        Actor::new("Actor_5_packer", bank.clone(), Some(task_packer.clone())),
-        Actor::new("Actor_6_packer", bank.clone(), Some(task_packer.clone())),
-    ];
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimulationSettings {
+    /// Stop after this many ticks, or run forever.
+    pub ticks: Option<u64>,
+    /// Ticks per second, or as fast as possible.
+    pub tick_rate: Option<f64>,
+}
 
-    // TODO: Render all actors and see if you can make them sell apples and buy apples and sell
-    // foodboxes
+///
+/// Runs in own thread. Responsible for simulation.
+///
+/// Ticks the world according to the settings and any commands arriving on the control channel,
+/// and calls `on_tick` after every tick. Interventions are applied between ticks and answered on
+/// their reply channel. `on_update` is called whenever the world may have changed, after every
+/// tick and after every command handled between ticks, also while paused. The simulation stops
+/// early if either returns false, for example when the renderer has gone away.
+///
+pub fn simulate(
+    world: &mut World,
+    settings: &SimulationSettings,
+    control: Option<Receiver<SimulationCommand>>,
+    mut on_tick: impl FnMut(&mut World) -> bool,
+    mut on_update: impl FnMut(&World) -> bool,
+) {
+    let mut clock = Clock::new(settings.tick_rate, control);
 
     // Run Simulation
     loop {
-        // Tick Market
-        market.tick();
-
-        // Tick each actor
-        for actor in actors.iter_mut() {
-            actor.borrow_mut().tick(&mut market);
+        if settings
+            .ticks
+            .is_some_and(|ticks| world.get_tick() >= ticks)
+        {
+            break;
         }
 
-        // Build renderable state
-        // TODO: Render these items and their counts - This will show what the market is like -
-        // Perhaps also render their average price?
-        let listed_item_kinds = market
-            .get_listed_item_kinds()
-            .map(|item_kind| {
-                (
-                    item_kind.clone(),
-                    market.get_listings_of_kind(item_kind).len(),
-                )
-            })
-            .collect::<Vec<_>>();
-
-        let actor_info = actors
-            .iter()
-            .map(|actor| {
-                (
-                    actor.borrow().id,
-                    actor.borrow().get_name(),
-                    actor.borrow().get_log(),
-                    actor.borrow().get_store_actual(),
-                )
-            })
-            .collect::<Vec<_>>();
-
-        let renderable_state = RenderableState {
-            actor_count: actors.len(),
-            listed_item_kinds,
-            actor_info,
-        };
+        // Wait for next tick
+        let mut is_stopped = false;
+        clock.wait_for_tick(world.get_tick(), |command| {
+            match command {
+                SimulationCommand::Intervention(request) => {
+                    let result = world.apply_intervention(&request.intervention);
+                    request.reply(result);
+                }
+                SimulationCommand::Inspect(actor_id) => world.set_inspected_actor(actor_id),
+                SimulationCommand::Control(_) => return,
+            }
+            is_stopped |= !on_update(world);
+        });
+        if is_stopped {
+            break;
+        }
 
-        // Send renderable state through channel to be rendered
-        tx.send(renderable_state).unwrap();
+        world.tick();
 
-        // Wait for next tick
-        thread::sleep(Duration::from_millis(1000));
+        if !on_tick(world) || !on_update(world) {
+            break;
+        }
     }
 }
 
+///
+/// Runs a world built from the scenario for the given number of ticks as fast as possible, without
+/// rendering, and returns the metrics of the final tick.
+///
+pub fn run_headless(scenario: &Scenario, ticks: u64) -> Metrics {
+    let mut world = World::new(scenario);
+    let settings = SimulationSettings {
+        ticks: Some(ticks),
+        tick_rate: None,
+    };
+    simulate(&mut world, &settings, None, |_| true, |_| true);
+    world.get_metrics()
+}
+
 // Helpers
 fn create_random_item() -> Item {
     let kinds = vec!["Apple", "Orange", "Banana"];
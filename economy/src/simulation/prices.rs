@@ -0,0 +1,114 @@
+use super::market::trade::Trade;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+///
+/// Prices of an item kind over a single tick.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub tick: u64,
+    /// Price of the last trade in the tick.
+    pub last_trade: Option<i64>,
+    /// Lowest listed price at the end of the tick.
+    pub best_ask: Option<i64>,
+    /// Highest limit of the open bids at the end of the tick.
+    #[serde(default)]
+    pub best_bid: Option<i64>,
+    pub trade_count: u64,
+    /// Sum of the prices of all trades in the tick.
+    pub turnover: i64,
+}
+impl PricePoint {
+    ///
+    /// Volume weighted average price of the trades in the tick. Every trade is for one item.
+    ///
+    pub fn get_vwap(&self) -> Option<f64> {
+        if self.trade_count == 0 {
+            return None;
+        }
+        Some(self.turnover as f64 / self.trade_count as f64)
+    }
+}
+
+///
+/// Builds the price point of every item kind which was traded, listed or bid on in a tick, given
+/// the trades of the tick and the price of every listing and the limit of every bid at the end of
+/// it.
+///
+pub fn get_price_points<'a>(
+    tick: u64,
+    trades: &[Trade],
+    listings: impl Iterator<Item = (&'a str, i64)>,
+    bids: impl Iterator<Item = (&'a str, i64)>,
+) -> BTreeMap<String, PricePoint> {
+    let mut points = BTreeMap::<String, PricePoint>::new();
+    for trade in trades.iter() {
+        let point = points
+            .entry(trade.item_kind.clone())
+            .or_insert_with(|| PricePoint {
+                tick,
+                ..Default::default()
+            });
+        point.last_trade = Some(trade.price);
+        point.trade_count += 1;
+        point.turnover += trade.price;
+    }
+    for (item_kind, price) in listings {
+        let point = points
+            .entry(item_kind.to_string())
+            .or_insert_with(|| PricePoint {
+                tick,
+                ..Default::default()
+            });
+        point.best_ask = Some(point.best_ask.map_or(price, |best_ask| best_ask.min(price)));
+    }
+    for (item_kind, limit) in bids {
+        let point = points
+            .entry(item_kind.to_string())
+            .or_insert_with(|| PricePoint {
+                tick,
+                ..Default::default()
+            });
+        point.best_bid = Some(point.best_bid.map_or(limit, |best_bid| best_bid.max(limit)));
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(item_kind: &str, price: i64) -> Trade {
+        Trade {
+            market: "Market".to_string(),
+            item_kind: item_kind.to_string(),
+            price,
+            seller: None,
+            buyer: "Buyer".to_string(),
+        }
+    }
+
+    #[test]
+    fn price_points() {
+        let trades = vec![trade("Apple", 10), trade("Apple", 20), trade("Apple", 15)];
+        let listings = vec![("Apple", 30), ("Apple", 25), ("FoodBox", 100)];
+        let bids = vec![("Apple", 12), ("Apple", 18), ("Orange", 5)];
+        let points = get_price_points(7, &trades, listings.into_iter(), bids.into_iter());
+        assert_eq!(points.len(), 3);
+        let apple = points["Apple"];
+        assert_eq!(apple.tick, 7);
+        assert_eq!(apple.last_trade, Some(15));
+        assert_eq!(apple.best_ask, Some(25));
+        assert_eq!(apple.best_bid, Some(18));
+        assert_eq!(apple.get_vwap(), Some(15.0));
+        let food_box = points["FoodBox"];
+        assert_eq!(food_box.last_trade, None);
+        assert_eq!(food_box.best_ask, Some(100));
+        assert_eq!(food_box.best_bid, None);
+        assert_eq!(food_box.get_vwap(), None);
+        let orange = points["Orange"];
+        assert_eq!(orange.best_ask, None);
+        assert_eq!(orange.best_bid, Some(5));
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
 pub struct Recipe {
     from: String,
     to: String,
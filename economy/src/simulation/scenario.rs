@@ -0,0 +1,455 @@
+use super::{
+    geography::{Route, DEFAULT_MARKET},
+    government::Policy,
+    item_count_list::ItemCountList,
+    logbook::DEFAULT_RETENTION,
+    market::mechanism::Mechanism,
+    task::Task,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
+
+///
+/// Describes the initial setup of a simulation. A scenario is plain data and can be loaded from a
+/// TOML file, cloned and tweaked before a world is built from it.
+///
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub seed: u64,
+    pub bank: String,
+    pub tasks: BTreeMap<String, TaskDefinition>,
+    pub actors: Vec<ActorDefinition>,
+    /// Names of the markets, actors trade in the first one unless located elsewhere. Scenarios
+    /// without any have a single market.
+    #[serde(default)]
+    pub markets: Vec<String>,
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    /// How each item kind is traded. Item kinds not named here trade continuously.
+    #[serde(default)]
+    pub mechanisms: BTreeMap<String, Mechanism>,
+    #[serde(default)]
+    pub government: Policy,
+    /// Number of entries kept in the log of every actor.
+    #[serde(default = "default_log_retention")]
+    pub log_retention: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TaskDefinition {
+    #[serde(default)]
+    pub inputs: BTreeMap<String, isize>,
+    #[serde(default)]
+    pub outputs: BTreeMap<String, isize>,
+    pub work_points: u64,
+    #[serde(default = "default_safety_factor")]
+    pub safety_factor: f32,
+    #[serde(default = "default_max_price")]
+    pub max_price: i64,
+    #[serde(default)]
+    pub listing_ttl: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ActorDefinition {
+    pub name: String,
+    pub task: Option<String>,
+    #[serde(default = "default_count")]
+    pub count: usize,
+    #[serde(default)]
+    pub balance: u64,
+    /// Market the actor starts out in.
+    pub location: Option<String>,
+    /// Item kind the actor carries between markets, instead of working a task.
+    pub trade: Option<String>,
+}
+impl ActorDefinition {
+    ///
+    /// Get the names of the actors the definition expands into, numbered from 1 if there are
+    /// several.
+    ///
+    pub fn get_names(&self) -> Vec<String> {
+        if self.count == 1 {
+            return vec![self.name.clone()];
+        }
+        (1..=self.count)
+            .map(|i| format!("{}_{}", self.name, i))
+            .collect()
+    }
+}
+
+fn default_safety_factor() -> f32 {
+    2.5
+}
+
+fn default_max_price() -> i64 {
+    500
+}
+
+fn default_count() -> usize {
+    1
+}
+
+fn default_log_retention() -> usize {
+    DEFAULT_RETENTION
+}
+
+impl Scenario {
+    ///
+    /// Reads and validates a scenario from a TOML file.
+    ///
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+        Self::parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+    ///
+    /// Parses and validates a scenario from TOML.
+    ///
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let scenario: Scenario = toml::from_str(contents)
+            .map_err(|error| format!("Could not parse scenario: {}", error))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+    ///
+    /// Checks that actors are uniquely named and refer to tasks and markets which exist in the
+    /// scenario, that tasks do not use negative item counts, that routes connect two markets and
+    /// that tax rates are fractions.
+    ///
+    pub fn validate(&self) -> Result<(), String> {
+        for (tax, rate) in [
+            ("sales_tax", self.government.sales_tax),
+            ("income_tax", self.government.income_tax),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!(
+                    "Government {} {} is not between 0 and 1",
+                    tax, rate
+                ));
+            }
+        }
+        let markets = self.get_market_names();
+        let mut market_names = HashSet::new();
+        for market in markets.iter() {
+            if !market_names.insert(market.as_str()) {
+                return Err(format!("Market '{}' is defined more than once", market));
+            }
+        }
+        for route in self.routes.iter() {
+            for market in [&route.from, &route.to] {
+                if !market_names.contains(market.as_str()) {
+                    return Err(format!("Route refers to unknown market '{}'", market));
+                }
+            }
+            if route.from == route.to || route.ticks == 0 {
+                return Err(format!(
+                    "Route from '{}' to '{}' does not go anywhere",
+                    route.from, route.to
+                ));
+            }
+        }
+        for (task_name, task) in self.tasks.iter() {
+            if !(task.safety_factor >= 0.0 && task.safety_factor.is_finite()) {
+                return Err(format!(
+                    "Task '{}' safety factor {} is not a positive number",
+                    task_name, task.safety_factor
+                ));
+            }
+            let items = task.inputs.iter().chain(task.outputs.iter());
+            for (item_kind, count) in items {
+                if *count < 0 {
+                    return Err(format!(
+                        "Task '{}' uses a negative count of '{}'",
+                        task_name, item_kind
+                    ));
+                }
+            }
+        }
+        let mut actor_names = HashSet::new();
+        for actor in self.actors.iter() {
+            for name in actor.get_names() {
+                if !actor_names.insert(name.clone()) {
+                    return Err(format!("Actor '{}' is defined more than once", name));
+                }
+            }
+            if let Some(task) = actor.task.as_ref() {
+                if !self.tasks.contains_key(task) {
+                    return Err(format!(
+                        "Actor '{}' refers to unknown task '{}'",
+                        actor.name, task
+                    ));
+                }
+                if actor.trade.is_some() {
+                    return Err(format!(
+                        "Actor '{}' can not both work a task and trade",
+                        actor.name
+                    ));
+                }
+            }
+            if let Some(location) = actor.location.as_ref() {
+                if !market_names.contains(location.as_str()) {
+                    return Err(format!(
+                        "Actor '{}' refers to unknown market '{}'",
+                        actor.name, location
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn get_market_names(&self) -> Vec<String> {
+        if self.markets.is_empty() {
+            return vec![DEFAULT_MARKET.to_string()];
+        }
+        self.markets.clone()
+    }
+    ///
+    /// Builds the runtime task for the given task name, if it exists.
+    ///
+    pub(super) fn get_task(&self, name: &str) -> Option<Task> {
+        self.tasks.get(name).map(|definition| Task {
+            name: name.to_string(),
+            inputs: to_item_count_list(&definition.inputs),
+            outputs: to_item_count_list(&definition.outputs),
+            work_points: definition.work_points,
+            safety_factor: definition.safety_factor,
+            max_price: definition.max_price,
+            listing_ttl: definition.listing_ttl,
+        })
+    }
+    ///
+    /// Overrides a single named parameter of the scenario. Supported names are
+    ///
+    /// - `seed`
+    /// - `tasks.<task>.work_points`
+    /// - `tasks.<task>.safety_factor`
+    /// - `tasks.<task>.max_price`
+    /// - `tasks.<task>.listing_ttl`
+    /// - `actors.<actor>.balance`
+    /// - `government.sales_tax`
+    /// - `government.income_tax`
+    /// - `government.listing_fee`
+    /// - `government.unemployment_benefit`
+    ///
+    /// Integer parameters are rounded to the nearest whole number.
+    ///
+    pub fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), String> {
+        let parts = name.split('.').collect::<Vec<_>>();
+        match parts.as_slice() {
+            ["seed"] => self.seed = to_unsigned(name, value)?,
+            ["tasks", task, field] => {
+                let task = self
+                    .tasks
+                    .get_mut(*task)
+                    .ok_or_else(|| format!("Unknown task in parameter '{}'", name))?;
+                match *field {
+                    "work_points" => task.work_points = to_unsigned(name, value)?,
+                    "safety_factor" => task.safety_factor = to_non_negative(name, value)? as f32,
+                    "max_price" => task.max_price = to_unsigned(name, value)? as i64,
+                    "listing_ttl" => task.listing_ttl = Some(to_unsigned(name, value)?),
+                    _ => return Err(format!("Unknown task field in parameter '{}'", name)),
+                }
+            }
+            ["actors", actor, "balance"] => {
+                let actor = self
+                    .actors
+                    .iter_mut()
+                    .find(|definition| definition.name == *actor)
+                    .ok_or_else(|| format!("Unknown actor in parameter '{}'", name))?;
+                actor.balance = to_unsigned(name, value)?;
+            }
+            ["government", field] => {
+                let government = &mut self.government;
+                match *field {
+                    "sales_tax" => government.sales_tax = value,
+                    "income_tax" => government.income_tax = value,
+                    "listing_fee" => government.listing_fee = to_unsigned(name, value)?,
+                    "unemployment_benefit" => {
+                        government.unemployment_benefit = to_unsigned(name, value)?
+                    }
+                    _ => return Err(format!("Unknown government field in parameter '{}'", name)),
+                }
+            }
+            _ => return Err(format!("Unknown parameter '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+impl Default for Scenario {
+    ///
+    /// The farmer and packer economy the simulation was originally hard-coded with.
+    ///
+    fn default() -> Self {
+        let mut tasks = BTreeMap::new();
+        tasks.insert(
+            "farmer".to_string(),
+            TaskDefinition {
+                inputs: BTreeMap::new(),
+                outputs: BTreeMap::from([("Apple".to_string(), 1)]),
+                work_points: 10,
+                safety_factor: default_safety_factor(),
+                max_price: default_max_price(),
+                listing_ttl: None,
+            },
+        );
+        tasks.insert(
+            "packer".to_string(),
+            TaskDefinition {
+                inputs: BTreeMap::from([("Apple".to_string(), 3)]),
+                outputs: BTreeMap::from([("FoodBox".to_string(), 1)]),
+                work_points: 14,
+                safety_factor: default_safety_factor(),
+                max_price: default_max_price(),
+                listing_ttl: None,
+            },
+        );
+        let actors = (1..=6)
+            .map(|i| {
+                let task = if i <= 4 { "farmer" } else { "packer" };
+                ActorDefinition {
+                    name: format!("Actor_{}_{}", i, task),
+                    task: Some(task.to_string()),
+                    count: 1,
+                    balance: 0,
+                    location: None,
+                    trade: None,
+                }
+            })
+            .collect();
+        Self {
+            name: "default".to_string(),
+            seed: 0,
+            bank: "Federal Reserve".to_string(),
+            tasks,
+            actors,
+            markets: Vec::new(),
+            routes: Vec::new(),
+            mechanisms: BTreeMap::new(),
+            government: Policy::default(),
+            log_retention: DEFAULT_RETENTION,
+        }
+    }
+}
+
+fn to_item_count_list(items: &BTreeMap<String, isize>) -> ItemCountList {
+    items
+        .iter()
+        .map(|(kind, count)| (kind.clone(), *count))
+        .collect()
+}
+
+fn to_non_negative(name: &str, value: f64) -> Result<f64, String> {
+    if value < 0.0 || !value.is_finite() {
+        return Err(format!("Parameter '{}' must be a positive number", name));
+    }
+    Ok(value)
+}
+
+fn to_unsigned(name: &str, value: f64) -> Result<u64, String> {
+    Ok(to_non_negative(name, value)?.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let scenario = Scenario::parse(
+            r#"
+            name = "test"
+            bank = "Bank"
+
+            [tasks.farmer]
+            outputs = { Apple = 1 }
+            work_points = 10
+
+            [[actors]]
+            name = "Farmer"
+            task = "farmer"
+            count = 3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(scenario.actors[0].count, 3);
+        let task = scenario.get_task("farmer").unwrap();
+        assert_eq!(task.outputs, vec![("Apple".to_string(), 1)]);
+        assert_eq!(task.safety_factor, 2.5);
+    }
+
+    #[test]
+    fn validate() {
+        assert!(Scenario::default().validate().is_ok());
+
+        let mut scenario = Scenario::default();
+        scenario.actors[0].task = Some("miner".to_string());
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = Scenario::default();
+        scenario.actors[1].name = scenario.actors[0].name.clone();
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = Scenario::default();
+        scenario
+            .tasks
+            .get_mut("packer")
+            .unwrap()
+            .inputs
+            .insert("Apple".to_string(), -3);
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = Scenario::default();
+        scenario.government.sales_tax = 1.5;
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = Scenario::default();
+        scenario.tasks.get_mut("packer").unwrap().safety_factor = -1.0;
+        assert!(scenario.validate().is_err());
+
+        // Actors numbered from a count clash with those named alike
+        let mut scenario = Scenario::default();
+        scenario.actors[0].name = "Farmer".to_string();
+        scenario.actors[0].count = 2;
+        scenario.actors[1].name = "Farmer_2".to_string();
+        assert_eq!(
+            scenario.validate(),
+            Err("Actor 'Farmer_2' is defined more than once".to_string())
+        );
+    }
+
+    #[test]
+    fn set_parameter() {
+        let mut scenario = Scenario::default();
+        scenario
+            .set_parameter("tasks.packer.work_points", 19.6)
+            .unwrap();
+        scenario
+            .set_parameter("tasks.packer.safety_factor", 1.5)
+            .unwrap();
+        scenario
+            .set_parameter("actors.Actor_1_farmer.balance", 1000.0)
+            .unwrap();
+        scenario.set_parameter("seed", 7.0).unwrap();
+        assert_eq!(scenario.tasks["packer"].work_points, 20);
+        assert_eq!(scenario.tasks["packer"].safety_factor, 1.5);
+        assert_eq!(scenario.actors[0].balance, 1000);
+        assert_eq!(scenario.seed, 7);
+        assert!(scenario
+            .set_parameter("tasks.miner.work_points", 1.0)
+            .is_err());
+        assert!(scenario.set_parameter("gravity", 1.0).is_err());
+        assert!(scenario.set_parameter("seed", -1.0).is_err());
+        assert!(scenario
+            .set_parameter("tasks.packer.safety_factor", -0.5)
+            .is_err());
+        assert_eq!(scenario.tasks["packer"].safety_factor, 1.5);
+    }
+}
@@ -0,0 +1,182 @@
+use super::{
+    market::trade::Trade,
+    metrics::Metrics,
+    prices::{get_price_points, PricePoint},
+    store::Store,
+};
+use crate::RenderableState;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{BufRead, Write},
+};
+use uuid::Uuid;
+
+///
+/// Serializable picture of a world after a tick. A sequence of snapshots, one per line of JSON,
+/// makes up an event log which can be replayed, inspected and exported without re-running the
+/// simulation.
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub scenario: String,
+    pub seed: u64,
+    pub tick: u64,
+    #[serde(default)]
+    pub interest_rate: f64,
+    pub metrics: Metrics,
+    pub actors: Vec<ActorSnapshot>,
+    pub accounts: Vec<AccountSnapshot>,
+    pub listings: Vec<ListingSnapshot>,
+    /// Bids which had not been filled yet.
+    #[serde(default)]
+    pub bids: Vec<BidSnapshot>,
+    pub trades: Vec<Trade>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActorSnapshot {
+    pub id: Uuid,
+    pub name: String,
+    /// Market the actor is in, or last left.
+    #[serde(default)]
+    pub location: String,
+    pub balance: i64,
+    pub action: String,
+    pub log: Vec<String>,
+    pub store_actual: BTreeMap<String, isize>,
+    pub store_target: BTreeMap<String, isize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub name: String,
+    pub bank: String,
+    pub balance: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListingSnapshot {
+    pub id: Uuid,
+    #[serde(default)]
+    pub market: String,
+    pub item_kind: String,
+    pub price: i64,
+    pub owner: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BidSnapshot {
+    pub market: String,
+    pub item_kind: String,
+    pub limit: i64,
+    pub buyer: String,
+}
+
+impl Snapshot {
+    ///
+    /// Builds the state the renderer would have received at the tick of the snapshot.
+    ///
+    pub fn get_renderable_state(&self) -> RenderableState {
+        let listed_item_kinds = self
+            .metrics
+            .listed_by_item_kind
+            .iter()
+            .map(|(item_kind, count)| (item_kind.clone(), *count))
+            .collect();
+        let actor_info = self
+            .actors
+            .iter()
+            .map(|actor| {
+                let mut store = Store::new();
+                for (item_kind, count) in actor.store_actual.iter() {
+                    store.set(item_kind, *count);
+                }
+                (actor.id, actor.name.clone(), actor.log.clone(), store)
+            })
+            .collect();
+        let price_history = self
+            .get_price_points()
+            .into_iter()
+            .map(|(item_kind, point)| (item_kind, VecDeque::from([point])))
+            .collect();
+        RenderableState {
+            version: 0,
+            tick: self.tick,
+            actor_count: self.actors.len(),
+            task_names: Vec::new(),
+            listed_item_kinds,
+            actor_info,
+            actor_detail: None,
+            flow_graph: Default::default(),
+            price_history,
+        }
+    }
+    pub fn get_price_points(&self) -> BTreeMap<String, PricePoint> {
+        let listings = self
+            .listings
+            .iter()
+            .map(|listing| (listing.item_kind.as_str(), listing.price));
+        let bids = self
+            .bids
+            .iter()
+            .map(|bid| (bid.item_kind.as_str(), bid.limit));
+        get_price_points(self.tick, &self.trades, listings, bids)
+    }
+}
+
+///
+/// Writes a snapshot as a single line of JSON.
+///
+pub fn write_snapshot(writer: &mut impl Write, snapshot: &Snapshot) -> Result<(), String> {
+    serde_json::to_writer(&mut *writer, snapshot)
+        .map_err(|error| format!("Could not write snapshot: {}", error))?;
+    writeln!(writer).map_err(|error| format!("Could not write snapshot: {}", error))
+}
+
+///
+/// Reads every snapshot from an event log, one snapshot per line. Blank lines are skipped.
+///
+pub fn read_snapshots(reader: impl BufRead) -> Result<Vec<Snapshot>, String> {
+    let mut snapshots = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| format!("Could not read line {}: {}", index + 1, error))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snapshot = serde_json::from_str(&line)
+            .map_err(|error| format!("Invalid snapshot on line {}: {}", index + 1, error))?;
+        snapshots.push(snapshot);
+    }
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{scenario::Scenario, world::World};
+
+    #[test]
+    fn write_and_read() {
+        let mut world = World::new(&Scenario::default());
+        let mut log = Vec::new();
+        for _ in 0..30 {
+            world.tick();
+            write_snapshot(&mut log, &world.get_snapshot()).unwrap();
+        }
+        let snapshots = read_snapshots(log.as_slice()).unwrap();
+        assert_eq!(snapshots.len(), 30);
+        assert_eq!(snapshots[29].tick, 30);
+        assert_eq!(snapshots[29].actors.len(), 6);
+        assert_eq!(
+            snapshots[29].get_renderable_state().actor_count,
+            world.get_renderable_state().actor_count
+        );
+    }
+
+    #[test]
+    fn read_invalid() {
+        let log = "{\"tick\": 1}\n";
+        assert!(read_snapshots(log.as_bytes()).is_err());
+    }
+}
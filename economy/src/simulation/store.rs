@@ -1,13 +1,21 @@
-use std::collections::HashMap;
+use super::market::item::Item;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct Store {
     items: HashMap<String, isize>,
+    /// Identities of held items by kind which have been handed over before, at most as many as
+    /// are held. Items made in the store get an identity the first time they leave it.
+    ids: HashMap<String, Vec<Uuid>>,
+    revision: u64,
 }
 impl Store {
-    pub(super) fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             items: HashMap::new(),
+            ids: HashMap::new(),
+            revision: 0,
         }
     }
     ///
@@ -39,9 +47,14 @@ impl Store {
         *self.items.get(item).unwrap()
     }
     ///
-    /// Adds the specific count of item to the store.
+    /// Adds the specific count of item to the store. Counts of zero or less add nothing, items
+    /// only leave the store by being taken.
     ///
     pub(super) fn add(&mut self, item: &str, count: isize) {
+        if count <= 0 {
+            return;
+        }
+        self.revision += 1;
         if !self.has(item) {
             self.items.insert(item.to_string(), count);
             return;
@@ -52,34 +65,86 @@ impl Store {
     /// Sets the specific count of item to the store.
     ///
     pub(super) fn set(&mut self, item: &str, count: isize) {
+        self.revision += 1;
         self.items.insert(item.to_string(), count);
+        self.forget_ids(item);
+    }
+    ///
+    /// Adds an item which was handed over, keeping its identity.
+    ///
+    pub(super) fn add_item(&mut self, item: Item) {
+        self.add(&item.kind, 1);
+        self.ids.entry(item.kind).or_default().push(item.id);
     }
     ///
-    /// Takes items from the store. Returns the number of items which was taken.
+    /// Takes items from the store. Returns the number of items which was taken, which is never
+    /// negative, so taking can not make goods.
     ///
     pub(super) fn take(&mut self, item: &str, count: isize) -> isize {
         let store_count = self.count(item);
 
-        // Check if store does not have any of the item.
-        if store_count == 0 {
+        // Check if store does not have any of the item, or nothing is to be taken.
+        if store_count <= 0 || count <= 0 {
             return 0;
         }
 
-        // Take entire count if possible
-        if store_count >= count {
-            *self.items.get_mut(item).unwrap() -= count;
-            return count;
-        }
+        self.revision += 1;
 
-        // Else take only what store has to offer
-        *self.items.get_mut(item).unwrap() -= store_count;
-        store_count
+        // Take entire count if possible, else take only what store has to offer
+        let taken = count.min(store_count);
+        *self.items.get_mut(item).unwrap() -= taken;
+        self.forget_ids(item);
+        taken
+    }
+    ///
+    /// Takes up to the count of items out of the store to hand over, for example to a market
+    /// holding them in escrow. Returns the items which were taken, with the identities they were
+    /// handed over with before. Nothing is taken for a count of zero or less.
+    ///
+    pub(super) fn take_items(&mut self, item: &str, count: isize) -> Vec<Item> {
+        if count <= 0 {
+            return Vec::new();
+        }
+        let mut ids = self.ids.remove(item).unwrap_or_default();
+        let taken = self.take(item, count).max(0);
+        let known = ids.split_off(ids.len().saturating_sub(taken as usize));
+        if !ids.is_empty() {
+            self.ids.insert(item.to_string(), ids);
+        }
+        let minted = (known.len()..taken as usize).map(|_| Uuid::new_v4());
+        known
+            .into_iter()
+            .chain(minted)
+            .map(|id| Item {
+                id,
+                kind: item.to_string(),
+            })
+            .collect()
+    }
+    ///
+    /// Drops identities of the item kind beyond the number of items held, after items were used
+    /// up or the count was set.
+    ///
+    fn forget_ids(&mut self, item: &str) {
+        let count = self.count(item).max(0) as usize;
+        if let Some(ids) = self.ids.get_mut(item) {
+            ids.truncate(count);
+        }
     }
     ///
     /// Clear all items from the store.
     ///
     pub(super) fn clear(&mut self) {
+        self.revision += 1;
         self.items.clear();
+        self.ids.clear();
+    }
+    ///
+    /// Number of changes made to the store so far. Used to tell whether the store changed without
+    /// comparing its contents.
+    ///
+    pub(crate) fn get_revision(&self) -> u64 {
+        self.revision
     }
     ///
     /// Get a list of all item kinds in the store.
@@ -90,6 +155,15 @@ impl Store {
             .filter(|key| self.count(key) != 0)
             .collect()
     }
+    ///
+    /// Get the count of every item kind in the store, ordered by item kind.
+    ///
+    pub(crate) fn get_counts(&self) -> BTreeMap<String, isize> {
+        self.get_item_kinds()
+            .into_iter()
+            .map(|item_kind| (item_kind.clone(), self.count(item_kind)))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -184,6 +258,58 @@ mod tests {
         assert!(!store.has("Grape"));
     }
 
+    #[test]
+    fn negative_counts() {
+        let mut store = Store::new();
+        store.add("Apple", 3);
+        let revision = store.get_revision();
+
+        // Negative counts neither make nor destroy goods
+        assert_eq!(store.take("Apple", -2), 0);
+        store.add("Apple", -2);
+        assert_eq!(store.count("Apple"), 3);
+        assert_eq!(store.get_revision(), revision);
+
+        // Nor does taking from a store which is short
+        store.set("Orange", -2);
+        assert_eq!(store.take("Orange", 1), 0);
+        assert_eq!(store.count("Orange"), -2);
+    }
+
+    #[test]
+    fn take_items() {
+        let mut store = Store::new();
+        store.add("Apple", 2);
+        let items = store.take_items("Apple", 3);
+        assert_eq!(items.len(), 2);
+        assert_eq!(store.count("Apple"), 0);
+
+        // Items handed back keep their identity when handed over again
+        for item in items.iter().cloned() {
+            store.add_item(item);
+        }
+        store.add("Apple", 1);
+        assert_eq!(store.count("Apple"), 3);
+        let again = store.take_items("Apple", 2);
+        assert!(again.iter().all(|item| items.contains(item)));
+        assert_ne!(again[0], again[1]);
+        let made = store.take_items("Apple", 1);
+        assert!(!items.contains(&made[0]));
+
+        // Nothing is taken, or made, for counts of zero or less
+        store.add("Apple", 1);
+        assert!(store.take_items("Apple", 0).is_empty());
+        assert!(store.take_items("Apple", -3).is_empty());
+        assert_eq!(store.count("Apple"), 1);
+        store.take("Apple", 1);
+
+        // Used up items take their identities with them
+        store.add_item(items[0].clone());
+        store.take("Apple", 1);
+        store.add("Apple", 1);
+        assert_ne!(store.take_items("Apple", 1)[0], items[0]);
+    }
+
     #[test]
     fn clear() {
         let mut store = Store::new();
@@ -197,6 +323,18 @@ mod tests {
         assert!(!store.has("Orange"));
     }
 
+    #[test]
+    fn revision() {
+        let mut store = Store::new();
+        store.add("Apple", 3);
+        let revision = store.get_revision();
+        assert!(revision > 0);
+        store.take("Orange", 1);
+        assert_eq!(store.get_revision(), revision);
+        store.take("Apple", 1);
+        assert!(store.get_revision() > revision);
+    }
+
     #[test]
     fn get_item_kinds() {
         let mut store = Store::new();
@@ -2,7 +2,13 @@ use super::item_count_list::ItemCountList;
 
 #[derive(Clone)]
 pub struct Task {
+    pub name: String,
     pub inputs: ItemCountList,
     pub outputs: ItemCountList,
     pub work_points: u64,
+    pub safety_factor: f32,
+    /// Most paid for one item of an input.
+    pub max_price: i64,
+    /// Ticks outputs stay listed before they are taken back, or forever.
+    pub listing_ttl: Option<u64>,
 }
@@ -0,0 +1,940 @@
+use super::{
+    accounting::{account::AccountId, bank::Bank},
+    actor::{Actor, ActorId},
+    flow::{FlowEdge, FlowGraph, FlowNode, FlowNodeKind, TaskFlow},
+    geography::Geography,
+    government::Government,
+    intervention::{Intervention, InterventionResult},
+    invariant::Violation,
+    logbook::{write_log_entry, LogEntry, LogQuery},
+    market::{delivery::Delivery, trade::Trade, Market},
+    metrics::Metrics,
+    prices::{get_price_points, PricePoint},
+    scenario::Scenario,
+    snapshot::{AccountSnapshot, ActorSnapshot, BidSnapshot, ListingSnapshot, Snapshot},
+    task::Task,
+};
+use crate::{
+    renderable::{ActorDetail, ActorInfo, PublishedRevision, RenderableDiff},
+    RenderableState,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::prelude::*;
+use slotmap::{SecondaryMap, SlotMap};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    io::Write,
+};
+use tracing::{debug_span, info_span};
+use uuid::Uuid;
+
+///
+/// Everything that makes up a running simulation. A world is built from a scenario and advanced
+/// one tick at a time, independent of any thread or renderer.
+///
+#[derive(Clone)]
+pub struct World {
+    scenario_name: String,
+    seed: u64,
+    tick: u64,
+    rng: StdRng,
+    markets: BTreeMap<String, Market>,
+    /// Market actors start in unless told otherwise, the first one the scenario declares.
+    default_market: String,
+    geography: Geography,
+    /// Receives the transport costs paid by travelling actors.
+    carrier_account: AccountId,
+    government: Government,
+    bank: Bank,
+    reserve_account: AccountId,
+    tasks: BTreeMap<String, Task>,
+    actors: SlotMap<ActorId, Actor>,
+    inspected_actor: Option<Uuid>,
+    /// Flows of removed actors by task, so totals keep counting what they did.
+    retired_flows: BTreeMap<String, TaskFlow>,
+    /// Items by kind which left the economy, held or listed by removed actors or destroyed.
+    removed_goods: BTreeMap<String, isize>,
+    /// Number of entries kept in the log of every actor.
+    log_retention: usize,
+}
+impl World {
+    pub fn new(scenario: &Scenario) -> Self {
+        let mut bank = Bank::new(&scenario.bank);
+        let reserve_account = bank.open_account("Reserve");
+        let carrier_account = bank.open_account("Carriers");
+        let treasury = bank.open_account("Government");
+        let markets = scenario
+            .get_market_names()
+            .into_iter()
+            .map(|name| {
+                let mut market = Market::new(&name);
+                for (item_kind, mechanism) in scenario.mechanisms.iter() {
+                    market.set_mechanism(item_kind, *mechanism);
+                }
+                market.set_policy(&scenario.government, treasury);
+                (name, market)
+            })
+            .collect();
+        let tasks = scenario
+            .tasks
+            .keys()
+            .filter_map(|name| Some((name.clone(), scenario.get_task(name)?)))
+            .collect();
+        let mut world = Self {
+            scenario_name: scenario.name.clone(),
+            seed: scenario.seed,
+            tick: 0,
+            rng: StdRng::seed_from_u64(scenario.seed),
+            markets,
+            default_market: scenario.get_market_names().remove(0),
+            geography: Geography::new(scenario.routes.clone()),
+            carrier_account,
+            government: Government::new(&scenario.government, treasury),
+            bank,
+            reserve_account,
+            tasks,
+            actors: SlotMap::with_key(),
+            inspected_actor: None,
+            retired_flows: BTreeMap::new(),
+            removed_goods: BTreeMap::new(),
+            log_retention: scenario.log_retention,
+        };
+        for definition in scenario.actors.iter() {
+            let task = definition
+                .task
+                .as_ref()
+                .and_then(|task| world.tasks.get(task).cloned());
+            let location = definition
+                .location
+                .clone()
+                .unwrap_or_else(|| world.default_market.clone());
+            for name in definition.get_names() {
+                let id = world.add_actor(&name, task.clone(), definition.balance, &location);
+                if let Some(item_kind) = definition.trade.as_ref() {
+                    world.actors[id].start_trading(item_kind);
+                }
+            }
+        }
+        world
+    }
+    fn add_actor(
+        &mut self,
+        name: &str,
+        task: Option<Task>,
+        balance: u64,
+        location: &str,
+    ) -> ActorId {
+        let mut actor = Actor::new(name, &mut self.bank, task, location, self.log_retention);
+        actor.set_tick(self.tick);
+
+        // Fund actor out of the bank reserve
+        if balance > 0 {
+            self.bank
+                .process_transaction(self.reserve_account, actor.get_account(), balance)
+                .expect("Reserve and actor accounts are open");
+        }
+
+        self.actors.insert(actor)
+    }
+    ///
+    /// Advances the world by one tick. Every actor decides what to do at once, on the threads of
+    /// the current rayon pool, from the world as it was at the start of the tick. The decisions
+    /// are then carried out one actor at a time in an order shuffled by the scenario seed, so that
+    /// no actor is always first in line at the market. As nothing else depends on the threads, the
+    /// outcome is the same however many there are. Auctions clear once every actor had its turn,
+    /// after which the government spends.
+    ///
+    pub fn tick(&mut self) {
+        let tick_span = info_span!("tick", tick = self.tick);
+        let _tick = tick_span.enter();
+        for actor in self.actors.values_mut() {
+            actor.set_tick(self.tick);
+        }
+
+        // Tick Markets
+        for market in self.markets.values_mut() {
+            market.tick();
+        }
+        self.deliver();
+
+        // Tick Bank
+        self.bank.tick();
+
+        // Let each actor decide
+        self.geography.update_prices(&self.markets);
+        let views = self
+            .actors
+            .iter_mut()
+            .filter_map(|(id, actor)| Some((id, actor.prepare(id, &self.markets)?)))
+            .collect::<Vec<_>>();
+        let actors = &self.actors;
+        let geography = &self.geography;
+        let mut decisions = views
+            .into_par_iter()
+            .map(|(id, view)| {
+                // Worker threads do not know which tick they work on
+                let actor = &actors[id];
+                let _decide =
+                    debug_span!(parent: &tick_span, "decide", actor = %actor.get_name()).entered();
+                (id, actor.get_action().decide(&view, geography))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect::<SecondaryMap<_, _>>();
+
+        // Tick each actor
+        let mut order = self.actors.keys().collect::<Vec<_>>();
+        order.shuffle(&mut self.rng);
+        for id in order {
+            if let Some(intents) = decisions.remove(id) {
+                self.actors[id].tick(
+                    id,
+                    &mut self.markets,
+                    &mut self.bank,
+                    &self.geography,
+                    self.carrier_account,
+                    intents,
+                );
+            }
+        }
+        for market in self.markets.values_mut() {
+            market.clear_auctions(&mut self.bank, &mut self.rng);
+        }
+        self.deliver();
+
+        // Tick Government
+        self.government
+            .tick(&mut self.markets, &self.actors, &mut self.bank);
+
+        self.tick += 1;
+    }
+    ///
+    /// Hands the items markets gave out to their actors. Items of actors which are gone leave the
+    /// economy with them.
+    ///
+    fn deliver(&mut self) {
+        for market in self.markets.values_mut() {
+            for delivery in market.take_deliveries() {
+                let (id, item) = match &delivery {
+                    Delivery::Returned { owner, item } => (*owner, item),
+                    Delivery::Bought { buyer, item, .. } => (*buyer, item),
+                };
+                let actor = match self.actors.get_mut(id) {
+                    Some(actor) => actor,
+                    None => {
+                        *self.removed_goods.entry(item.kind.clone()).or_default() += 1;
+                        continue;
+                    }
+                };
+                match delivery {
+                    Delivery::Returned { item, .. } => actor.take_back_item(item),
+                    Delivery::Bought { item, price, .. } => actor.receive_item(item, price),
+                }
+            }
+        }
+    }
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+    pub fn get_task_names(&self) -> Vec<String> {
+        self.tasks.keys().cloned().collect()
+    }
+    ///
+    /// Applies an intervention between ticks. Returns a description of what changed, or why
+    /// nothing did.
+    ///
+    pub fn apply_intervention(&mut self, intervention: &Intervention) -> InterventionResult {
+        match intervention {
+            Intervention::SpawnActor {
+                name,
+                task,
+                balance,
+                location,
+            } => {
+                if self.find_actor(name).is_some() {
+                    return Err(format!("Actor '{}' already exists", name));
+                }
+                let location = match location {
+                    Some(location) if self.markets.contains_key(location) => location.clone(),
+                    Some(location) => return Err(format!("Unknown market '{}'", location)),
+                    None => self.default_market.clone(),
+                };
+                let task = match task {
+                    Some(task) => Some(
+                        self.tasks
+                            .get(task)
+                            .cloned()
+                            .ok_or_else(|| format!("Unknown task '{}'", task))?,
+                    ),
+                    None => None,
+                };
+                self.add_actor(name, task, *balance, &location);
+                Ok(format!("Spawned actor '{}'", name))
+            }
+            Intervention::RemoveActor { name } => {
+                let id = self
+                    .find_actor(name)
+                    .ok_or_else(|| format!("Unknown actor '{}'", name))?;
+                let actor = self.actors.remove(id).unwrap();
+                let mut goods = actor.get_store_actual().get_counts();
+                for market in self.markets.values_mut() {
+                    // Listed items go back to the actor and leave with it
+                    for item in market.cancel_listings_of(id) {
+                        *goods.entry(item.kind).or_default() += 1;
+                    }
+                    market.cancel_bids_of(id);
+                }
+                for (item_kind, count) in goods {
+                    *self.removed_goods.entry(item_kind).or_default() += count;
+                }
+                if let Some(task) = actor.get_task() {
+                    self.retired_flows
+                        .entry(task.name.clone())
+                        .or_default()
+                        .add(actor.get_flow());
+                }
+                Ok(format!("Removed actor '{}'", name))
+            }
+            Intervention::GrantMoney { account, amount } => {
+                let account_id = self
+                    .bank
+                    .get_accounts()
+                    .find(|(_, candidate)| candidate.get_name() == *account)
+                    .map(|(id, _)| id)
+                    .ok_or_else(|| format!("Unknown account '{}'", account))?;
+                self.bank
+                    .process_transaction(self.reserve_account, account_id, *amount)
+                    .map_err(|error| error.to_string())?;
+                Ok(format!("Granted {} to '{}'", amount, account))
+            }
+            Intervention::SetInterestRate { rate } => {
+                if !rate.is_finite() || *rate < 0.0 {
+                    return Err(format!("Invalid interest rate {}", rate));
+                }
+                self.bank.set_interest_rate(*rate);
+                Ok(format!("Set interest rate to {}", rate))
+            }
+            Intervention::SetPriceCeiling { item_kind, ceiling } => {
+                if ceiling.is_some_and(|ceiling| ceiling < 0) {
+                    return Err(format!("Invalid price ceiling for '{}'", item_kind));
+                }
+                for market in self.markets.values_mut() {
+                    market.set_price_ceiling(item_kind, *ceiling);
+                }
+                Ok(match ceiling {
+                    Some(ceiling) => format!("Capped price of '{}' at {}", item_kind, ceiling),
+                    None => format!("Lifted price ceiling of '{}'", item_kind),
+                })
+            }
+            Intervention::DestroyStock {
+                actor,
+                item_kind,
+                fraction,
+            } => {
+                if !(0.0..=1.0).contains(fraction) {
+                    return Err(format!("Fraction {} is not between 0 and 1", fraction));
+                }
+                let ids = match actor {
+                    Some(name) => vec![self
+                        .find_actor(name)
+                        .ok_or_else(|| format!("Unknown actor '{}'", name))?],
+                    None => self.actors.keys().collect(),
+                };
+                let destroyed = ids
+                    .into_iter()
+                    .map(|id| self.actors[id].destroy_stock(item_kind, *fraction))
+                    .sum::<isize>();
+                *self.removed_goods.entry(item_kind.clone()).or_default() += destroyed;
+                Ok(format!("Destroyed {} of '{}'", destroyed, item_kind))
+            }
+        }
+    }
+    fn find_actor(&self, name: &str) -> Option<ActorId> {
+        self.actors
+            .iter()
+            .find(|(_, actor)| actor.get_name() == name)
+            .map(|(id, _)| id)
+    }
+    ///
+    /// Get the log entries of the named actor which match the query, least recently logged first.
+    ///
+    pub fn get_actor_log(&self, name: &str, query: &LogQuery) -> Option<Vec<LogEntry>> {
+        let id = self.find_actor(name)?;
+        Some(self.actors[id].query_log(query).cloned().collect())
+    }
+    ///
+    /// Writes the log entries of every actor which match the query as JSON lines, actor by actor in
+    /// the order of their names.
+    ///
+    pub fn write_actor_logs(
+        &self,
+        writer: &mut impl Write,
+        query: &LogQuery,
+    ) -> Result<(), String> {
+        let mut actors = self
+            .actors
+            .values()
+            .map(|actor| (actor.get_name(), actor))
+            .collect::<Vec<_>>();
+        actors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, actor) in actors {
+            for entry in actor.query_log(query) {
+                write_log_entry(writer, &name, entry)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn get_metrics(&self) -> Metrics {
+        let mut held_by_item_kind = BTreeMap::new();
+        for actor in self.actors.values() {
+            let store = actor.get_store_actual();
+            for item_kind in store.get_item_kinds() {
+                *held_by_item_kind.entry(item_kind.clone()).or_insert(0) += store.count(item_kind);
+            }
+        }
+        let listed_by_item_kind = self.get_listed_counts();
+        let balances = self
+            .actors
+            .values()
+            .map(|actor| self.bank.get_balance(actor.get_account()))
+            .collect::<Vec<_>>();
+        Metrics {
+            tick: self.tick,
+            actor_count: self.actors.len(),
+            trade_count: self
+                .markets
+                .values()
+                .map(|market| market.get_trade_count())
+                .sum(),
+            trade_volume: self
+                .markets
+                .values()
+                .map(|market| market.get_trade_volume())
+                .sum(),
+            balance_min: balances.iter().copied().min().unwrap_or(0),
+            balance_max: balances.iter().copied().max().unwrap_or(0),
+            held_by_item_kind,
+            listed_by_item_kind,
+            tax_revenue: self.government.get_revenue(),
+            government_spending: self.government.get_spending(),
+            total_tax_revenue: self.government.get_total_revenue(),
+            total_government_spending: self.government.get_total_spending(),
+        }
+    }
+    pub fn get_renderable_state(&self) -> RenderableState {
+        let actor_info = self
+            .actors
+            .values()
+            .map(Self::get_actor_info)
+            .collect::<Vec<_>>();
+
+        RenderableState {
+            version: 0,
+            tick: self.tick,
+            actor_count: self.actors.len(),
+            task_names: self.get_task_names(),
+            listed_item_kinds: self.get_listed_item_kinds(),
+            actor_info,
+            actor_detail: self
+                .inspected_actor
+                .and_then(|id| self.get_actor_detail(id)),
+            flow_graph: self.get_flow_graph(),
+            price_history: self
+                .get_price_points()
+                .into_iter()
+                .map(|(item_kind, point)| (item_kind, VecDeque::from([point])))
+                .collect(),
+        }
+    }
+    ///
+    /// Builds the changes to the renderable state since the revisions in `published` were
+    /// recorded, and records the current ones. Only stores whose revision moved are cloned, actors
+    /// which only logged send their recent log.
+    ///
+    pub fn get_renderable_diff(
+        &self,
+        published: &mut HashMap<Uuid, PublishedRevision>,
+    ) -> RenderableDiff {
+        let mut changed_actors = HashMap::new();
+        let mut changed_logs = HashMap::new();
+        let mut is_membership_changed = false;
+        for actor in self.actors.values() {
+            let revision = PublishedRevision {
+                store: actor.get_store_revision(),
+                log: actor.get_log_revision(),
+            };
+            match published.insert(actor.id, revision) {
+                Some(previous) if previous == revision => {}
+                Some(previous) if previous.store == revision.store => {
+                    changed_logs.insert(actor.id, actor.get_recent_log());
+                }
+                previous => {
+                    is_membership_changed |= previous.is_none();
+                    changed_actors.insert(actor.id, Self::get_actor_info(actor));
+                }
+            }
+        }
+
+        // Forget actors which have been removed since
+        if published.len() != self.actors.len() {
+            let ids = self
+                .actors
+                .values()
+                .map(|actor| actor.id)
+                .collect::<HashSet<_>>();
+            published.retain(|id, _| ids.contains(id));
+            is_membership_changed = true;
+        }
+        let actor_ids =
+            is_membership_changed.then(|| self.actors.values().map(|actor| actor.id).collect());
+
+        RenderableDiff {
+            version: 0,
+            tick: self.tick,
+            actor_count: self.actors.len(),
+            task_names: self.get_task_names(),
+            listed_item_kinds: self.get_listed_item_kinds(),
+            changed_actors,
+            changed_logs,
+            actor_ids,
+            actor_detail: self
+                .inspected_actor
+                .and_then(|id| self.get_actor_detail(id)),
+            flow_graph: self.get_flow_graph(),
+            price_points: self
+                .get_price_points()
+                .into_iter()
+                .map(|(item_kind, point)| (item_kind, vec![point]))
+                .collect(),
+        }
+    }
+    ///
+    /// Get the prices of every traded, listed or bid on item kind over the last tick, in all
+    /// markets.
+    ///
+    pub fn get_price_points(&self) -> BTreeMap<String, PricePoint> {
+        let listings = self
+            .markets
+            .values()
+            .flat_map(|market| market.get_listings())
+            .map(|listing| (listing.item.kind.as_str(), listing.price));
+        let bids = self
+            .markets
+            .values()
+            .flat_map(|market| market.get_bids())
+            .map(|bid| (bid.item_kind.as_str(), bid.limit));
+        get_price_points(self.tick, &self.get_trades(), listings, bids)
+    }
+    ///
+    /// Get the trades of the last tick in every market.
+    ///
+    fn get_trades(&self) -> Vec<Trade> {
+        self.markets
+            .values()
+            .flat_map(|market| market.get_trades().iter().cloned())
+            .collect()
+    }
+    ///
+    /// Builds the production network from the tasks of the world. Edges carry the number of
+    /// items every task has consumed and produced so far, item nodes the current inventory.
+    ///
+    pub fn get_flow_graph(&self) -> FlowGraph {
+        let mut flows = self.get_task_flows();
+        let inventory = self.get_inventory();
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut item_kinds = inventory.keys().cloned().collect::<BTreeSet<_>>();
+        for (name, task) in self.tasks.iter() {
+            nodes.push(FlowNode {
+                name: name.clone(),
+                kind: FlowNodeKind::Task,
+                inventory: 0,
+            });
+            let flow = flows.remove(name).unwrap_or_default();
+            for (item_kind, _) in task.inputs.iter() {
+                item_kinds.insert(item_kind.clone());
+                edges.push(FlowEdge {
+                    from: item_kind.clone(),
+                    to: name.clone(),
+                    total: flow.consumed.get(item_kind).copied().unwrap_or(0),
+                });
+            }
+            for (item_kind, _) in task.outputs.iter() {
+                item_kinds.insert(item_kind.clone());
+                edges.push(FlowEdge {
+                    from: name.clone(),
+                    to: item_kind.clone(),
+                    total: flow.produced.get(item_kind).copied().unwrap_or(0),
+                });
+            }
+        }
+        for item_kind in item_kinds {
+            nodes.push(FlowNode {
+                inventory: inventory.get(&item_kind).copied().unwrap_or(0),
+                name: item_kind,
+                kind: FlowNodeKind::Item,
+            });
+        }
+
+        FlowGraph {
+            tick: self.tick,
+            nodes,
+            edges,
+        }
+    }
+    ///
+    /// Get the items every task has consumed and produced so far, including removed actors.
+    ///
+    fn get_task_flows(&self) -> BTreeMap<String, TaskFlow> {
+        let mut flows = self.retired_flows.clone();
+        for actor in self.actors.values() {
+            if let Some(task) = actor.get_task() {
+                flows
+                    .entry(task.name.clone())
+                    .or_default()
+                    .add(actor.get_flow());
+            }
+        }
+        flows
+    }
+    ///
+    /// Get the number of items of every kind held by actors or in escrow in any market.
+    ///
+    fn get_inventory(&self) -> BTreeMap<String, isize> {
+        let mut inventory = BTreeMap::<String, isize>::new();
+        for actor in self.actors.values() {
+            for (item_kind, count) in actor.get_store_actual().get_counts() {
+                *inventory.entry(item_kind).or_default() += count;
+            }
+        }
+        for market in self.markets.values() {
+            for (item_kind, count) in market.get_escrow() {
+                *inventory.entry(item_kind).or_default() += count;
+            }
+        }
+        inventory
+    }
+    ///
+    /// Get the number of items of every kind which appeared or vanished other than by being
+    /// produced, consumed, removed or used up by the government. Empty unless goods leak.
+    ///
+    pub fn get_unaccounted_goods(&self) -> BTreeMap<String, isize> {
+        let mut unaccounted = BTreeMap::<String, isize>::new();
+        for flow in self.get_task_flows().values() {
+            for (item_kind, count) in flow.produced.iter() {
+                *unaccounted.entry(item_kind.clone()).or_default() += *count as isize;
+            }
+            for (item_kind, count) in flow.consumed.iter() {
+                *unaccounted.entry(item_kind.clone()).or_default() -= *count as isize;
+            }
+        }
+        let accounted_for = self
+            .get_inventory()
+            .into_iter()
+            .chain(self.removed_goods.clone())
+            .chain(self.government.get_used_up().clone());
+        for (item_kind, count) in accounted_for {
+            *unaccounted.entry(item_kind).or_default() -= count;
+        }
+        unaccounted.retain(|_, count| *count != 0);
+        unaccounted
+    }
+    ///
+    /// Checks that goods and money are conserved, that the indexes of every market agree with
+    /// the listings and bids they hold, and that no actor holds a negative number of items.
+    /// Returns the first violation found. Meant to be run between ticks, as it takes time in
+    /// proportion to everything the world holds.
+    ///
+    pub fn check_invariants(&self) -> Result<(), Violation> {
+        let unaccounted = self.get_unaccounted_goods();
+        if !unaccounted.is_empty() {
+            return Err(Violation::GoodsNotConserved(unaccounted));
+        }
+        self.bank.check().map_err(Violation::MoneyNotConserved)?;
+        for (name, market) in self.markets.iter() {
+            market
+                .check(|id| self.actors.contains_key(id))
+                .map_err(|context| Violation::MarketInconsistent {
+                    market: name.clone(),
+                    context,
+                })?;
+        }
+        for actor in self.actors.values() {
+            let counts = actor.get_store_actual().get_counts();
+            if let Some((item_kind, count)) = counts.into_iter().find(|(_, count)| *count < 0) {
+                return Err(Violation::NegativeInventory {
+                    actor: actor.get_name(),
+                    item_kind,
+                    count,
+                });
+            }
+        }
+        Ok(())
+    }
+    ///
+    /// Select the actor whose details are included in renderable diffs.
+    ///
+    pub fn set_inspected_actor(&mut self, id: Option<Uuid>) {
+        self.inspected_actor = id;
+    }
+    pub fn get_actor_detail(&self, id: Uuid) -> Option<ActorDetail> {
+        let (actor_id, actor) = self.actors.iter().find(|(_, actor)| actor.id == id)?;
+        let listings = self
+            .markets
+            .values()
+            .flat_map(|market| market.get_listings_of_owner(actor_id))
+            .map(|listing| (listing.item.kind.clone(), listing.price))
+            .collect();
+        Some(ActorDetail {
+            id,
+            name: actor.get_name(),
+            balance: self.bank.get_balance(actor.get_account()),
+            loans: self.bank.get_loan_dues(actor.get_account()),
+            action: actor.get_action_name(),
+            action_progress: actor.get_action_progress(),
+            store_actual: actor.get_store_actual().get_counts(),
+            store_target: actor.get_store_target().get_counts(),
+            listings,
+            log: actor.get_log(),
+        })
+    }
+    fn get_actor_info(actor: &Actor) -> ActorInfo {
+        (
+            actor.id,
+            actor.get_name(),
+            actor.get_recent_log(),
+            actor.get_store_actual(),
+        )
+    }
+    fn get_listed_item_kinds(&self) -> Vec<(String, usize)> {
+        self.get_listed_counts().into_iter().collect()
+    }
+    ///
+    /// Get the number of listings of every item kind listed in any market.
+    ///
+    fn get_listed_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for market in self.markets.values() {
+            for item_kind in market.get_listed_item_kinds() {
+                *counts.entry(item_kind.clone()).or_default() += market.get_listed_count(item_kind);
+            }
+        }
+        counts
+    }
+    pub fn get_snapshot(&self) -> Snapshot {
+        let actors = self
+            .actors
+            .values()
+            .map(|actor| ActorSnapshot {
+                id: actor.id,
+                name: actor.get_name(),
+                location: actor.get_location().to_string(),
+                balance: self.bank.get_balance(actor.get_account()),
+                action: actor.get_action_name(),
+                log: actor.get_recent_log(),
+                store_actual: actor.get_store_actual().get_counts(),
+                store_target: actor.get_store_target().get_counts(),
+            })
+            .collect();
+        let accounts = self
+            .bank
+            .get_accounts()
+            .map(|(id, account)| AccountSnapshot {
+                name: account.get_name(),
+                bank: self.bank.get_name(),
+                balance: self.bank.get_balance(id),
+            })
+            .collect();
+        let mut listings = self
+            .markets
+            .values()
+            .flat_map(|market| {
+                market
+                    .get_listings()
+                    .map(move |listing| (market.get_name(), listing))
+            })
+            .map(|(market, listing)| ListingSnapshot {
+                id: listing.item.id,
+                market: market.to_string(),
+                item_kind: listing.item.kind.clone(),
+                price: listing.price,
+                owner: listing.owner.as_ref().map(|owner| owner.name.clone()),
+            })
+            .collect::<Vec<_>>();
+        listings.sort_by(|a, b| {
+            (&a.market, &a.item_kind, a.price).cmp(&(&b.market, &b.item_kind, b.price))
+        });
+        let mut bids = self
+            .markets
+            .values()
+            .flat_map(|market| market.get_bids().map(move |bid| (market.get_name(), bid)))
+            .map(|(market, bid)| BidSnapshot {
+                market: market.to_string(),
+                item_kind: bid.item_kind.clone(),
+                limit: bid.limit,
+                buyer: bid.buyer_name.clone(),
+            })
+            .collect::<Vec<_>>();
+        bids.sort_by(|a, b| {
+            (&a.market, &a.item_kind, a.limit).cmp(&(&b.market, &b.item_kind, b.limit))
+        });
+        Snapshot {
+            scenario: self.scenario_name.clone(),
+            seed: self.seed,
+            tick: self.tick,
+            interest_rate: self.bank.get_interest_rate(),
+            metrics: self.get_metrics(),
+            actors,
+            accounts,
+            listings,
+            bids,
+            trades: self.get_trades(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        fixture::{auctions, regions, run},
+        logbook::RECENT_ENTRIES,
+    };
+
+    #[test]
+    fn world_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<World>();
+    }
+
+    #[test]
+    fn same_outcome_on_any_number_of_threads() {
+        for scenario in [regions(), auctions()] {
+            let run_on = |thread_count: usize| {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(thread_count)
+                    .build()
+                    .unwrap();
+                pool.install(|| {
+                    let world = run(&scenario, 200);
+                    let metrics = world.get_metrics();
+                    let actors = world
+                        .get_snapshot()
+                        .actors
+                        .into_iter()
+                        .map(|actor| (actor.name, actor.balance, actor.store_actual))
+                        .collect::<Vec<_>>();
+                    (metrics.trade_count, metrics.trade_volume, actors)
+                })
+            };
+            let outcome = run_on(1);
+            assert!(outcome.0 > 0);
+            assert_eq!(run_on(4), outcome);
+        }
+    }
+
+    #[test]
+    fn goods_are_conserved() {
+        let mut listing_ttl = Scenario::default();
+        listing_ttl.tasks.get_mut("farmer").unwrap().listing_ttl = Some(5);
+        listing_ttl.government.purchases = BTreeMap::from([("FoodBox".to_string(), 1)]);
+        for scenario in [listing_ttl, regions(), auctions()] {
+            let mut world = World::new(&scenario);
+            for tick in 0..200 {
+                if tick == 50 {
+                    let name = world.actors.values().next().unwrap().get_name();
+                    let remove = Intervention::RemoveActor { name };
+                    assert!(world.apply_intervention(&remove).is_ok());
+                }
+                if tick == 100 {
+                    let shock = Intervention::DestroyStock {
+                        actor: None,
+                        item_kind: "Apple".to_string(),
+                        fraction: 0.5,
+                    };
+                    assert!(world.apply_intervention(&shock).is_ok());
+                }
+                world.tick();
+                assert_eq!(world.get_unaccounted_goods(), BTreeMap::new());
+                assert_eq!(world.check_invariants(), Ok(()), "at tick {}", tick);
+            }
+        }
+    }
+
+    #[test]
+    fn invariant_violation() {
+        let mut world = run(&Scenario::default(), 20);
+        assert_eq!(world.check_invariants(), Ok(()));
+        // Apples which leave the economy without ever being in it
+        world.removed_goods.insert("Apple".to_string(), 2);
+        assert_eq!(
+            world.check_invariants(),
+            Err(Violation::GoodsNotConserved(BTreeMap::from([(
+                "Apple".to_string(),
+                -2
+            )])))
+        );
+    }
+
+    #[test]
+    fn renderable_diff() {
+        let mut world = World::new(&Scenario::default());
+        let mut published = HashMap::new();
+        let diff = world.get_renderable_diff(&mut published);
+        assert_eq!(diff.changed_actors.len(), 6);
+        assert_eq!(diff.actor_ids.map(|ids| ids.len()), Some(6));
+        // Nothing changed in between
+        let diff = world.get_renderable_diff(&mut published);
+        assert!(diff.changed_actors.is_empty());
+        assert!(diff.actor_ids.is_none());
+        // Nobody has anything to store yet, yet everyone logs what they set out to do
+        world.tick();
+        let diff = world.get_renderable_diff(&mut published);
+        assert!(diff.changed_actors.is_empty());
+        assert_eq!(diff.changed_logs.len(), 6);
+
+        // Applying every diff gives the same state as building it in full
+        let mut state = RenderableState::default();
+        state.apply(RenderableDiff::from_state(world.get_renderable_state()));
+        for _ in 0..20 {
+            world.tick();
+            state.apply(world.get_renderable_diff(&mut published));
+        }
+        let remove = Intervention::RemoveActor {
+            name: "Actor_1_farmer".to_string(),
+        };
+        assert!(world.apply_intervention(&remove).is_ok());
+        let diff = world.get_renderable_diff(&mut published);
+        assert_eq!(diff.actor_ids.as_ref().map(|ids| ids.len()), Some(5));
+        state.apply(diff);
+        let expected = world.get_renderable_state();
+        assert_eq!(state.actor_info.len(), expected.actor_info.len());
+        for (actual, expected) in state.actor_info.iter().zip(expected.actor_info.iter()) {
+            assert_eq!(actual.0, expected.0);
+            assert_eq!(actual.2, expected.2);
+            assert_eq!(actual.3.get_counts(), expected.3.get_counts());
+        }
+    }
+
+    #[test]
+    fn inspect_actor() {
+        let mut world = run(&Scenario::default(), 30);
+        let id = world.actors.values().next().unwrap().id;
+        let mut published = HashMap::new();
+        assert!(world
+            .get_renderable_diff(&mut published)
+            .actor_detail
+            .is_none());
+        world.set_inspected_actor(Some(id));
+        let detail = world
+            .get_renderable_diff(&mut published)
+            .actor_detail
+            .unwrap();
+        assert_eq!(detail.name, "Actor_1_farmer");
+        // The detail holds the whole log, the renderable state only the recent part of it
+        let recent = world.get_renderable_state().actor_info[0].2.clone();
+        assert!(recent.len() <= RECENT_ENTRIES);
+        assert_eq!(detail.log[..recent.len()], recent[..]);
+    }
+}
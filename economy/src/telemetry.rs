@@ -0,0 +1,176 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+use tracing_flame::{FlameLayer, FlushGuard};
+use tracing_subscriber::{fmt::format::FmtSpan, prelude::*, EnvFilter};
+
+///
+/// Spans and events recorded unless a filter is given. Ticks, actor ticks, market operations and
+/// trades, but not every transaction.
+///
+pub const DEFAULT_FILTER: &str = "economy=debug";
+
+///
+/// How the spans and events the simulation is instrumented with are written.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Readable lines on stderr, with the time spent in every span as it closes.
+    Pretty,
+    /// One JSON object per event and closed span, written to a file.
+    Json,
+    /// Folded stacks of the time spent in every span, written to a file. They can be turned into
+    /// a flamegraph with `inferno-flamegraph`.
+    Flame,
+}
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(TraceFormat::Pretty),
+            "json" => Ok(TraceFormat::Json),
+            "flame" => Ok(TraceFormat::Flame),
+            _ => Err(format!(
+                "Unknown trace format '{}', expected pretty, json or flame",
+                s
+            )),
+        }
+    }
+}
+impl Display for TraceFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceFormat::Pretty => write!(f, "pretty"),
+            TraceFormat::Json => write!(f, "json"),
+            TraceFormat::Flame => write!(f, "flame"),
+        }
+    }
+}
+
+///
+/// Keeps the trace output open. Folded stacks are only complete once it is dropped.
+///
+pub struct TraceGuard {
+    _flame: Option<FlushGuard<BufWriter<File>>>,
+}
+
+///
+/// Installs the global subscriber writing the trace in the given format, keeping what the filter
+/// directives let through, for example `economy=trace` or `economy[tick]=info`. The pretty format
+/// writes to stderr, the others need an output file.
+///
+pub fn install(
+    format: TraceFormat,
+    output: Option<&Path>,
+    filter: &str,
+) -> Result<TraceGuard, String> {
+    let filter = EnvFilter::try_new(filter)
+        .map_err(|error| format!("Invalid trace filter '{}': {}", filter, error))?;
+    let registry = tracing_subscriber::registry().with(filter);
+    let get_output = || -> Result<PathBuf, String> {
+        output
+            .map(Path::to_path_buf)
+            .ok_or(format!("The {} trace format needs an output file", format))
+    };
+    let create_error = |path: &Path, error: &dyn Display| {
+        format!("Could not create {}: {}", path.display(), error)
+    };
+    let mut guard = TraceGuard { _flame: None };
+    let result = match format {
+        TraceFormat::Pretty => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .pretty()
+                    .with_span_events(FmtSpan::CLOSE)
+                    .with_writer(std::io::stderr),
+            )
+            .try_init(),
+        TraceFormat::Json => {
+            let path = get_output()?;
+            let file = File::create(&path).map_err(|error| create_error(&path, &error))?;
+            registry
+                .with(
+                    tracing_subscriber::fmt::layer()
+                        .json()
+                        .with_span_events(FmtSpan::CLOSE)
+                        .with_writer(Mutex::new(file)),
+                )
+                .try_init()
+        }
+        TraceFormat::Flame => {
+            let path = get_output()?;
+            let (layer, flush) =
+                FlameLayer::with_file(&path).map_err(|error| create_error(&path, &error))?;
+            guard._flame = Some(flush);
+            let layer = layer.with_threads_collapsed(true).with_file_and_line(false);
+            registry.with(layer).try_init()
+        }
+    };
+    result.map_err(|error| format!("Could not install trace subscriber: {}", error))?;
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{fixture::advance, scenario::Scenario, world::World};
+
+    #[test]
+    fn parse_format() {
+        for format in [TraceFormat::Pretty, TraceFormat::Json, TraceFormat::Flame] {
+            assert_eq!(format.to_string().parse(), Ok(format));
+        }
+        assert!("xml".parse::<TraceFormat>().is_err());
+    }
+
+    #[test]
+    fn tick_is_traced() {
+        use std::{io::Write, sync::Arc};
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+        impl Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_span_events(FmtSpan::CLOSE)
+                .with_writer(move || writer.clone()),
+        );
+        let mut world = World::new(&Scenario::default());
+        tracing::subscriber::with_default(subscriber, || advance(&mut world, 20));
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let lines = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let count = |message: &str, name: &str| {
+            lines
+                .iter()
+                .filter(|line| line["fields"]["message"] == message && line["span"]["name"] == name)
+                .count()
+        };
+        assert_eq!(count("close", "tick"), 20);
+        assert_eq!(count("close", "market_tick"), 20);
+        assert!(count("close", "actor_tick") > 0);
+        assert!(count("close", "place_bid") > 0);
+        // Trades are logged within the market operation which made them
+        assert!(count("trade", "buy_listing") > 0);
+        assert!(count("transaction", "buy_listing") > 0);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    renderable::StateReceiver,
+    simulation::control::{ControlCommand, SimulationCommand, MIN_SPEED},
+    RenderableState,
+};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::{
+    io::{self, Stdout},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+use uuid::Uuid;
+
+mod view;
+
+///
+/// How long to wait for a key before drawing the latest state again.
+///
+const FRAME_TIME: Duration = Duration::from_millis(100);
+const LOG_SCROLL_STEP: u16 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tab {
+    Listings,
+    Actors,
+}
+impl Tab {
+    const ALL: [Tab; 2] = [Tab::Listings, Tab::Actors];
+
+    fn get_title(&self) -> &'static str {
+        match self {
+            Tab::Listings => "Listings",
+            Tab::Actors => "Actors",
+        }
+    }
+}
+
+struct TuiState {
+    renderable_state: RenderableState,
+    command_tx: Sender<SimulationCommand>,
+    tab: Tab,
+    is_paused: bool,
+    speed: f64,
+    selected_actor: Option<Uuid>,
+    log_scroll: u16,
+}
+impl TuiState {
+    fn new(command_tx: Sender<SimulationCommand>) -> Self {
+        Self {
+            renderable_state: RenderableState::default(),
+            command_tx,
+            tab: Tab::Listings,
+            is_paused: false,
+            speed: 1.0,
+            selected_actor: None,
+            log_scroll: 0,
+        }
+    }
+    fn send(&self, command: SimulationCommand) {
+        let _ = self.command_tx.send(command);
+    }
+    fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(MIN_SPEED);
+        self.send(ControlCommand::SetSpeed(self.speed).into());
+    }
+    ///
+    /// Moves the actor selection by the given number of rows and inspects the newly selected
+    /// actor.
+    ///
+    fn move_selection(&mut self, rows: isize) {
+        let actors = &self.renderable_state.actor_info;
+        if actors.is_empty() {
+            return;
+        }
+        let index = self
+            .selected_actor
+            .and_then(|id| {
+                actors
+                    .iter()
+                    .position(|(actor_id, _, _, _)| *actor_id == id)
+            })
+            .map_or(0, |index| {
+                (index as isize + rows).clamp(0, actors.len() as isize - 1) as usize
+            });
+        let id = actors[index].0;
+        if self.selected_actor != Some(id) {
+            self.selected_actor = Some(id);
+            self.log_scroll = 0;
+            self.send(SimulationCommand::Inspect(Some(id)));
+        }
+    }
+    ///
+    /// Applies a key press. Returns false when the user asked to quit.
+    ///
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab | KeyCode::BackTab => {
+                let index = Tab::ALL.iter().position(|tab| *tab == self.tab).unwrap();
+                self.tab = Tab::ALL[(index + 1) % Tab::ALL.len()];
+            }
+            KeyCode::Char(' ') => {
+                self.is_paused = !self.is_paused;
+                let command = if self.is_paused {
+                    ControlCommand::Pause
+                } else {
+                    ControlCommand::Resume
+                };
+                self.send(command.into());
+            }
+            KeyCode::Char('s') => {
+                self.is_paused = true;
+                self.send(ControlCommand::Step.into());
+            }
+            KeyCode::Char('f') => {
+                let target_tick = self.renderable_state.tick + 100;
+                self.send(ControlCommand::JumpToTick(target_tick).into());
+            }
+            KeyCode::Char('+') => self.set_speed(self.speed * 2.0),
+            KeyCode::Char('-') => self.set_speed(self.speed / 2.0),
+            KeyCode::Char('1') => self.set_speed(1.0),
+            KeyCode::Char('m') => self.set_speed(f64::INFINITY),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.log_scroll = self.log_scroll.saturating_add(LOG_SCROLL_STEP),
+            KeyCode::PageUp => self.log_scroll = self.log_scroll.saturating_sub(LOG_SCROLL_STEP),
+            _ => {}
+        }
+        true
+    }
+}
+
+///
+/// Terminal front-end, showing the same renderable state as the window of `graphics::render`.
+/// Runs until the user quits.
+///
+pub fn render(rx: StateReceiver, command_tx: Sender<SimulationCommand>) -> Result<(), String> {
+    let mut terminal =
+        setup_terminal().map_err(|error| format!("Could not set up the terminal: {}", error))?;
+    let result = run(&mut terminal, rx, TuiState::new(command_tx));
+
+    // Restore the terminal even when drawing failed
+    let restored = restore_terminal(&mut terminal);
+    result
+        .and(restored)
+        .map_err(|error| format!("Terminal error: {}", error))
+}
+
+fn run(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    rx: StateReceiver,
+    mut state: TuiState,
+) -> io::Result<()> {
+    loop {
+        if let Some(diff) = rx.try_recv() {
+            state.renderable_state.apply(diff);
+        }
+        terminal.draw(|frame| view::draw(frame, &state))?;
+        if !event::poll(FRAME_TIME)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !state.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn setup_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(stdout))
+}
+
+fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::store::Store;
+    use std::sync::mpsc::channel;
+
+    fn get_control_commands(
+        rx: &std::sync::mpsc::Receiver<SimulationCommand>,
+    ) -> Vec<ControlCommand> {
+        rx.try_iter()
+            .filter_map(|command| match command {
+                SimulationCommand::Control(command) => Some(command),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn controls() {
+        let (tx, rx) = channel();
+        let mut state = TuiState::new(tx);
+        assert!(state.handle_key(KeyCode::Char(' ')));
+        assert!(state.is_paused);
+        state.handle_key(KeyCode::Char('s'));
+        state.handle_key(KeyCode::Char('+'));
+        state.handle_key(KeyCode::Char('-'));
+        state.handle_key(KeyCode::Char('-'));
+        assert_eq!(
+            get_control_commands(&rx),
+            vec![
+                ControlCommand::Pause,
+                ControlCommand::Step,
+                ControlCommand::SetSpeed(2.0),
+                ControlCommand::SetSpeed(1.0),
+                ControlCommand::SetSpeed(0.5),
+            ]
+        );
+        assert!(!state.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn select_actor() {
+        let (tx, rx) = channel();
+        let mut state = TuiState::new(tx);
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        state.renderable_state.actor_info = ids
+            .iter()
+            .map(|id| (*id, "Farmer".to_string(), Vec::new(), Store::new()))
+            .collect();
+
+        state.handle_key(KeyCode::Down);
+        assert_eq!(state.selected_actor, Some(ids[0]));
+        state.handle_key(KeyCode::Down);
+        state.handle_key(KeyCode::Down);
+        assert_eq!(state.selected_actor, Some(ids[1]));
+        state.handle_key(KeyCode::Up);
+        assert_eq!(state.selected_actor, Some(ids[0]));
+
+        let inspected = rx
+            .try_iter()
+            .filter_map(|command| match command {
+                SimulationCommand::Inspect(id) => id,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(inspected, vec![ids[0], ids[1], ids[0]]);
+    }
+}
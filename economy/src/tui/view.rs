@@ -0,0 +1,265 @@
+use super::{Tab, TuiState};
+use crate::simulation::prices::PricePoint;
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, Tabs},
+    Frame,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+const KEYS: &str =
+    "q quit  tab switch view  space pause  s step  f +100 ticks  +/- speed  1 normal  m max  \
+     up/down select  pgup/pgdn scroll log";
+
+pub(super) fn draw<B: Backend>(frame: &mut Frame<B>, state: &TuiState) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+    draw_status(frame, state, rows[0]);
+    draw_tabs(frame, state, rows[1]);
+    match state.tab {
+        Tab::Listings => draw_listings(frame, state, rows[2]),
+        Tab::Actors => draw_actors(frame, state, rows[2]),
+    }
+    let keys = Paragraph::new(KEYS).style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(keys, rows[3]);
+}
+
+///
+/// Key metrics of the latest tick.
+///
+fn draw_status<B: Backend>(frame: &mut Frame<B>, state: &TuiState, area: Rect) {
+    let renderable_state = &state.renderable_state;
+    let status = if state.is_paused { "Paused" } else { "Running" };
+    let speed = if state.speed.is_infinite() {
+        "max".to_string()
+    } else {
+        format!("{:.1}x", state.speed)
+    };
+    let listed = renderable_state
+        .listed_item_kinds
+        .iter()
+        .map(|(_, count)| count)
+        .sum::<usize>();
+    let trades = get_latest_prices(state)
+        .values()
+        .map(|point| point.trade_count)
+        .sum::<u64>();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let line = Spans::from(vec![
+        Span::styled(format!("Tick {}", renderable_state.tick), bold),
+        Span::raw(format!("  {} {}", status, speed)),
+        Span::raw(format!("  |  Actors {}", renderable_state.actor_count)),
+        Span::raw(format!("  |  Listed {}", listed)),
+        Span::raw(format!("  |  Trades this tick {}", trades)),
+    ]);
+    let paragraph = Paragraph::new(line).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_tabs<B: Backend>(frame: &mut Frame<B>, state: &TuiState, area: Rect) {
+    let titles = Tab::ALL
+        .iter()
+        .map(|tab| Spans::from(tab.get_title()))
+        .collect::<Vec<_>>();
+    let selected = Tab::ALL.iter().position(|tab| *tab == state.tab).unwrap();
+    let tabs = Tabs::new(titles)
+        .select(selected)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().fg(Color::Green));
+    frame.render_widget(tabs, area);
+}
+
+///
+/// Every listed item kind with its listing count and latest prices.
+///
+fn draw_listings<B: Backend>(frame: &mut Frame<B>, state: &TuiState, area: Rect) {
+    let latest_prices = get_latest_prices(state);
+    let listed = state
+        .renderable_state
+        .listed_item_kinds
+        .iter()
+        .map(|(item_kind, count)| (item_kind, *count))
+        .collect::<BTreeMap<_, _>>();
+    let item_kinds = listed
+        .keys()
+        .copied()
+        .chain(latest_prices.keys().copied())
+        .collect::<BTreeSet<_>>();
+    let format_price =
+        |price: Option<i64>| price.map_or("-".to_string(), |price| price.to_string());
+    let rows = item_kinds.into_iter().map(|item_kind| {
+        let point = latest_prices.get(item_kind);
+        Row::new(vec![
+            item_kind.clone(),
+            listed.get(item_kind).copied().unwrap_or(0).to_string(),
+            format_price(point.and_then(|point| point.best_bid)),
+            format_price(point.and_then(|point| point.best_ask)),
+            format_price(point.and_then(|point| point.last_trade)),
+            point.map_or(0, |point| point.trade_count).to_string(),
+        ])
+    });
+    let header = Row::new(vec![
+        "Item",
+        "Listed",
+        "Best bid",
+        "Best ask",
+        "Last trade",
+        "Trades",
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let widths = [Constraint::Ratio(1, 6); 6];
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title("Listings"))
+        .widths(&widths);
+    frame.render_widget(table, area);
+}
+
+///
+/// Actor list on the left, the store and log of the selected actor on the right.
+///
+fn draw_actors<B: Backend>(frame: &mut Frame<B>, state: &TuiState, area: Rect) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(32), Constraint::Min(0)])
+        .split(area);
+
+    let actors = &state.renderable_state.actor_info;
+    let items = actors
+        .iter()
+        .map(|(_, name, _, _)| ListItem::new(name.as_str()))
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Actors ({})", actors.len())),
+        )
+        .highlight_style(Style::default().fg(Color::Green))
+        .highlight_symbol("> ");
+    let mut list_state = ListState::default();
+    list_state.select(state.selected_actor.and_then(|id| {
+        actors
+            .iter()
+            .position(|(actor_id, _, _, _)| *actor_id == id)
+    }));
+    frame.render_stateful_widget(list, columns[0], &mut list_state);
+
+    let (name, store, log) = match get_selected_actor(state) {
+        Some(actor) => actor,
+        None => {
+            let hint = Paragraph::new("Select an actor with up and down")
+                .block(Block::default().borders(Borders::ALL));
+            frame.render_widget(hint, columns[1]);
+            return;
+        }
+    };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(store.len() as u16 + 3),
+            Constraint::Min(0),
+        ])
+        .split(columns[1]);
+
+    let store_rows = store.into_iter().map(|(item_kind, actual, target)| {
+        let target = target.map_or("-".to_string(), |target| target.to_string());
+        Row::new(vec![item_kind, actual.to_string(), target])
+    });
+    let widths = [
+        Constraint::Percentage(40),
+        Constraint::Percentage(30),
+        Constraint::Percentage(30),
+    ];
+    let table = Table::new(store_rows)
+        .header(
+            Row::new(vec!["Item", "Actual", "Target"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} store", name)),
+        )
+        .widths(&widths);
+    frame.render_widget(table, rows[0]);
+
+    let log_title = format!("Log ({} entries, newest first)", log.len());
+    let lines = log.into_iter().map(Spans::from).collect::<Vec<_>>();
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(log_title))
+        .scroll((state.log_scroll, 0));
+    frame.render_widget(paragraph, rows[1]);
+}
+
+///
+/// Name, store and log of the selected actor. The details published for the inspected actor
+/// are used once they arrive, until then the summary every actor has.
+///
+#[allow(clippy::type_complexity)]
+fn get_selected_actor(
+    state: &TuiState,
+) -> Option<(String, Vec<(String, isize, Option<isize>)>, Vec<String>)> {
+    let id = state.selected_actor?;
+    if let Some(detail) = state
+        .renderable_state
+        .actor_detail
+        .as_ref()
+        .filter(|detail| detail.id == id)
+    {
+        let item_kinds = detail
+            .store_actual
+            .keys()
+            .chain(detail.store_target.keys())
+            .collect::<BTreeSet<_>>();
+        let store = item_kinds
+            .into_iter()
+            .map(|item_kind| {
+                (
+                    item_kind.clone(),
+                    detail.store_actual.get(item_kind).copied().unwrap_or(0),
+                    Some(detail.store_target.get(item_kind).copied().unwrap_or(0)),
+                )
+            })
+            .collect();
+        return Some((detail.name.clone(), store, detail.log.clone()));
+    }
+    let (_, name, log, store) = state
+        .renderable_state
+        .actor_info
+        .iter()
+        .find(|(actor_id, _, _, _)| *actor_id == id)?;
+    let store = store
+        .get_counts()
+        .into_iter()
+        .map(|(item_kind, count)| (item_kind, count, None))
+        .collect();
+    Some((name.clone(), store, log.clone()))
+}
+
+///
+/// Prices of every item kind in the latest tick.
+///
+fn get_latest_prices(state: &TuiState) -> BTreeMap<&String, &PricePoint> {
+    state
+        .renderable_state
+        .price_history
+        .iter()
+        .filter_map(|(item_kind, history)| {
+            history
+                .back()
+                .filter(|point| point.tick == state.renderable_state.tick)
+                .map(|point| (item_kind, point))
+        })
+        .collect()
+}